@@ -0,0 +1,134 @@
+//! 轻量的采集端, 不依赖图形界面与报告导出
+//!
+//! 只扫描本机并输出 JSON 结果(格式见 `assets/report.schema.json`), 适合批量分发到大量服务器;
+//! 完整版通过"打开报告"或 `export -i` 读取其输出生成各种报告. 构建方法:
+//! `cargo build --release --no-default-features --bin sh-sds-agent`.
+
+// 与完整版共用检查相关的模块, 其中部分函数只在完整版中使用
+#![allow(dead_code)]
+
+mod util;
+mod parse;
+mod patterns;
+mod config;
+mod policy;
+mod i18n;
+mod init;
+mod sysguard;
+mod check;
+#[cfg(target_os = "macos")]
+mod macos;
+mod prereq;
+mod baseline;
+mod report;
+mod schema;
+mod lock;
+mod upload;
+mod secrets;
+mod integrity;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use report::ScanReport;
+
+#[derive(Parser)]
+#[clap(name = "sh-sds-agent", version, about = "主机安全基线检查采集端, 以 JSON 格式输出扫描结果")]
+struct Cli {
+    /// 配置文件, 默认为配置目录下的 config.toml
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// 基线策略文件, 默认为配置目录下的 policy.toml
+    #[clap(long, value_name = "FILE")]
+    policy: Option<PathBuf>,
+    /// 输出文件, 默认输出到标准输出; 配置了上传目标时写入后上传
+    #[clap(short, long, value_name = "FILE")]
+    output: Option<String>,
+    /// 以 HTTP 服务方式运行, 每次收到 GET 请求时扫描并返回 JSON 结果, 例如 127.0.0.1:8730
+    #[clap(long, value_name = "ADDR")]
+    serve: Option<String>,
+    /// 已有扫描在进行时仍然扫描
+    #[clap(long)]
+    force: bool,
+    /// 检查挂载在该目录下的系统(镜像或离线磁盘)而不是本机, 只检查基于配置文件的项目
+    #[clap(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+}
+
+fn collect(force: bool) -> Result<String, String> {
+    let _lock = lock::acquire(force)?;
+    schema::to_json(&ScanReport::scan())
+}
+
+fn respond(stream: &mut TcpStream, force: bool) {
+    // 只读取请求行, 任何路径都返回扫描结果
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).unwrap_or(0);
+    let request = String::from_utf8_lossy(&buf[..n]).to_string();
+    let (status, body) = if !request.starts_with("GET ") {
+        ("405 Method Not Allowed", "only GET is supported".to_string())
+    } else {
+        match collect(force) {
+            Ok(json) => ("200 OK", json),
+            Err(e) => ("503 Service Unavailable", e),
+        }
+    };
+    let content_type = if status.starts_with("200") { "application/json" } else { "text/plain" };
+    let response = format!(
+        "HTTP/1.0 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body,
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()) {
+        eprintln!("cannot write response: {:?}", e);
+    }
+}
+
+// 结果中包含主机的配置细节且不做鉴权, 应只监听本机或管理网地址
+fn serve(addr: &str, force: bool) -> Result<(), String> {
+    let listener = TcpListener::bind(addr).map_err(|e| format!("cannot listen on {}: {:?}", addr, e))?;
+    eprintln!("listening on {}", addr);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(mut stream) => respond(&mut stream, force),
+            Err(e) => eprintln!("cannot accept connection: {:?}", e),
+        }
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    if let Some(addr) = cli.serve {
+        return serve(&addr, cli.force);
+    }
+    let json = collect(cli.force)?;
+    match cli.output {
+        Some(output) => {
+            std::fs::write(&output, json).map_err(|e| format!("cannot write {}: {:?}", output, e))?;
+            upload::upload(Path::new(&output))
+        },
+        None => {
+            println!("{}", json);
+            Ok(())
+        },
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Some(path) = cli.config.as_ref() {
+        config::set_path(path.to_path_buf());
+    }
+    if let Some(path) = cli.policy.as_ref() {
+        policy::set_path(path.to_path_buf());
+    }
+    if let Some(root) = cli.root.as_ref() {
+        util::set_root(root.to_path_buf());
+    }
+    if let Err(e) = run(cli) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,185 @@
+//! 基线包
+//!
+//! 不同客户要求使用不同厂商(如奇安信、启明星辰)的检查表格式. 基线包是配置目录 `baselines` 下的
+//! TOML 文件, 指定要执行的检查项、报告模板以及各检查项在模板中的位置, 在 config.toml 中按名称选用.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+
+use crate::check::{self, Check};
+use crate::config;
+use crate::report::ReportHeader;
+
+static BASELINE: Lazy<Baseline> = Lazy::new(|| Baseline::load());
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Baseline {
+    pub name: String,
+    // 报告模板, 相对路径相对于 baselines 目录, 为空时使用内置的台账模板
+    pub template: String,
+    // 写入检查结果的工作表
+    pub sheet: String,
+    // 执行的检查项 ID, 为空时执行全部检查项
+    pub checks: Vec<String>,
+    // 检查项标题在模板中的行号, 未列出的检查项使用内置模板中的行号
+    pub rows: HashMap<String, u32>,
+    pub columns: Columns,
+    // 表头各字段在模板中的单元格(例如 unit = "B2"), 为空的字段只写入"报告信息"表
+    pub header: ReportHeader,
+}
+
+/// 标题、检查要求、备注三列在模板中的列
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Columns {
+    pub title: String,
+    pub result: String,
+    pub remarks: String,
+}
+
+impl Default for Baseline {
+    fn default() -> Self {
+        Baseline {
+            name: "".to_string(),
+            template: "".to_string(),
+            sheet: "工作站".to_string(),
+            checks: vec![],
+            rows: HashMap::new(),
+            columns: Columns::default(),
+            header: ReportHeader::default(),
+        }
+    }
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns {
+            title: "A".to_string(),
+            result: "B".to_string(),
+            remarks: "C".to_string(),
+        }
+    }
+}
+
+/// 基线包所在目录
+pub fn dir() -> PathBuf {
+    config::dir().join("baselines")
+}
+
+impl Baseline {
+    fn load() -> Self {
+        let name = &config::get().baseline;
+        if name.len() == 0 {
+            return Baseline::default();
+        }
+        let path = dir().join(format!("{}.toml", name));
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("cannot read baseline {}: {}", path.display(), e);
+                return Baseline::default();
+            },
+        };
+        match Baseline::parse(&text) {
+            Ok(mut baseline) => {
+                if baseline.name.len() == 0 {
+                    baseline.name = name.to_string();
+                }
+                baseline
+            },
+            Err(e) => {
+                eprintln!("cannot parse baseline {}: {}", path.display(), e);
+                Baseline::default()
+            },
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let baseline = toml::from_str::<Baseline>(text).map_err(|e| e.to_string())?;
+        for id in baseline.checks.iter().chain(baseline.rows.keys()) {
+            if check::find(id).is_none() {
+                return Err(format!("unknown check '{}'", id));
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// 按基线执行的检查项, 顺序与注册表一致
+    pub fn items(&self) -> Vec<Arc<dyn Check>> {
+        check::all().into_iter()
+            .filter(|x| self.checks.len() == 0 || self.checks.iter().any(|id| id == x.id()))
+            .collect()
+    }
+
+    /// 报告模板, 未指定时为 None
+    pub fn template(&self) -> Option<PathBuf> {
+        if self.template.len() == 0 {
+            None
+        } else {
+            Some(dir().join(&self.template))
+        }
+    }
+
+    /// 将检查结果在内置模板中的单元格位置(例如 "B9")换算为基线模板中的位置
+    pub fn position(&self, id: &str, pos: &str) -> String {
+        let idx = pos.find(|c: char| c.is_ascii_digit()).unwrap_or(pos.len());
+        let (col, row) = pos.split_at(idx);
+        let row = match row.parse::<i64>() {
+            Ok(row) => row,
+            Err(_) => return pos.to_string(),
+        };
+        let col = match col {
+            "A" => self.columns.title.as_str(),
+            "B" => self.columns.result.as_str(),
+            "C" => self.columns.remarks.as_str(),
+            _ => col,
+        };
+        // 同一检查项占多行时整体平移
+        let row = match (self.rows.get(id), check::find(id)) {
+            (Some(&base), Some(item)) => row + base as i64 - item.row() as i64,
+            _ => row,
+        };
+        format!("{}{}", col, row)
+    }
+
+    /// 表头字段在模板中的单元格及要写入的值, 不包括未指定单元格的字段
+    pub fn header_cells(&self, header: &ReportHeader) -> Vec<(String, String)> {
+        self.header.fields().into_iter()
+            .zip(header.fields())
+            .filter(|((_, pos), _)| pos.len() > 0)
+            .map(|((_, pos), (_, value))| (pos.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+pub fn get() -> &'static Baseline {
+    &BASELINE
+}
+
+#[test]
+fn test_baseline() {
+    let baseline = Baseline::default();
+    assert_eq!(check::all().len(), baseline.items().len());
+    assert_eq!("B9", baseline.position("acct", "B9"));
+
+    let text = "checks = [\"acct\", \"passwd\"]\n[rows]\nacct = 20\n[columns]\nresult = \"E\"\n";
+    let baseline = Baseline::parse(text).unwrap();
+    assert_eq!(vec!["acct", "passwd"], baseline.items().iter().map(|x| x.id()).collect::<Vec<&str>>());
+    assert_eq!("E21", baseline.position("acct", "B9"));
+    assert_eq!("A10", baseline.position("passwd", "A10"));
+    assert!(Baseline::parse("checks = [\"no.such.check\"]").is_err());
+
+    let baseline = Baseline::parse("[header]\nunit = \"B2\"\naudit_date = \"D2\"\n").unwrap();
+    let header = ReportHeader {
+        unit: "某某单位".to_string(),
+        audit_date: "2022-05-01".to_string(),
+        ..ReportHeader::default()
+    };
+    let cells = vec![("B2".to_string(), "某某单位".to_string()), ("D2".to_string(), "2022-05-01".to_string())];
+    assert_eq!(cells, baseline.header_cells(&header));
+}
@@ -0,0 +1,134 @@
+//! 检查项接口与注册表
+//!
+//! 内置检查项(`GuardItem`)与组织自行编译进来的检查项都实现 `Check` 并登记在同一个注册表中, 扫描、界面、
+//! 基线包与导出都通过注册表查找检查项. 自有检查项放在单独的模块(或依赖的 crate)中, 在扫描前调用
+//! `register` 登记即可, 无需修改 sysguard.rs.
+
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use crate::i18n;
+use crate::sysguard::{Category, GuardCell, GuardItem, ReportRow};
+use crate::util;
+
+static REGISTRY: Lazy<RwLock<Registry>> = Lazy::new(|| RwLock::new(Registry::builtin()));
+
+pub trait Check: Send + Sync {
+    /// 稳定的检查项 ID, 同时也是该项安全要求 ID 的前缀, 不能与其他检查项重复
+    fn id(&self) -> &str;
+
+    /// 检查项标题在台账中的行号, 检查结果的单元格位于该行及之后
+    fn row(&self) -> u32;
+
+    fn category(&self) -> Category;
+
+    /// 检查项标题, 默认取翻译表中的 `<id>.title`
+    fn title(&self) -> String {
+        i18n::tr(&format!("{}.title", self.id())).to_string()
+    }
+
+    /// 依赖运行中的系统(进程、网络、内核状态、日志或命令)的检查项, 检查备用根目录时跳过
+    fn live_only(&self) -> bool {
+        false
+    }
+
+    /// 执行检查, 返回各条安全要求的结果及备注; 标题由 `title` 提供
+    fn run(&self) -> ReportRow;
+}
+
+/// 按登记顺序排列的检查项, 内置检查项在前
+pub struct Registry {
+    checks: Vec<Arc<dyn Check>>,
+}
+
+impl Registry {
+    fn builtin() -> Self {
+        Registry {
+            checks: GuardItem::all().into_iter().map(|x| Arc::new(x) as Arc<dyn Check>).collect(),
+        }
+    }
+
+    pub fn register(&mut self, check: Box<dyn Check>) -> Result<(), String> {
+        if self.checks.iter().any(|x| x.id() == check.id()) {
+            return Err(format!("duplicate check '{}'", check.id()));
+        }
+        self.checks.push(Arc::from(check));
+        Ok(())
+    }
+
+    pub fn all(&self) -> Vec<Arc<dyn Check>> {
+        self.checks.clone()
+    }
+
+    pub fn find(&self, id: &str) -> Option<Arc<dyn Check>> {
+        self.checks.iter().find(|x| x.id() == id).cloned()
+    }
+}
+
+/// 登记一个检查项, 之后的扫描中排在已有检查项之后; 需在读取基线包之前调用
+pub fn register(check: Box<dyn Check>) -> Result<(), String> {
+    REGISTRY.write().unwrap().register(check)
+}
+
+/// 所有已登记的检查项
+pub fn all() -> Vec<Arc<dyn Check>> {
+    REGISTRY.read().unwrap().all()
+}
+
+pub fn find(id: &str) -> Option<Arc<dyn Check>> {
+    REGISTRY.read().unwrap().find(id)
+}
+
+/// 执行一个检查项并记录其证据来源; 检查备用根目录时跳过依赖运行中系统的检查项, 只输出标题及说明
+pub fn run(check: &dyn Check) -> GuardCell {
+    util::begin_sources();
+    let row = match (util::root(), check.live_only()) {
+        (Some(root), true) => ReportRow::new(check.id())
+            .evidence(format!("检查备用根目录 {} 时跳过, 该项依赖运行中的系统", root.display())),
+        _ => check.run(),
+    };
+    let sources = util::end_sources();
+    let mut cell = row.into_cell(check.row(), &check.title());
+    cell.sources = sources;
+    cell
+}
+
+#[test]
+fn test_register() {
+    use crate::sysguard::Req;
+
+    struct Motd;
+    impl Check for Motd {
+        fn id(&self) -> &str {
+            "site.motd"
+        }
+        fn row(&self) -> u32 {
+            60
+        }
+        fn category(&self) -> Category {
+            Category::System
+        }
+        fn title(&self) -> String {
+            "登录提示信息".to_string()
+        }
+        fn run(&self) -> ReportRow {
+            let _ = crate::parse::read_lines("/dev/null");
+            ReportRow::new(self.id()).status(Req::from("site.motd.banner"), Some(true))
+        }
+    }
+
+    let mut registry = Registry::builtin();
+    assert!(registry.register(Box::new(Motd)).is_ok());
+    assert!(registry.register(Box::new(Motd)).is_err());
+    assert!(registry.register(Box::new(GuardItem::OS)).is_err());
+    assert_eq!(GuardItem::all().len() + 1, registry.all().len());
+    assert_eq!("site.motd", registry.all().last().unwrap().id());
+
+    let cell = run(registry.find("site.motd").unwrap().as_ref());
+    assert_eq!("site.motd", cell.id);
+    assert_eq!("登录提示信息", cell.get("A60"));
+    assert_eq!("[✓]site.motd.banner", cell.get("B60"));
+    assert_eq!(vec![("site.motd.banner".to_string(), Some(true))], cell.reqs);
+    assert_eq!(vec!["/dev/null".to_string()], cell.sources);
+}
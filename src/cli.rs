@@ -0,0 +1,791 @@
+//! 命令行参数及批处理子命令
+//!
+//! 不带子命令运行时启动图形界面, 其余子命令用于脚本与自动化场景.
+
+use std::path::{Path, PathBuf};
+
+use clap::{ArgEnum, Parser, Subcommand};
+use serde::Serialize;
+
+use crate::baseline;
+use crate::check;
+use crate::export;
+use crate::i18n;
+use crate::integrity;
+use crate::report::ScanReport;
+use crate::schema::Status;
+use crate::secrets;
+use crate::store;
+use crate::sysguard::{self, Category};
+use crate::tui;
+use crate::upload;
+use crate::util;
+
+#[derive(Parser)]
+#[clap(name = "sysguard", version, about = "主机安全基线检查")]
+pub struct Cli {
+    /// 配置文件, 默认为配置目录下的 config.toml
+    #[clap(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// 基线策略文件, 默认为配置目录下的 policy.toml
+    #[clap(long, global = true, value_name = "FILE")]
+    pub policy: Option<PathBuf>,
+    /// 日志级别, verbose 时逐条输出执行的命令、耗时与退出状态
+    #[clap(long, global = true, arg_enum, default_value = "normal")]
+    pub log_level: LogLevel,
+    /// 已有扫描在进行时仍然扫描
+    #[clap(long, global = true)]
+    pub force: bool,
+    /// 使用内置的演示数据, 不扫描本机
+    #[clap(long, global = true)]
+    pub demo: bool,
+    /// 检查挂载在该目录下的系统(镜像或离线磁盘)而不是本机, 只检查基于配置文件的项目
+    #[clap(long, global = true, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+    /// 在 Wayland 会话中强制界面及文件对话框使用 X11(XWayland)后端
+    #[clap(long, global = true)]
+    pub x11: bool,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Clone, Copy, PartialEq, ArgEnum)]
+pub enum LogLevel {
+    Normal,
+    Verbose,
+}
+
+#[derive(Clone, Copy, PartialEq, ArgEnum)]
+pub enum Format {
+    Xlsx,
+    Gbt22239,
+    Docx,
+    Json,
+    Bundle,
+    Yaml,
+    Csv,
+}
+
+impl Category {
+    // 多个分类存在不符合项时退出码按位或, 1 保留给执行错误
+    fn exit_code(&self) -> i32 {
+        match self {
+            Category::Account => 2,
+            Category::Network => 4,
+            Category::Audit => 8,
+            Category::System => 16,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ArgEnum)]
+pub enum DiffFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ArgEnum)]
+pub enum SchemaFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Subcommand)]
+pub enum BaselineAction {
+    /// 计算策略 [integrity] 中关键路径下各文件的 SHA-256, 保存为新的完整性基线
+    Create,
+    /// 与完整性基线比较, 列出内容变化、缺失及新增的文件; 存在变化时以 2 退出
+    Verify,
+}
+
+#[derive(Subcommand)]
+pub enum SecretAction {
+    /// 保存口令, 名称为 upload.password 或 mail.password; 口令在终端中输入, 首次保存时设置主口令
+    Set { name: String },
+    /// 删除保存的口令
+    Remove { name: String },
+    /// 列出已保存口令的名称
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 终端界面
+    Tui,
+    /// 扫描本机并输出检查结果概要, 可同时导出报告
+    Scan {
+        /// 导出的文件
+        #[clap(short, long, value_name = "FILE")]
+        output: Option<String>,
+        #[clap(short, long, arg_enum, default_value = "xlsx")]
+        format: Format,
+        /// 指定分类存在不符合项时以非 0 退出: account 2, network 4, audit 8, system 16, 多个分类按位或
+        #[clap(long, arg_enum, value_name = "CATEGORY", use_value_delimiter = true)]
+        fail_on: Vec<Category>,
+        /// 只执行指定的检查项并输出其详细结果, 可以是检查项 ID(如 passwd)或安全要求 ID(如 passwd.min_len)
+        #[clap(long, value_name = "ID", use_value_delimiter = true)]
+        only: Vec<String>,
+        /// 不执行指定的检查项, 为安全要求 ID 时只是不输出该要求
+        #[clap(long, value_name = "ID", use_value_delimiter = true)]
+        skip: Vec<String>,
+    },
+    /// 将保存的报告或结果数据库中的扫描导出为其他格式
+    Export {
+        /// JSON 报告文件; 不指定时从结果数据库中读取
+        #[clap(short, long, value_name = "FILE")]
+        input: Option<String>,
+        /// 结果数据库中的扫描 ID, 默认为最近一次扫描
+        #[clap(long, value_name = "ID")]
+        scan: Option<i64>,
+        #[clap(short, long, value_name = "FILE")]
+        output: String,
+        #[clap(short, long, arg_enum, default_value = "xlsx")]
+        format: Format,
+        /// 只列出 xlsx 模板中将被写入的单元格及其原值与新值, 不写入文件
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// 扫描本机并以 JSON 格式输出结果, 用于集中汇总
+    Collect {
+        /// 输出文件, 默认输出到标准输出
+        #[clap(short, long, value_name = "FILE")]
+        output: Option<String>,
+        /// 同 scan 的 --fail-on
+        #[clap(long, arg_enum, value_name = "CATEGORY", use_value_delimiter = true)]
+        fail_on: Vec<Category>,
+    },
+    /// 比较两次扫描中安全要求检查结果的变化, 参数为 JSON 报告文件或结果数据库中的扫描 ID
+    Diff {
+        old: String,
+        new: String,
+        /// json 时输出变化集, 包括恶化、修复、其他变化及未变化的要求与前后的证据
+        #[clap(short, long, arg_enum, default_value = "text")]
+        format: DiffFormat,
+    },
+    /// 检查虚拟机镜像(qcow2/raw 等)或已挂载的镜像目录, 输出随镜像分发的合规清单(JSON)
+    ImageAudit {
+        /// 镜像文件, 未指定 --mountpoint 时通过 guestmount(libguestfs)只读挂载; 清单中记录其 SHA-256
+        image: Option<String>,
+        /// 已挂载的镜像根目录, 指定时不再挂载镜像文件
+        #[clap(long, value_name = "DIR")]
+        mountpoint: Option<PathBuf>,
+        /// 输出文件, 默认输出到标准输出
+        #[clap(short, long, value_name = "FILE")]
+        output: Option<String>,
+        /// 同 scan 的 --fail-on
+        #[clap(long, arg_enum, value_name = "CATEGORY", use_value_delimiter = true)]
+        fail_on: Vec<Category>,
+    },
+    /// 监视账户、sshd、sudo 及 PAM 配置文件, 变化后重新执行相关检查项, 原先符合的要求变为不符合时输出日志并发送桌面通知(仅 Linux)
+    Watch,
+    /// 汇总各主机自上次摘要以来合规率的变化、新增的不符合要求及已修复的要求, 通过邮件发送; 由每周的定时任务执行
+    Digest {
+        /// 只输出邮件内容, 不发送, 也不记录为已发送
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// 输出由当前版本代码生成的 JSON 结果的 JSON Schema 或 CSV 结果的列定义, 供编写解析程序时参考
+    Schema {
+        #[clap(short, long, arg_enum, default_value = "json")]
+        format: SchemaFormat,
+    },
+    /// 关键程序及配置文件的完整性基线, 保存在数据目录下
+    Baseline {
+        #[clap(subcommand)]
+        action: BaselineAction,
+    },
+    /// 上传及发送邮件使用的口令, 加密保存在配置目录下的 secrets.age 中
+    Secret {
+        #[clap(subcommand)]
+        action: SecretAction,
+    },
+}
+
+impl Command {
+    /// 是否会扫描本机, 扫描时需要持有扫描锁
+    pub fn scans(&self) -> bool {
+        match self {
+            Command::Tui | Command::Scan { .. } | Command::Collect { .. } => true,
+            // watch 每次只重新执行少数检查项, 不持有扫描锁, 以免阻塞定时扫描
+            Command::Export { .. } | Command::Diff { .. } | Command::ImageAudit { .. } | Command::Watch
+                | Command::Digest { .. } | Command::Schema { .. } | Command::Baseline { .. } | Command::Secret { .. } => false,
+        }
+    }
+}
+
+fn exporter(format: Format) -> fn(&ScanReport, String) -> Result<String, String> {
+    match format {
+        Format::Xlsx => export::saveas,
+        Format::Gbt22239 => export::saveas_gbt22239,
+        Format::Docx => export::saveas_docx,
+        Format::Json => export::saveas_json,
+        Format::Bundle => export::saveas_bundle,
+        Format::Yaml => export::saveas_yaml,
+        Format::Csv => export::saveas_csv,
+    }
+}
+
+// 导出后按配置上传到审计文件服务器
+fn save(format: Format, report: &ScanReport, output: String) -> Result<(), String> {
+    let path = exporter(format)(report, output)?;
+    upload::upload(Path::new(&path))
+}
+
+// 列出导出 xlsx 时模板中将被写入的单元格, 值中的换行等以转义形式显示
+fn preview(format: Format, report: &ScanReport, output: String) -> Result<(), String> {
+    let with_gbt22239 = match format {
+        Format::Xlsx => false,
+        Format::Gbt22239 => true,
+        _ => return Err("--dry-run 只适用于 xlsx 与 gbt22239 格式".to_string()),
+    };
+    let changes = export::preview_xlsx(report, output.clone())?;
+    for change in changes.iter() {
+        let mark = if change.old == change.new { "(不变)" } else { "" };
+        println!("{}!{}: {:?} -> {:?} {}", change.sheet, change.pos, change.old, change.new, mark);
+    }
+    println!("共写入 {} 个单元格, 另新增工作表: {}", changes.len(), export::added_sheets(with_gbt22239).join(", "));
+    let path = export::with_extension(output, "xlsx");
+    if Path::new(&path).exists() {
+        println!("将覆盖已存在的文件 {}", path);
+    }
+    Ok(())
+}
+
+fn scan(demo: bool) -> ScanReport {
+    if demo {
+        return ScanReport::demo();
+    }
+    let report = ScanReport::scan();
+    store::record(&report);
+    report
+}
+
+// 参数为数字时从结果数据库中读取扫描, 否则作为 JSON 报告文件读取
+fn load(arg: &str) -> Result<ScanReport, String> {
+    if let Ok(id) = arg.parse::<i64>() {
+        return load_scan(Some(id));
+    }
+    let text = std::fs::read_to_string(arg).map_err(|e| format!("cannot read {}: {:?}", arg, e))?;
+    ScanReport::from_json(&text)
+}
+
+fn load_scan(id: Option<i64>) -> Result<ScanReport, String> {
+    let path = store::path();
+    let store = store::Store::open(&path).map_err(|e| format!("{:?}", e))?;
+    let id = match id {
+        Some(id) => id,
+        None => match store.scans(None).map_err(|e| format!("{:?}", e))?.first() {
+            Some(scan) => scan.id,
+            None => return Err(format!("{} 中没有扫描记录", path.display())),
+        },
+    };
+    match store.load(id).map_err(|e| format!("{:?}", e))? {
+        Some(report) => Ok(report),
+        None => Err(format!("扫描 {} 不存在", id)),
+    }
+}
+
+/// `scan --only/--skip` 选择的检查项与安全要求, 安全要求 ID 以所属检查项的 ID 加 "." 开头
+struct Filter {
+    only: Vec<String>,
+    skip: Vec<String>,
+}
+
+impl Filter {
+    fn new(only: Vec<String>, skip: Vec<String>) -> Result<Self, String> {
+        for id in only.iter().chain(skip.iter()) {
+            if Self::owner(id).is_none() {
+                return Err(format!("unknown check or requirement '{}'", id));
+            }
+        }
+        Ok(Filter { only, skip })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.only.len() == 0 && self.skip.len() == 0
+    }
+
+    // 检查项或安全要求 ID 所属的检查项
+    fn owner(id: &str) -> Option<String> {
+        check::all().iter()
+            .map(|x| x.id().to_string())
+            .filter(|x| id == x || id.starts_with(&format!("{}.", x)))
+            .max_by_key(|x| x.len())
+    }
+
+    fn check(&self, id: &str) -> bool {
+        (self.only.len() == 0 || self.only.iter().any(|x| Self::owner(x).as_deref() == Some(id)))
+            && !self.skip.iter().any(|x| x == id)
+    }
+
+    fn req(&self, id: &str) -> bool {
+        let matches = |x: &String| x == id || id.starts_with(&format!("{}.", x));
+        (self.only.len() == 0 || self.only.iter().any(matches)) && !self.skip.iter().any(matches)
+    }
+}
+
+fn mark(status: Status) -> &'static str {
+    match status {
+        Status::Pass => "✓",
+        Status::Fail => "✗",
+        Status::Manual => " ",
+        Status::Partial => "◐",
+    }
+}
+
+fn summary(report: &ScanReport, filter: &Filter) {
+    println!("主机: {}  扫描时间: {} - {}", report.meta.hostname, report.meta.started_at, report.meta.finished_at);
+    println!("网络功能: {}", sysguard::network_features().join(", "));
+    for item in report.meta.missing.iter() {
+        println!("缺少依赖: {}", item);
+    }
+    for cell in report.cells.iter() {
+        for (id, status) in cell.statuses().into_iter().filter(|x| filter.req(&x.0)) {
+            println!("[{}] {:<24} {}", mark(status), id, i18n::tr(&id));
+        }
+    }
+}
+
+// 选中的安全要求及所在检查项采集的信息与证据, 用于整改后快速复核
+fn detail(report: &ScanReport, filter: &Filter) {
+    println!("主机: {}  扫描时间: {} - {}", report.meta.hostname, report.meta.started_at, report.meta.finished_at);
+    for cell in report.cells.iter() {
+        println!("\n== {} ({}) ==", cell.column('A').join(" "), cell.id);
+        let statuses = cell.statuses().into_iter().filter(|x| filter.req(&x.0)).collect::<Vec<(String, Status)>>();
+        for (id, status) in statuses.iter() {
+            println!("[{}] {:<24} {}", mark(*status), id, i18n::tr(id));
+        }
+        // 不含安全要求的检查项(如操作系统、IP)只采集信息
+        if cell.reqs.len() == 0 {
+            for line in cell.column('B') {
+                println!("{}", line);
+            }
+        }
+        for id in filter.only.iter().filter(|x| **x != cell.id && Filter::owner(x).as_deref() == Some(cell.id.as_str())) {
+            if !statuses.iter().any(|x| x.0 == *id || x.0.starts_with(&format!("{}.", id))) {
+                println!("未找到安全要求 {}", id);
+            }
+        }
+        for text in cell.column('C') {
+            println!("备注: {}", text.replace("\n", "\n      "));
+        }
+        if cell.sources.len() > 0 {
+            println!("来源: {}", cell.sources.join(", "));
+        }
+    }
+}
+
+// 按 --only/--skip 执行部分检查项; 结果不完整, 不写入结果数据库
+fn scan_filtered(demo: bool, filter: &Filter) -> Result<ScanReport, String> {
+    let mut report = if demo {
+        ScanReport::demo()
+    } else {
+        ScanReport::scan_checks(baseline::get().items().into_iter().filter(|x| filter.check(x.id())).collect())
+    };
+    report.cells.retain(|x| filter.check(&x.id));
+    if report.cells.len() == 0 {
+        return Err("没有选中当前基线中的任何检查项".to_string());
+    }
+    Ok(report)
+}
+
+/// 按 `--fail-on` 指定的分类计算退出码
+fn exit_code(report: &ScanReport, fail_on: &[Category]) -> i32 {
+    let mut code = 0;
+    for cell in report.cells.iter() {
+        let category = match check::find(&cell.id) {
+            Some(item) => item.category(),
+            None => continue,
+        };
+        if fail_on.contains(&category) && cell.reqs.iter().any(|x| x.1 == Some(false)) {
+            code |= category.exit_code();
+        }
+    }
+    code
+}
+
+/// 一项安全要求在两次扫描之间的状态, 不存在时为 None
+#[derive(Serialize)]
+struct Change {
+    id: String,
+    title: String,
+    old: Option<Status>,
+    new: Option<Status>,
+    old_evidence: String,
+    new_evidence: String,
+}
+
+// 要求所在检查项的备注列, 即其证据
+fn evidence(report: &ScanReport, id: &str) -> String {
+    let cell = match report.cells.iter().find(|x| x.reqs.iter().any(|r| r.0 == id)) {
+        Some(cell) => cell,
+        None => return "".to_string(),
+    };
+    let mut evidence = cell.mp.iter().filter(|(k, _)| k.starts_with("C")).collect::<Vec<(&String, &String)>>();
+    evidence.sort();
+    evidence.into_iter().map(|(_, v)| v.as_str()).collect::<Vec<&str>>().join("\n")
+}
+
+// 按新报告中的顺序列出所有要求, 其后为只在旧报告中出现的要求
+fn changes(old: &ScanReport, new: &ScanReport) -> Vec<Change> {
+    let statuses = |report: &ScanReport| {
+        report.cells.iter()
+            .flat_map(|x| x.statuses())
+            .collect::<Vec<(String, Status)>>()
+    };
+    let old_statuses = statuses(old);
+    let new_statuses = statuses(new);
+    let status = |statuses: &[(String, Status)], id: &str| statuses.iter().find(|x| x.0 == id).map(|x| x.1);
+
+    let mut ids = new_statuses.iter().map(|x| x.0.to_string()).collect::<Vec<String>>();
+    ids.extend(old_statuses.iter().filter(|x| status(&new_statuses, &x.0).is_none()).map(|x| x.0.to_string()));
+    ids.into_iter()
+        .map(|id| Change {
+            title: i18n::tr(&id).to_string(),
+            old: status(&old_statuses, &id),
+            new: status(&new_statuses, &id),
+            old_evidence: evidence(old, &id),
+            new_evidence: evidence(new, &id),
+            id,
+        })
+        .collect()
+}
+
+fn diff(old: &ScanReport, new: &ScanReport) {
+    let name = |status: Option<Status>| match status {
+        Some(Status::Pass) => "pass",
+        Some(Status::Fail) => "fail",
+        Some(Status::Manual) => "manual",
+        Some(Status::Partial) => "partial",
+        None => "-",
+    };
+
+    println!("{} ({}) -> {} ({})", old.meta.hostname, old.meta.started_at, new.meta.hostname, new.meta.started_at);
+    let mut changed = 0;
+    for change in changes(old, new).into_iter().filter(|x| x.old != x.new) {
+        changed += 1;
+        println!("{:<24} {:>6} -> {:<6} {}", change.id, name(change.old), name(change.new), change.title);
+    }
+    println!("共 {} 项变化", changed);
+}
+
+// 新结果为不符合的为恶化, 由不符合变为符合的为修复, 其余状态变化(人工核查、新增或删除的要求)归为其他变化
+fn diff_json(old: &ScanReport, new: &ScanReport) -> Result<String, String> {
+    let mut regressed = vec![];
+    let mut fixed = vec![];
+    let mut changed = vec![];
+    let mut unchanged = vec![];
+    for change in changes(old, new) {
+        if change.old == change.new {
+            unchanged.push(change);
+        } else if change.new == Some(Status::Fail) {
+            regressed.push(change);
+        } else if change.old == Some(Status::Fail) && (change.new == Some(Status::Pass) || change.new == Some(Status::Partial)) {
+            fixed.push(change);
+        } else {
+            changed.push(change);
+        }
+    }
+    let scan = |report: &ScanReport| serde_json::json!({
+        "hostname": report.meta.hostname,
+        "started_at": report.meta.started_at,
+    });
+    let value = serde_json::json!({
+        "old": scan(old),
+        "new": scan(new),
+        "regressed": regressed,
+        "fixed": fixed,
+        "changed": changed,
+        "unchanged": unchanged,
+    });
+    serde_json::to_string_pretty(&value).map_err(|e| format!("cannot serialize diff: {:?}", e))
+}
+
+// 执行命令并返回标准输出; 镜像路径作为单独的参数传递, 其中可以含有引号或空格
+fn run_image_tool(cmd: &mut std::process::Command) -> Result<String, String> {
+    let output = util::output(cmd).map_err(|e| format!("{:?}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// 只读挂载镜像, 返回挂载目录; 目录在返回值释放时删除, 需先卸载
+fn mount_image(image: &str) -> Result<tempfile::TempDir, String> {
+    let dir = tempfile::tempdir().map_err(|e| format!("cannot create temporary directory: {:?}", e))?;
+    run_image_tool(std::process::Command::new("guestmount").args(["-a", image, "-i", "--ro"]).arg(dir.path()))
+        .map_err(|e| format!("cannot mount {} with guestmount: {}", image, e))?;
+    Ok(dir)
+}
+
+/// 镜像合规清单, `image` 为镜像文件, 只检查挂载目录时为空
+fn image_manifest(report: &ScanReport, image: Option<&str>) -> Result<String, String> {
+    let image = match image {
+        Some(image) => {
+            let size = std::fs::metadata(image).map_err(|e| format!("cannot read {}: {:?}", image, e))?.len();
+            let sha256 = run_image_tool(std::process::Command::new("sha256sum").arg(image))
+                .map_err(|e| format!("cannot hash {}: {}", image, e))?
+                .split_whitespace().next().unwrap_or("").to_string();
+            let file = Path::new(image).file_name().map_or(image.to_string(), |x| x.to_string_lossy().to_string());
+            serde_json::json!({ "file": file, "size": size, "sha256": sha256 })
+        },
+        None => serde_json::Value::Null,
+    };
+    let (mut passed, mut manual, mut partial, mut failed) = (0, 0, 0, vec![]);
+    for cell in report.cells.iter() {
+        for (id, status) in cell.statuses() {
+            match status {
+                Status::Pass => passed += 1,
+                Status::Fail => failed.push(id),
+                Status::Manual => manual += 1,
+                Status::Partial => partial += 1,
+            }
+        }
+    }
+    let skipped = report.cells.iter()
+        .filter(|x| check::find(&x.id).map_or(false, |x| x.live_only()))
+        .map(|x| x.id.as_str())
+        .collect::<Vec<&str>>();
+    let value = serde_json::json!({
+        "manifest_version": 1,
+        "image": image,
+        "hostname": report.meta.hostname,
+        "tool_version": report.meta.version,
+        "scanned_at": report.meta.finished_at,
+        "compliant": failed.len() == 0,
+        "summary": { "pass": passed, "fail": failed.len(), "manual": manual, "partial": partial },
+        "failed": failed,
+        "skipped": skipped,
+        "report": crate::schema::Report::from(report),
+    });
+    serde_json::to_string_pretty(&value).map_err(|e| format!("cannot serialize manifest: {:?}", e))
+}
+
+fn image_audit(image: Option<String>, mountpoint: Option<PathBuf>, demo: bool) -> Result<(ScanReport, String), String> {
+    if demo {
+        let report = ScanReport::demo();
+        let manifest = image_manifest(&report, None)?;
+        return Ok((report, manifest));
+    }
+    if util::root().is_some() {
+        return Err("image-audit 不能与 --root 同时使用, 请使用 --mountpoint".to_string());
+    }
+    let (root, mounted) = match (mountpoint, image.as_ref()) {
+        (Some(mountpoint), _) => (mountpoint, None),
+        (None, Some(image)) => {
+            let dir = mount_image(image)?;
+            (dir.path().to_path_buf(), Some(dir))
+        },
+        (None, None) => return Err("需要指定镜像文件或 --mountpoint".to_string()),
+    };
+    util::set_root(root);
+    // 镜像不是本机, 结果不写入结果数据库
+    let report = ScanReport::scan();
+    if let Some(dir) = mounted.as_ref() {
+        if let Err(e) = run_image_tool(std::process::Command::new("guestunmount").arg(dir.path())) {
+            eprintln!("cannot unmount {}: {}", dir.path().display(), e);
+        }
+    }
+    let manifest = image_manifest(&report, image.as_deref())?;
+    Ok((report, manifest))
+}
+
+/// 执行图形界面以外的子命令, 返回进程的退出码
+pub fn run(command: Command, demo: bool) -> Result<i32, String> {
+    match command {
+        Command::Tui => tui::run(demo).map_err(|e| format!("{:?}", e))?,
+        Command::Scan { output, format, fail_on, only, skip } => {
+            let filter = Filter::new(only, skip)?;
+            let report = if filter.is_empty() { scan(demo) } else { scan_filtered(demo, &filter)? };
+            if filter.only.len() > 0 {
+                detail(&report, &filter);
+            } else {
+                summary(&report, &filter);
+            }
+            if let Some(output) = output {
+                save(format, &report, output)?;
+            }
+            return Ok(exit_code(&report, &fail_on));
+        },
+        Command::Export { input, scan, output, format, dry_run } => {
+            let mut report = match input {
+                Some(input) => load(&input)?,
+                None => load_scan(scan)?,
+            };
+            report.meta.header = crate::report::default_header(&report.meta);
+            if let Some(warning) = report.stale_warning() {
+                eprintln!("[!] {}", warning);
+            }
+            if dry_run {
+                preview(format, &report, output)?;
+            } else {
+                save(format, &report, output)?;
+            }
+        },
+        Command::Collect { output, fail_on } => {
+            let report = scan(demo);
+            match output {
+                Some(output) => save(Format::Json, &report, output)?,
+                None => println!("{}", crate::schema::to_json(&report)?),
+            }
+            return Ok(exit_code(&report, &fail_on));
+        },
+        Command::ImageAudit { image, mountpoint, output, fail_on } => {
+            let (report, manifest) = image_audit(image, mountpoint, demo)?;
+            match output {
+                Some(output) => {
+                    std::fs::write(&output, manifest).map_err(|e| format!("cannot write {}: {:?}", output, e))?;
+                    upload::upload(Path::new(&output))?;
+                },
+                None => println!("{}", manifest),
+            }
+            return Ok(exit_code(&report, &fail_on));
+        },
+        Command::Diff { old, new, format } => {
+            let (old, new) = (load(&old)?, load(&new)?);
+            match format {
+                DiffFormat::Text => diff(&old, &new),
+                DiffFormat::Json => println!("{}", diff_json(&old, &new)?),
+            }
+        },
+        Command::Watch => {
+            if demo || util::root().is_some() {
+                return Err("watch 只能监视本机, 不能与 --demo 或 --root 同时使用".to_string());
+            }
+            #[cfg(target_os = "linux")]
+            crate::watch::run()?;
+            #[cfg(not(target_os = "linux"))]
+            return Err("watch 只支持 Linux".to_string());
+        },
+        Command::Digest { dry_run } => crate::digest::run(dry_run)?,
+        Command::Schema { format } => match format {
+            SchemaFormat::Json => {
+                let schema = crate::schema::json_schema();
+                println!("{}", serde_json::to_string_pretty(&schema).map_err(|e| format!("cannot serialize schema: {:?}", e))?);
+            },
+            SchemaFormat::Csv => {
+                println!("column,description");
+                for (name, description) in crate::schema::CSV_COLUMNS {
+                    println!("{},\"{}\"", name, description.replace("\"", "\"\""));
+                }
+            },
+        },
+        Command::Baseline { action } => {
+            if demo || util::root().is_some() {
+                return Err("完整性基线只适用于本机, 不能与 --demo 或 --root 同时使用".to_string());
+            }
+            match action {
+                BaselineAction::Create => {
+                    let baseline = integrity::create()?;
+                    println!("已记录 {} 个文件到 {}", baseline.files.len(), integrity::path().display());
+                },
+                BaselineAction::Verify => {
+                    let baseline = integrity::load()?
+                        .ok_or(format!("尚未建立完整性基线 {}, 请先运行 baseline create", integrity::path().display()))?;
+                    let changes = integrity::verify(&baseline);
+                    println!("基线建立于 {}, 共 {} 个文件", baseline.created_at, baseline.files.len());
+                    for (label, files) in [("内容变化", &changes.changed), ("缺失", &changes.missing), ("新增", &changes.added)] {
+                        for file in files.iter() {
+                            println!("{} {}", label, file);
+                        }
+                    }
+                    if !changes.is_empty() {
+                        return Ok(2);
+                    }
+                    println!("没有变化");
+                },
+            }
+        },
+        Command::Secret { action } => match action {
+            SecretAction::Set { name } => {
+                let value = rpassword::prompt_password(format!("{}: ", name)).map_err(|e| format!("cannot read {}: {:?}", name, e))?;
+                secrets::set(&name, &value)?;
+                println!("已保存 {} 到 {}", name, secrets::path().display());
+            },
+            SecretAction::Remove { name } => {
+                if !secrets::remove(&name)? {
+                    return Err(format!("{} 中没有 {}", secrets::path().display(), name));
+                }
+            },
+            SecretAction::List => {
+                for name in secrets::names()? {
+                    println!("{}", name);
+                }
+            },
+        },
+    }
+    Ok(0)
+}
+
+/// 应用全局参数, 需在读取配置与策略之前调用
+pub fn setup(cli: &Cli) {
+    if let Some(path) = cli.config.as_ref() {
+        crate::config::set_path(path.to_path_buf());
+    }
+    if let Some(path) = cli.policy.as_ref() {
+        crate::policy::set_path(path.to_path_buf());
+    }
+    util::set_verbose(cli.log_level == LogLevel::Verbose);
+    if let Some(root) = cli.root.as_ref() {
+        util::set_root(root.to_path_buf());
+    }
+}
+
+#[test]
+fn test_exit_code() {
+    let report = ScanReport::demo();
+    assert_eq!(0, exit_code(&report, &[]));
+    assert_eq!(4, exit_code(&report, &[Category::Network]));
+    let all = [Category::Account, Category::Network, Category::Audit, Category::System];
+    assert_eq!(2 | 4 | 8 | 16, exit_code(&report, &all));
+}
+
+#[test]
+fn test_filter() {
+    let filter = Filter::new(vec!["passwd.min_len".to_string(), "net.port".to_string()], vec![]).unwrap();
+    assert!(filter.check("passwd") && filter.check("net.port"));
+    assert!(!filter.check("acct") && !filter.check("net.ip"));
+    assert!(filter.req("passwd.min_len") && filter.req("net.port.22"));
+    assert!(!filter.req("passwd.max_days"));
+
+    let filter = Filter::new(vec![], vec!["net.port".to_string(), "passwd.min_len".to_string()]).unwrap();
+    assert!(!filter.check("net.port") && filter.check("passwd"));
+    assert!(!filter.req("passwd.min_len") && filter.req("passwd.max_days"));
+    assert!(Filter::new(vec!["no.such".to_string()], vec![]).is_err());
+}
+
+#[test]
+fn test_diff_json() {
+    let old = ScanReport::demo();
+    let mut new = ScanReport::demo();
+    for cell in new.cells.iter_mut() {
+        for req in cell.reqs.iter_mut() {
+            if req.0 == "passwd.min_len" {
+                req.1 = Some(false);
+            }
+            if req.0 == "passwd.max_days" {
+                req.1 = Some(true);
+            }
+        }
+    }
+    let value: serde_json::Value = serde_json::from_str(&diff_json(&old, &new).unwrap()).unwrap();
+    assert_eq!("passwd.min_len", value["regressed"][0]["id"]);
+    assert_eq!("pass", value["regressed"][0]["old"]);
+    assert_eq!("passwd.max_days", value["fixed"][0]["id"]);
+    assert!(value["fixed"][0]["new_evidence"].as_str().unwrap().contains("PASS_MAX_DAYS"));
+    assert_eq!(0, value["changed"].as_array().unwrap().len());
+}
+
+#[test]
+fn test_image_manifest() {
+    let report = ScanReport::demo();
+    let manifest = image_manifest(&report, None).unwrap();
+    let value = serde_json::from_str::<serde_json::Value>(&manifest).unwrap();
+    assert!(value["image"].is_null());
+    assert_eq!(false, value["compliant"]);
+    let total = report.cells.iter().map(|x| x.reqs.len() as u64).sum::<u64>();
+    let summary = &value["summary"];
+    assert_eq!(total, summary["pass"].as_u64().unwrap() + summary["fail"].as_u64().unwrap() + summary["manual"].as_u64().unwrap() + summary["partial"].as_u64().unwrap());
+    assert!(value["skipped"].as_array().unwrap().iter().any(|x| x == "net.port"));
+    assert_eq!(report.cells.len(), value["report"]["checks"].as_array().unwrap().len());
+}
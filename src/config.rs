@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Serialize, Deserialize};
+
+use crate::report::ReportHeader;
+use crate::util;
+
+// Excel 单元格最多能容纳 32767 个字符
+pub const XLSX_CELL_MAX_CHARS: usize = 32767;
+
+static CONFIG: Lazy<Config> = Lazy::new(|| Config::load());
+// 命令行指定的配置文件, 需在首次读取配置前设置
+static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // 报告中记录的操作人员, 为空时使用当前登录用户
+    pub operator: String,
+    // 使用的基线包名称, 对应配置目录下的 baselines/<名称>.toml, 为空时使用内置基线
+    pub baseline: String,
+    // 报告表头中单位名称、资产编号、责任人及检查日期的默认值, 检查日期为空时使用扫描日期
+    pub header: ReportHeader,
+    pub evidence: EvidenceConfig,
+    pub export: ExportConfig,
+    pub ui: UiConfig,
+    pub store: StoreConfig,
+    pub network: NetworkConfig,
+    pub upload: UploadConfig,
+    pub mail: MailConfig,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadConfig {
+    // 导出后上传的目标, 支持 sftp://user@host[:port]/dir、smb://server/share/dir 及 WebDAV 的 http(s):// 地址, 为空时不上传
+    pub url: String,
+    // 用户名, sftp 地址中包含用户名时以地址为准
+    pub username: String,
+    // 已废弃, 口令以 `secret set upload.password` 保存到凭据存储, 或由环境变量 SH_SDS_UPLOAD_PASSWORD 提供
+    #[serde(skip_serializing)]
+    pub password: String,
+    // sftp 使用的私钥文件, 为空且未设置口令时使用 ssh-agent
+    pub identity: String,
+    // sftp 校验主机密钥使用的 known_hosts, 为空时使用 ~/.ssh/known_hosts
+    pub known_hosts: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    // 发送合规摘要的 SMTP 服务器, 如 smtps://smtp.example.com:465 或 smtp://smtp.example.com:587(要求 STARTTLS), 为空时不发送
+    pub url: String,
+    pub from: String,
+    pub to: Vec<String>,
+    pub username: String,
+    // 已废弃, 口令以 `secret set mail.password` 保存到凭据存储, 或由环境变量 SH_SDS_MAIL_PASSWORD 提供
+    #[serde(skip_serializing)]
+    pub password: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    // 离线模式, 不发起任何访问本机以外地址的网络连接, 用于隔离网络环境
+    pub offline: bool,
+    // WebDAV 上传及发送邮件使用的代理, 如 http://proxy.example.com:3128, 为空时使用 curl 默认的 http_proxy 等环境变量;
+    // sftp 及 smb 上传总是直接连接
+    pub proxy: String,
+    // 不经过代理的主机, 以逗号分隔, 同 curl 的 --noproxy
+    pub no_proxy: String,
+}
+
+impl NetworkConfig {
+    /// 传给 curl 的代理参数
+    pub fn curl_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.proxy.len() > 0 {
+            args.push("-x".to_string());
+            args.push(self.proxy.clone());
+        }
+        if self.no_proxy.len() > 0 {
+            args.push("--noproxy".to_string());
+            args.push(self.no_proxy.clone());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    // 是否将每次扫描结果写入 SQLite 数据库
+    pub enabled: bool,
+    // 数据库文件, 为空时使用配置目录下的 results.db
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    // 导出文件已存在时是否先改名为 <名称>.bak-N.<扩展名> 保留, 否则直接覆盖
+    pub backup: bool,
+    // YAML 合规记录中是否包含扫描开始与结束时间, 默认不包含, 使提交到 Git 的记录只在合规状态变化时改变
+    pub yaml_timestamps: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    // 界面使用的字体文件, 为空时自动查找系统中的中文字体
+    pub font: String,
+    // 界面缩放比例, 为 0 时使用 FLTK 检测的值; XWayland 下通常无法检测到桌面的缩放设置
+    pub scale: f32,
+    // 扫描结果的初始视图, 可在结果界面中切换
+    pub view: UiView,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UiView {
+    // 完整的检查结果、证据与备注, 供审计人员使用
+    Auditor,
+    // 只列出各项要求是否符合及整改说明, 供现场操作人员使用
+    Operator,
+}
+
+impl Default for UiView {
+    fn default() -> Self {
+        UiView::Auditor
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvidenceConfig {
+    // 单元格中证据文本的最大字符数, 超出部分会被截断
+    pub max_chars: usize,
+    // 截断时是否将完整内容另存到导出文件旁的证据目录
+    pub sidecar: bool,
+    // 以本机的语言环境执行命令, 默认以 LC_ALL=C 执行, 使各主机的证据文本一致
+    pub host_locale: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            operator: "".to_string(),
+            baseline: "".to_string(),
+            header: ReportHeader::default(),
+            evidence: EvidenceConfig::default(),
+            export: ExportConfig::default(),
+            ui: UiConfig::default(),
+            store: StoreConfig::default(),
+            network: NetworkConfig::default(),
+            upload: UploadConfig::default(),
+            mail: MailConfig::default(),
+        }
+    }
+}
+
+impl Default for EvidenceConfig {
+    fn default() -> Self {
+        EvidenceConfig {
+            max_chars: 4000,
+            sidecar: true,
+            host_locale: false,
+        }
+    }
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            backup: true,
+            yaml_timestamps: false,
+        }
+    }
+}
+
+impl Config {
+    fn load() -> Self {
+        let path = match CONFIG_PATH.get() {
+            Some(path) => path.to_path_buf(),
+            None => dir().join("config.toml"),
+        };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                if CONFIG_PATH.get().is_some() {
+                    eprintln!("cannot read {}: {}", path.display(), e);
+                }
+                return Config::default();
+            },
+        };
+        match toml::from_str::<Config>(&text) {
+            // 口令应保存在加密的凭据存储中; 旧配置中的明文口令只在文件仅属主可读时使用
+            Ok(config) if config.upload.password.len() > 0 || config.mail.password.len() > 0 => {
+                if crate::secrets::readable_by_others(&path) {
+                    eprintln!("refusing to load {}: it contains a password but is readable by other users, run `chmod 600` or move the password with `secret set`", path.display());
+                    return Config::default();
+                }
+                eprintln!("{}: password in the config file is deprecated, move it with `secret set`", path.display());
+                config
+            },
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("cannot parse {}: {}", path.display(), e);
+                Config::default()
+            },
+        }
+    }
+
+    /// 单元格允许的最大字符数, 不会超过 Excel 的上限
+    pub fn max_evidence_chars(&self) -> usize {
+        std::cmp::min(self.evidence.max_chars, XLSX_CELL_MAX_CHARS)
+    }
+}
+
+/// 配置目录, 优先使用 `$XDG_CONFIG_HOME/sh-sds`, 否则为 `~/.config/sh-sds`
+pub fn dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+        if dir.len() > 0 {
+            return PathBuf::from(dir).join("sh-sds");
+        }
+    }
+    util::home_dir().join(".config").join("sh-sds")
+}
+
+/// 数据目录, 优先使用 `$XDG_DATA_HOME/sh-sds`, 否则为 `~/.local/share/sh-sds`
+pub fn data_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("XDG_DATA_HOME") {
+        if dir.len() > 0 {
+            return PathBuf::from(dir).join("sh-sds");
+        }
+    }
+    util::home_dir().join(".local").join("share").join("sh-sds")
+}
+
+pub fn set_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+pub fn get() -> &'static Config {
+    &CONFIG
+}
@@ -0,0 +1,242 @@
+//! 合规摘要邮件
+//!
+//! 汇总结果数据库中各主机最近一次扫描相对上次摘要时的变化: 合规率的升降、新增的不符合要求及已修复的要求,
+//! 通过 curl 以 SMTP 发送, 不再每次附带完整报告. 由每周的定时任务(cron 或 systemd timer)执行 `digest`,
+//! 发送成功后在数据库中记录各主机已包含到哪次扫描, 下次摘要从这里开始比较.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::config;
+use crate::i18n;
+use crate::report;
+use crate::schema::Status;
+use crate::secrets;
+use crate::store::{self, ScanRow, Store};
+use crate::util;
+
+/// 一台主机自上次摘要以来的变化
+struct HostDigest {
+    hostname: String,
+    // 上次摘要所依据的扫描, 首次摘要时为空
+    previous: Option<ScanRow>,
+    latest: ScanRow,
+    failed: Vec<String>,
+    fixed: Vec<String>,
+}
+
+/// 生成摘要并发送, `dry_run` 时只输出邮件内容, 不发送也不记录
+pub fn run(dry_run: bool) -> Result<(), String> {
+    let conf = &config::get().mail;
+    if !dry_run {
+        if conf.url.len() == 0 || conf.from.len() == 0 || conf.to.len() == 0 {
+            return Err("未在 config.toml 的 [mail] 中配置 url、from 及 to".to_string());
+        }
+        if config::get().network.offline {
+            return Err("离线模式下不发送合规摘要".to_string());
+        }
+    }
+    let path = store::path();
+    if !path.exists() {
+        return Err(format!("结果数据库 {} 不存在, 请在 config.toml 的 [store] 中启用", path.display()));
+    }
+    let store = Store::open(&path).map_err(|e| format!("{:?}", e))?;
+    let digests = collect(&store).map_err(|e| format!("{:?}", e))?;
+    let (subject, body) = compose(&digests);
+    if dry_run {
+        println!("Subject: {}\n\n{}", subject, body);
+        return Ok(());
+    }
+    send(&subject, &body)?;
+
+    let sent_at = report::now();
+    let _lock = store::write_lock(&path).map_err(|e| format!("{:?}", e))?;
+    for digest in digests.iter() {
+        store.set_digest(digest.latest.id, &sent_at).map_err(|e| format!("{:?}", e))?;
+    }
+    Ok(())
+}
+
+fn collect(store: &Store) -> errlog::AnyResult<Vec<HostDigest>> {
+    let scans = store.scans(None)?;
+    let mut hostnames = scans.iter().map(|x| x.hostname.to_string()).collect::<Vec<String>>();
+    hostnames.sort();
+    hostnames.dedup();
+
+    let mut digests = vec![];
+    for hostname in hostnames {
+        // 扫描按时间倒序排列, 第一条即最近一次扫描
+        let latest = match scans.iter().find(|x| x.hostname == hostname) {
+            Some(latest) => latest.clone(),
+            None => continue,
+        };
+        let previous = match store.last_digest(&hostname)? {
+            Some(id) => scans.iter().find(|x| x.id == id).cloned(),
+            None => None,
+        };
+        let (failed, fixed) = match previous.as_ref() {
+            Some(previous) => changes(&store.statuses(previous.id)?, &store.statuses(latest.id)?),
+            None => (vec![], vec![]),
+        };
+        digests.push(HostDigest { hostname, previous, latest, failed, fixed });
+    }
+    Ok(digests)
+}
+
+/// 新增的不符合要求及由不符合变为符合的要求, 与 `diff --format json` 的恶化、修复一致
+fn changes(old: &[(String, Status)], new: &[(String, Status)]) -> (Vec<String>, Vec<String>) {
+    let status = |statuses: &[(String, Status)], id: &str| statuses.iter().find(|x| x.0 == id).map(|x| x.1);
+    let mut failed = vec![];
+    let mut fixed = vec![];
+    for (id, new_status) in new.iter() {
+        let old_status = status(old, id);
+        if *new_status == Status::Fail && old_status != Some(Status::Fail) {
+            failed.push(id.to_string());
+        } else if old_status == Some(Status::Fail) && (*new_status == Status::Pass || *new_status == Status::Partial) {
+            fixed.push(id.to_string());
+        }
+    }
+    (failed, fixed)
+}
+
+fn score(scan: &ScanRow) -> String {
+    match scan.score() {
+        Some(score) => format!("{:.1}%", score),
+        None => "-".to_string(),
+    }
+}
+
+// 邮件主题与正文
+fn compose(digests: &[HostDigest]) -> (String, String) {
+    let failed = digests.iter().map(|x| x.failed.len()).sum::<usize>();
+    let fixed = digests.iter().map(|x| x.fixed.len()).sum::<usize>();
+    let subject = format!("SH-SDS 合规摘要: {} 台主机, 新增不符合 {} 项, 修复 {} 项", digests.len(), failed, fixed);
+
+    let mut body = String::new();
+    for digest in digests.iter() {
+        let latest = &digest.latest;
+        body.push_str(&format!("{}  最近扫描 #{} ({})\n", digest.hostname, latest.id, latest.started_at));
+        match digest.previous.as_ref() {
+            Some(previous) if previous.id == latest.id => {
+                body.push_str(&format!("  合规率 {}, 自上次摘要以来没有新的扫描\n", score(latest)));
+            },
+            Some(previous) => {
+                let delta = match (previous.score(), latest.score()) {
+                    (Some(old), Some(new)) => format!(" ({:+.1})", new - old),
+                    _ => "".to_string(),
+                };
+                body.push_str(&format!("  合规率 {} -> {}{}, 比较扫描 #{} ({})\n", score(previous), score(latest), delta, previous.id, previous.started_at));
+            },
+            None => {
+                body.push_str(&format!("  合规率 {}, 不符合 {} 项; 首次摘要, 下次起列出变化\n", score(latest), latest.failed));
+            },
+        }
+        for id in digest.failed.iter() {
+            body.push_str(&format!("  新增不符合 {:<24} {}\n", id, i18n::tr(id)));
+        }
+        for id in digest.fixed.iter() {
+            body.push_str(&format!("  已修复     {:<24} {}\n", id, i18n::tr(id)));
+        }
+        body.push('\n');
+    }
+    if digests.len() == 0 {
+        body.push_str("结果数据库中没有扫描记录\n");
+    }
+    (subject, body)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | ((*b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(TABLE[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// 非 ASCII 的邮件头按 RFC 2047 以 UTF-8 的 Base64 编码; 每个编码字最长 75 个字符, 即最多 45 字节,
+// 按字符边界切分, 多个编码字之间折行
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    const MAX_BYTES: usize = 45;
+    let mut words = vec![];
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        if idx + c.len_utf8() - start > MAX_BYTES {
+            words.push(format!("=?UTF-8?B?{}?=", base64(&text.as_bytes()[start..idx])));
+            start = idx;
+        }
+    }
+    words.push(format!("=?UTF-8?B?{}?=", base64(&text.as_bytes()[start..])));
+    words.join("\r\n ")
+}
+
+fn message(subject: &str, body: &str) -> String {
+    let conf = &config::get().mail;
+    let date = util::runcmd("date -R", None).map(|x| x.trim().to_string()).unwrap_or_default();
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{}",
+        conf.from, conf.to.join(", "), encode_header(subject), date, body.replace("\n", "\r\n"),
+    )
+}
+
+// 调用 curl 发送; -K 从标准输入读取配置时不能再从标准输入读取邮件, 因此邮件写入临时文件, 口令随配置传入
+fn send(subject: &str, body: &str) -> Result<(), String> {
+    let conf = &config::get().mail;
+    let password = match conf.username.len() {
+        0 => "".to_string(),
+        _ => secrets::password("mail", &conf.password)?,
+    };
+    let mut file = tempfile::NamedTempFile::new().map_err(|e| format!("cannot create temporary file: {:?}", e))?;
+    file.write_all(message(subject, body).as_bytes()).map_err(|e| format!("cannot write temporary file: {:?}", e))?;
+
+    let mut cmd = Command::new("curl");
+    cmd.args(config::get().network.curl_args());
+    cmd.args(&["-sS", "-K", "-", "--mail-from"]).arg(&conf.from);
+    for to in conf.to.iter() {
+        cmd.arg("--mail-rcpt").arg(to);
+    }
+    if conf.url.starts_with("smtp://") {
+        cmd.arg("--ssl-reqd");
+    }
+    let mut child = cmd.arg("-T").arg(file.path()).arg(&conf.url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run curl: {:?}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        if conf.username.len() > 0 {
+            let user = format!("{}:{}", conf.username, password).replace("\\", "\\\\").replace("\"", "\\\"");
+            let _ = writeln!(stdin, "user = \"{}\"", user);
+        }
+    }
+    let output = child.wait_with_output().map_err(|e| format!("cannot run curl: {:?}", e))?;
+    if !output.status.success() {
+        return Err(format!("sending digest to {} failed: {}", conf.url, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_changes() {
+    let old = vec![("a".to_string(), Status::Pass), ("b".to_string(), Status::Fail), ("c".to_string(), Status::Fail)];
+    let new = vec![("a".to_string(), Status::Fail), ("b".to_string(), Status::Partial), ("c".to_string(), Status::Fail), ("d".to_string(), Status::Fail)];
+    assert_eq!((vec!["a".to_string(), "d".to_string()], vec!["b".to_string()]), changes(&old, &new));
+    assert_eq!("=?UTF-8?B?5ZGo5oql?=", encode_header("周报"));
+    assert_eq!("weekly", encode_header("weekly"));
+    let subject = "合规摘要".repeat(5);
+    let encoded = encode_header(&subject);
+    assert_eq!(2, encoded.split("\r\n ").count());
+    assert!(encoded.split("\r\n ").all(|x| x.len() <= 75 && x.starts_with("=?UTF-8?B?") && x.ends_with("?=")));
+    assert_eq!(format!("=?UTF-8?B?{}?=\r\n =?UTF-8?B?{}?=", base64(&subject.as_bytes()[..45]), base64(&subject.as_bytes()[45..])), encoded);
+}
@@ -0,0 +1,726 @@
+use std::io::{Write};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use docx_rs::{AlignmentType, BreakType, Docx, Paragraph, Run, Table, TableCell, TableRow};
+use tempfile;
+use umya_spreadsheet::{self, Spreadsheet, Worksheet};
+use zip::{ZipWriter, write::FileOptions};
+
+use crate::baseline;
+use crate::config;
+use crate::gbt22239;
+use crate::i18n;
+use crate::reference;
+use crate::report::ScanReport;
+use crate::schema::{self, Status};
+use crate::store::{self, ScanRow};
+use crate::sysguard::GuardCell;
+use crate::util;
+
+// 界面中拖入的自定义报告模板, 未设置时使用内置的台账模板
+static TEMPLATE: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+// 界面中打包导出前对结果界面的截图(PNG), 只附带到下一次打包导出中
+static SCREENSHOT: Lazy<Mutex<Option<Vec<u8>>>> = Lazy::new(|| Mutex::new(None));
+
+// 检查要求前的标记, 写入单页报告及表格的"报告信息"表
+const LEGEND: &str = "✓ 符合　✗ 不符合　◐ 自动检查的部分符合, 其余条件需人工核查　未标记的需人工核查";
+
+// 各导出函数成功时返回实际写入的文件路径(可能补充了扩展名), 供调用方继续上传
+
+pub fn saveas(report: &ScanReport, dst: String) -> Result<String, String> {
+    write_xlsx(report, dst, false)
+}
+
+/// 在台账之外附加 GB/T 22239 条款对照汇总及明细
+pub fn saveas_gbt22239(report: &ScanReport, dst: String) -> Result<String, String> {
+    write_xlsx(report, dst, true)
+}
+
+/// 按 `assets/report.schema.json` 的格式导出 JSON 结果
+pub fn saveas_json(report: &ScanReport, dst: String) -> Result<String, String> {
+    let dst = with_extension(dst, "json");
+    let json = schema::to_json(report)?;
+    backup(Path::new(&dst))?;
+    std::fs::write(&dst, json).map_err(|e| format!("cannot write {}: {:?}", dst, e))?;
+    Ok(dst)
+}
+
+/// 导出每个安全要求一行的 CSV 结果, 列见 `schema::CSV_COLUMNS`
+pub fn saveas_csv(report: &ScanReport, dst: String) -> Result<String, String> {
+    let dst = with_extension(dst, "csv");
+    backup(Path::new(&dst))?;
+    std::fs::write(&dst, schema::to_csv(report)).map_err(|e| format!("cannot write {}: {:?}", dst, e))?;
+    Ok(dst)
+}
+
+// 提交到 Git 仓库的合规记录的元数据, 默认不含扫描时间、操作人及命令行参数等每次扫描都会变化的值
+#[derive(Serialize)]
+struct YamlMeta<'a> {
+    hostname: &'a str,
+    version: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    started_at: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished_at: Option<&'a str>,
+    missing: &'a [String],
+}
+
+#[derive(Serialize)]
+struct YamlReport<'a> {
+    schema_version: u32,
+    meta: YamlMeta<'a>,
+    checks: Vec<schema::Check>,
+}
+
+fn to_yaml(report: &ScanReport, timestamps: bool) -> Result<String, String> {
+    let meta = &report.meta;
+    let yaml = YamlReport {
+        schema_version: schema::SCHEMA_VERSION,
+        meta: YamlMeta {
+            hostname: &meta.hostname,
+            version: &meta.version,
+            started_at: if timestamps { Some(&meta.started_at) } else { None },
+            finished_at: if timestamps { Some(&meta.finished_at) } else { None },
+            missing: &meta.missing,
+        },
+        checks: report.cells.iter().map(|x| schema::Check::from(x)).collect(),
+    };
+    serde_yaml::to_string(&yaml).map_err(|e| format!("cannot serialize report: {:?}", e))
+}
+
+/// 导出 YAML 格式的合规记录, 用于按主机提交到 Git 仓库, 以 diff 及评审跟踪合规状态的变化.
+/// 字段顺序固定, 文件由 Git 保留历史, 因此直接覆盖而不另存备份
+pub fn saveas_yaml(report: &ScanReport, dst: String) -> Result<String, String> {
+    let dst = if dst.ends_with(".yml") { dst } else { with_extension(dst, "yaml") };
+    let yaml = to_yaml(report, config::get().export.yaml_timestamps)?;
+    std::fs::write(&dst, yaml).map_err(|e| format!("cannot write {}: {:?}", dst, e))?;
+    Ok(dst)
+}
+
+/// 补充导出文件的扩展名, 例如 `with_extension("report", "docx")` 为 "report.docx"
+pub fn with_extension(dst: String, ext: &str) -> String {
+    if !dst.ends_with(&format!(".{}", ext)) {
+        format!("{}.{}", dst, ext)
+    } else {
+        dst
+    }
+}
+
+// 形如 report.bak-1.xlsx, 没有扩展名时为 report.bak-1
+fn backup_name(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().map(|x| x.to_string_lossy().to_string()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.bak-{}.{}", stem, n, ext.to_string_lossy()),
+        None => format!("{}.bak-{}", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+/// 导出前处理已存在的目标文件: 按配置改名为编号最大的 `<名称>.bak-N.<扩展名>` 保留并返回新的文件名,
+/// 或直接删除; 文件不存在时不做任何操作
+pub fn backup(dst: &Path) -> Result<Option<PathBuf>, String> {
+    if !dst.exists() {
+        return Ok(None);
+    }
+    if !config::get().export.backup {
+        std::fs::remove_file(dst).map_err(|e| format!("cannot remove {}: {:?}", dst.display(), e))?;
+        return Ok(None);
+    }
+    let bak = (1..).map(|n| backup_name(dst, n)).find(|x| !x.exists()).unwrap();
+    std::fs::rename(dst, &bak).map_err(|e| format!("cannot rename {} to {}: {:?}", dst.display(), bak.display(), e))?;
+    Ok(Some(bak))
+}
+
+/// 默认的导出文件名(不含扩展名), 形如 `<主机名>-<扫描日期>`
+pub fn default_name(report: &ScanReport) -> String {
+    let date = report.meta.started_at.split(" ").nth(0).unwrap_or("").replace("-", "");
+    let hostname = if report.meta.hostname.len() > 0 {
+        report.meta.hostname.to_string()
+    } else {
+        "report".to_string()
+    };
+    if date.len() > 0 {
+        format!("{}-{}", hostname, date)
+    } else {
+        hostname
+    }
+}
+
+/// 将表格、JSON、HTML、命令执行记录及完整的证据文件打包为一个 zip 文件, `dst` 为目录时以默认文件名保存在该目录下
+pub fn saveas_bundle(report: &ScanReport, dst: String) -> Result<String, String> {
+    let dst = if Path::new(&dst).is_dir() {
+        Path::new(&dst).join(format!("{}.zip", default_name(report))).to_string_lossy().to_string()
+    } else {
+        with_extension(dst, "zip")
+    };
+
+    let tmpdir = tempfile::tempdir().map_err(|e| format!("cannot create temporary directory: {:?}", e))?;
+    let xlsx = tmpdir.path().join("report.xlsx");
+    write_xlsx(report, xlsx.to_string_lossy().to_string(), true)?;
+    let xlsx = std::fs::read(&xlsx).map_err(|e| format!("cannot read xlsx: {:?}", e))?;
+
+    let json = schema::to_json(report)?;
+    let journal = util::cmdlog().iter().map(|x| x.to_string()).collect::<Vec<String>>().join("\n");
+
+    let mut files = vec![
+        ("report.xlsx".to_string(), xlsx),
+        ("report.json".to_string(), json.into_bytes()),
+        ("report.html".to_string(), html(report).into_bytes()),
+        ("journal.txt".to_string(), journal.into_bytes()),
+    ];
+    if let Some(png) = SCREENSHOT.lock().unwrap().take() {
+        files.push(("screenshot.png".to_string(), png));
+    }
+    // 证据文件保留未截断的完整内容
+    for r in report.cells.iter() {
+        let mut evidence = r.mp.iter()
+            .filter(|(k, v)| k.starts_with("C") && v.len() > 0)
+            .collect::<Vec<(&String, &String)>>();
+        evidence.sort();
+        for (k, v) in evidence {
+            files.push((format!("evidence/{}.txt", k), v.as_bytes().to_vec()));
+        }
+    }
+
+    backup(Path::new(&dst))?;
+    let file = File::create(&dst).map_err(|e| format!("cannot create {}: {:?}", dst, e))?;
+    let mut zip = ZipWriter::new(file);
+    for (name, data) in files {
+        zip.start_file(name, FileOptions::default()).map_err(|e| format!("failed to write zip with error: {:?}", e))?;
+        zip.write_all(&data).map_err(|e| format!("failed to write zip with error: {:?}", e))?;
+    }
+    zip.finish().map_err(|e| format!("failed to write zip with error: {:?}", e))?;
+    Ok(dst)
+}
+
+/// 设置下一次打包导出附带的结果界面截图, `rgb` 为逐行排列的 8 位 RGB 像素
+pub fn set_screenshot(width: u32, height: u32, rgb: &[u8]) -> Result<(), String> {
+    let mut data = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut data, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| format!("cannot encode screenshot: {:?}", e))?;
+        writer.write_image_data(rgb).map_err(|e| format!("cannot encode screenshot: {:?}", e))?;
+    }
+    *SCREENSHOT.lock().unwrap() = Some(data);
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;").replace("\"", "&quot;")
+}
+
+// 已填写的表头字段, 形如 "单位名称：某某单位　检查日期：2022-05-01", 都未填写时为 None
+fn header_line(report: &ScanReport) -> Option<String> {
+    let fields = report.meta.header.fields().into_iter()
+        .filter(|(_, v)| v.len() > 0)
+        .map(|(k, v)| format!("{}：{}", k, v))
+        .collect::<Vec<String>>();
+    if fields.len() == 0 {
+        None
+    } else {
+        Some(fields.join("　"))
+    }
+}
+
+// 便于在浏览器中直接查看的单页报告
+fn html(report: &ScanReport) -> String {
+    let meta = &report.meta;
+    let mut body = vec![format!("<h1>主机安全检查报告 - {}</h1>", escape(&meta.hostname))];
+    if let Some(header) = header_line(report) {
+        body.push(format!("<p>{}</p>", escape(&header)));
+    }
+    body.extend(vec![
+        format!(
+            "<p>操作人员：{} 扫描时间：{} 至 {} 工具版本：{}</p>",
+            escape(&meta.operator), escape(&meta.started_at), escape(&meta.finished_at), escape(&meta.version),
+        ),
+        format!("<p>{}</p>", LEGEND),
+        "<table border=\"1\" cellspacing=\"0\" cellpadding=\"4\">".to_string(),
+        "<tr><th>检查项</th><th>检查要求</th><th>备注</th></tr>".to_string(),
+    ]);
+    for r in report.cells.iter() {
+        let column = |col: char| escape(&r.column(col).join("\n")).replace("\n", "<br>");
+        let sources = match r.sources.len() {
+            0 => "".to_string(),
+            _ => format!("<br><small style=\"color:#888\">来源：{}</small>", escape(&r.sources.join("、"))),
+        };
+        body.push(format!("<tr><td>{}</td><td>{}</td><td>{}{}</td></tr>", column('A'), column('B'), column('C'), sources));
+    }
+    body.push("</table>".to_string());
+
+    // 本机历史扫描的合规率趋势, 只包含本次扫描及之前的扫描
+    let history = store::history(&meta.hostname).into_iter()
+        .filter(|x| x.started_at <= meta.started_at)
+        .collect::<Vec<ScanRow>>();
+    if let Some(svg) = trend_svg(&history) {
+        body.push("<h2>合规率趋势</h2>".to_string());
+        body.push(svg);
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n{}\n</body>\n</html>\n",
+        escape(&meta.hostname), body.join("\n"),
+    )
+}
+
+// 以折线图绘制各次扫描的合规率, 少于两次可计算合规率的扫描时不绘制
+fn trend_svg(scans: &[ScanRow]) -> Option<String> {
+    let points = scans.iter()
+        .filter_map(|x| x.score().map(|score| (x, score)))
+        .collect::<Vec<(&ScanRow, f64)>>();
+    if points.len() < 2 {
+        return None;
+    }
+    let (width, height, margin) = (640.0, 240.0, 40.0);
+    let step = (width - margin * 2.0) / (points.len() - 1) as f64;
+    let xy = |idx: usize, score: f64| (margin + step * idx as f64, height - margin - (height - margin * 2.0) * score / 100.0);
+
+    let mut svg = vec![
+        format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-size=\"10\">", width, height),
+    ];
+    // 纵轴刻度 0%、50%、100%
+    for score in vec![0.0, 50.0, 100.0] {
+        let (_, y) = xy(0, score);
+        svg.push(format!("<line x1=\"{}\" y1=\"{:.1}\" x2=\"{}\" y2=\"{:.1}\" stroke=\"#ddd\"/>", margin, y, width - margin, y));
+        svg.push(format!("<text x=\"4\" y=\"{:.1}\">{}%</text>", y + 3.0, score));
+    }
+    let polyline = points.iter().enumerate()
+        .map(|(idx, (_, score))| {
+            let (x, y) = xy(idx, *score);
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect::<Vec<String>>();
+    svg.push(format!("<polyline points=\"{}\" fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"2\"/>", polyline.join(" ")));
+    for (idx, (scan, score)) in points.iter().enumerate() {
+        let (x, y) = xy(idx, *score);
+        svg.push(format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"#1f77b4\"><title>{} {:.1}%</title></circle>",
+            x, y, escape(&scan.started_at), score,
+        ));
+    }
+    // 只标注首末两次扫描的日期, 避免文字重叠
+    for idx in vec![0, points.len() - 1] {
+        let (x, _) = xy(idx, 0.0);
+        let date = points[idx].0.started_at.split_whitespace().next().unwrap_or("");
+        svg.push(format!("<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x, height - margin + 16.0, escape(date)));
+    }
+    svg.push("</svg>".to_string());
+    Some(svg.join("\n"))
+}
+
+/// 生成 Word 格式的检查报告, 包括概述、分类检查结果与整改计划
+pub fn saveas_docx(report: &ScanReport, dst: String) -> Result<String, String> {
+    let dst = with_extension(dst, "docx");
+
+    let (mut passed, mut failed, mut manual, mut partial) = (0, 0, 0, 0);
+    for r in report.cells.iter() {
+        for (_, status) in r.statuses() {
+            match status {
+                Status::Pass => passed += 1,
+                Status::Fail => failed += 1,
+                Status::Manual => manual += 1,
+                Status::Partial => partial += 1,
+            }
+        }
+    }
+
+    let meta = &report.meta;
+    let mut docx = Docx::new()
+        .add_paragraph(heading("主机安全检查报告", 36).align(AlignmentType::Center));
+    if let Some(header) = header_line(report) {
+        docx = docx.add_paragraph(text_paragraph(&header).align(AlignmentType::Center));
+    }
+    docx = docx
+        .add_paragraph(heading("一、概述", 28))
+        .add_paragraph(text_paragraph(&format!(
+            "本次检查由 {} 于 {} 至 {} 对主机 {} 进行, 检查工具版本为 {}.",
+            meta.operator, meta.started_at, meta.finished_at, meta.hostname, meta.version,
+        )))
+        .add_paragraph(text_paragraph(&format!(
+            "共检查 {} 项安全要求, 其中符合 {} 项, 不符合 {} 项, 需人工核查 {} 项, 部分自动检查 {} 项(◐, 自动检查的部分符合, 其余条件需人工核查).",
+            passed + failed + manual + partial, passed, failed, manual, partial,
+        )))
+        .add_paragraph(heading("二、检查结果", 28));
+
+    let max = config::get().max_evidence_chars();
+    for r in report.cells.iter() {
+        if r.reqs.len() == 0 {
+            continue;
+        }
+        docx = docx.add_paragraph(heading(&r.column('A').join(" "), 24));
+        let mut rows = vec![table_row(&["安全要求", "检查结果"])];
+        for (id, status) in r.statuses() {
+            rows.push(table_row(&[i18n::tr(&id), gbt22239::verdict(&[status])]));
+        }
+        docx = docx.add_table(Table::new(rows).set_grid(vec![6400, 2000]));
+
+        let remarks = r.column('C').join("\n");
+        if remarks.trim().len() > 0 {
+            let remarks = match util::truncate(&remarks, max) {
+                Some(text) => format!("{}\n…(内容已截断, 完整内容请导出表格查看)", text),
+                None => remarks,
+            };
+            docx = docx.add_paragraph(text_paragraph(&format!("备注：\n{}", remarks.trim())));
+        }
+        if r.sources.len() > 0 {
+            docx = docx.add_paragraph(text_paragraph(&format!("来源：{}", r.sources.join("、"))));
+        }
+    }
+
+    docx = docx.add_paragraph(heading("三、整改计划", 28));
+    let mut idx = 0;
+    let plan = vec![
+        ("下列不符合项应按要求整改：", vec![Status::Fail]),
+        ("下列项需人工核查确认：", vec![Status::Manual, Status::Partial]),
+    ];
+    for (title, expected) in plan {
+        let items = report.cells.iter()
+            .flat_map(|r| r.statuses().into_iter().filter(|x| expected.contains(&x.1)).map(move |x| (r.column('A').join(" "), x.0)))
+            .collect::<Vec<(String, String)>>();
+        if items.len() == 0 {
+            continue;
+        }
+        docx = docx.add_paragraph(text_paragraph(title));
+        for (category, id) in items {
+            idx += 1;
+            docx = docx.add_paragraph(text_paragraph(&format!("{}. [{}] {}", idx, category, i18n::tr(&id))));
+        }
+    }
+    if idx == 0 {
+        docx = docx.add_paragraph(text_paragraph("所有检查项均符合要求, 无需整改."));
+    }
+
+    backup(Path::new(&dst))?;
+    let file = File::create(&dst).map_err(|e| format!("cannot create {}: {:?}", dst, e))?;
+    if let Err(e) = docx.build().pack(file) {
+        return Err(format!("failed to write docx with error: {:?}", e));
+    }
+    Ok(dst)
+}
+
+fn heading(text: &str, size: usize) -> Paragraph {
+    Paragraph::new().add_run(Run::new().add_text(text).bold().size(size))
+}
+
+// 多行文本在同一段落中以换行分隔
+fn text_paragraph(text: &str) -> Paragraph {
+    let mut run = Run::new();
+    for (idx, line) in text.lines().enumerate() {
+        if idx > 0 {
+            run = run.add_break(BreakType::TextWrapping);
+        }
+        run = run.add_text(line);
+    }
+    Paragraph::new().add_run(run)
+}
+
+fn table_row(values: &[&str]) -> TableRow {
+    TableRow::new(values.iter().map(|x| TableCell::new().add_paragraph(text_paragraph(x))).collect())
+}
+
+// 超出单元格字符上限的证据会被截断, 按配置将完整内容保存到证据目录中; 预览时不写入证据文件
+fn fit_evidence(pos: &str, val: &str, evidence_dir: &Path, dry_run: bool) -> Result<String, String> {
+    let config = config::get();
+    let max = config.max_evidence_chars();
+    if val.chars().count() <= max {
+        return Ok(val.to_string());
+    }
+
+    let note = if config.evidence.sidecar {
+        let path = evidence_dir.join(format!("{}.txt", pos));
+        if !dry_run {
+            std::fs::create_dir_all(evidence_dir).map_err(|e| format!("cannot create evidence directory: {:?}", e))?;
+            std::fs::write(&path, val).map_err(|e| format!("cannot write evidence file: {:?}", e))?;
+        }
+        format!("\n…(内容已截断, 完整内容见 {})", path.display())
+    } else {
+        "\n…(内容已截断)".to_string()
+    };
+    let truncated = util::truncate(val, max.saturating_sub(note.chars().count())).unwrap_or_default();
+    Ok(truncated + &note)
+}
+
+/// 校验并设置导出 xlsx 时使用的报告模板, 模板中需包含与当前基线模板相同布局的工作表(内置模板为"工作站")
+pub fn set_template(path: &Path) -> Result<(), String> {
+    let book = umya_spreadsheet::reader::xlsx::read(path)
+        .map_err(|e| format!("cannot read template {}: {:?}", path.display(), e))?;
+    let sheet = &baseline::get().sheet;
+    if book.get_sheet_by_name(sheet).is_err() {
+        return Err(format!("模板 {} 中没有\"{}\"工作表", path.display(), sheet));
+    }
+    *TEMPLATE.lock().unwrap() = Some(path.to_path_buf());
+    Ok(())
+}
+
+// 拖入的模板优先, 其次为基线包指定的模板, 都没有时使用内置模板
+fn read_template() -> Result<Spreadsheet, String> {
+    let tmpdir = tempfile::tempdir().map_err(|e| format!("cannot create temporary directory: {:?}", e))?;
+    let template = TEMPLATE.lock().unwrap().clone().or_else(|| baseline::get().template());
+    let tplpath = match template {
+        Some(path) => path,
+        None => {
+            let tplbytes = include_bytes!("../assets/附件2：网络安全台账（原件）.xlsx");
+            let tplpath = tmpdir.path().join("tpl.xlsx");
+            let mut tplfile = File::create(&tplpath).map_err(|e| format!("cannot create template file: {:?}", e))?;
+            let _ = tplfile.write_all(&tplbytes[..]);
+            tplpath
+        },
+    };
+    umya_spreadsheet::reader::xlsx::read(&tplpath)
+        .map_err(|e| format!("cannot read template {}: {:?}", tplpath.display(), e))
+}
+
+/// 导出 xlsx 时将写入模板的一个单元格, 包括模板中的原值与将写入的值
+pub struct CellChange {
+    pub sheet: String,
+    pub pos: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// 预览导出 xlsx 时模板中将被写入的单元格, 按行、列排列, 不写入任何文件;
+/// 用于在覆盖文件前核对自定义模板的单元格对应关系
+pub fn preview_xlsx(report: &ScanReport, dst: String) -> Result<Vec<CellChange>, String> {
+    let evidence_dir = Path::new(&with_extension(dst, "xlsx")).with_extension("evidence");
+    let baseline = baseline::get();
+    let book = read_template()?;
+    let sheet = book.get_sheet_by_name(&baseline.sheet).map_err(|e| format!("invalid template: {}", e))?;
+    let mut changes = vec![];
+    for r in report.cells.iter() {
+        for (k, v) in r.mp.iter() {
+            let pos = baseline.position(&r.id, k);
+            changes.push(CellChange {
+                sheet: baseline.sheet.to_string(),
+                old: sheet.get_cell(&pos).map_or("".to_string(), |x| x.get_value().to_string()),
+                new: fit_evidence(k, v, &evidence_dir, true)?,
+                pos,
+            });
+        }
+    }
+    for (pos, v) in baseline.header_cells(&report.meta.header) {
+        changes.push(CellChange {
+            sheet: baseline.sheet.to_string(),
+            old: sheet.get_cell(&pos).map_or("".to_string(), |x| x.get_value().to_string()),
+            new: v,
+            pos,
+        });
+    }
+    let key = |pos: &str| {
+        let idx = pos.find(|c: char| c.is_ascii_digit()).unwrap_or(pos.len());
+        (pos[idx..].parse::<u32>().unwrap_or(0), pos[..idx].len(), pos[..idx].to_string())
+    };
+    changes.sort_by_key(|x| key(&x.pos));
+    Ok(changes)
+}
+
+/// 导出 xlsx 时在模板之外新增的工作表
+pub fn added_sheets(with_gbt22239: bool) -> Vec<&'static str> {
+    let mut sheets = vec!["报告信息"];
+    if with_gbt22239 {
+        sheets.extend(vec!["等保对照汇总", "等保对照明细"]);
+    }
+    sheets.push("要求说明");
+    sheets.push("要求对照");
+    sheets.push("证据来源");
+    sheets
+}
+
+fn write_xlsx(report: &ScanReport, dst: String, with_gbt22239: bool) -> Result<String, String> {
+    let dst = with_extension(dst, "xlsx");
+    let dst = Path::new(&dst);
+    let evidence_dir = dst.with_extension("evidence");
+    // 证据目录随备份的文件一起改名保留
+    match backup(dst)? {
+        Some(bak) if evidence_dir.exists() => {
+            let _ = std::fs::rename(&evidence_dir, bak.with_extension("evidence"));
+        },
+        _ => if evidence_dir.exists() {
+            let _ = std::fs::remove_dir_all(&evidence_dir);
+        },
+    }
+
+    let baseline = baseline::get();
+    let mut book = read_template()?;
+    let sheet = book.get_sheet_by_name_mut(&baseline.sheet).map_err(|e| format!("invalid template: {}", e))?;
+    for r in report.cells.iter() {
+        for (k, v) in r.mp.iter() {
+            let v = fit_evidence(k, v, &evidence_dir, false)?;
+            sheet.get_cell_mut(baseline.position(&r.id, k)).set_value(v);
+        }
+    }
+    for (pos, v) in baseline.header_cells(&report.meta.header) {
+        sheet.get_cell_mut(pos).set_value(v);
+    }
+
+    write_meta_sheet(&mut book, report)?;
+    if with_gbt22239 {
+        write_gbt22239_sheets(&mut book, &report.cells)?;
+    }
+    write_reference_sheet(&mut book, report)?;
+    write_cross_reference_sheet(&mut book, report)?;
+    write_sources_sheet(&mut book, report)?;
+
+    if let Err(e) = umya_spreadsheet::writer::xlsx::write(&book, &dst) {
+        return Err(format!("failed to write xlsx with error: {:?}", e));
+    }
+    Ok(dst.to_string_lossy().to_string())
+}
+
+fn set_row(sheet: &mut Worksheet, row: usize, values: &[String]) {
+    for (col, v) in values.iter().enumerate() {
+        let pos = format!("{}{}", (b'A' + col as u8) as char, row);
+        sheet.get_cell_mut(pos).set_value(v.to_string());
+    }
+}
+
+fn write_meta_sheet(book: &mut Spreadsheet, report: &ScanReport) -> Result<(), String> {
+    let meta = &report.meta;
+    let sheet = book.new_sheet("报告信息").map_err(|e| format!("cannot create sheet: {}", e))?;
+    let mut rows = vec![
+        ("操作人员", meta.operator.to_string()),
+        ("主机名", meta.hostname.to_string()),
+        ("工具版本", meta.version.to_string()),
+        ("扫描开始时间", meta.started_at.to_string()),
+        ("扫描结束时间", meta.finished_at.to_string()),
+        ("命令行参数", meta.args.join(" ")),
+        ("缺失的依赖", meta.missing.join("\n")),
+        ("标记说明", LEGEND.to_string()),
+    ];
+    rows.extend(meta.header.fields().into_iter().map(|(k, v)| (k, v.to_string())));
+    for (idx, (k, v)) in rows.into_iter().enumerate() {
+        set_row(sheet, idx + 1, &[k.to_string(), v]);
+    }
+    Ok(())
+}
+
+// 各安全要求的完整说明及对应条款, 工作表默认隐藏, 需要时在 Excel 中取消隐藏查看
+fn write_reference_sheet(book: &mut Spreadsheet, report: &ScanReport) -> Result<(), String> {
+    let sheet = book.new_sheet("要求说明").map_err(|e| format!("cannot create sheet: {}", e))?;
+    sheet.set_sheet_state("hidden".to_string());
+    set_row(sheet, 1, &["要求 ID", "要求", "说明", "条款", "条款内容"].iter().map(|x| x.to_string()).collect::<Vec<String>>());
+    let mut row = 2;
+    for r in report.cells.iter() {
+        for (id, _) in r.reqs.iter() {
+            let (clause, text) = match gbt22239::clause_of(id) {
+                Some(clause) => (clause.id.to_string(), clause.text.to_string()),
+                None => ("".to_string(), "".to_string()),
+            };
+            let desc = reference::description(id).unwrap_or("");
+            set_row(sheet, row, &[id.to_string(), i18n::tr(id).to_string(), desc.to_string(), clause, text]);
+            row += 1;
+        }
+    }
+    Ok(())
+}
+
+// 每条安全要求一行的对照表, 下游程序按要求 ID 读取结果, 不必解析检查表中带勾选标记的文本
+fn write_cross_reference_sheet(book: &mut Spreadsheet, report: &ScanReport) -> Result<(), String> {
+    let sheet = book.new_sheet("要求对照").map_err(|e| format!("cannot create sheet: {}", e))?;
+    set_row(sheet, 1, &["id", "zh", "en", "clause", "severity", "status"].iter().map(|x| x.to_string()).collect::<Vec<String>>());
+    let mut row = 2;
+    for r in report.cells.iter() {
+        for (id, status) in r.statuses() {
+            let clause = gbt22239::clause_of(&id).map_or("".to_string(), |x| x.id.to_string());
+            set_row(sheet, row, &[
+                id.to_string(),
+                i18n::tr(&id).to_string(),
+                i18n::text(&id, i18n::Lang::En).to_string(),
+                clause,
+                reference::severity(&id).as_str().to_string(),
+                status.as_str().to_string(),
+            ]);
+            row += 1;
+        }
+    }
+    Ok(())
+}
+
+// 各检查项读取的文件及执行的命令, 每个来源一行, 供复核人员独立验证检查结果
+fn write_sources_sheet(book: &mut Spreadsheet, report: &ScanReport) -> Result<(), String> {
+    let sheet = book.new_sheet("证据来源").map_err(|e| format!("cannot create sheet: {}", e))?;
+    set_row(sheet, 1, &["检查项 ID", "检查项", "来源"].iter().map(|x| x.to_string()).collect::<Vec<String>>());
+    let mut row = 2;
+    for r in report.cells.iter() {
+        for source in r.sources.iter() {
+            set_row(sheet, row, &[r.id.to_string(), r.column('A').join(" "), source.to_string()]);
+            row += 1;
+        }
+    }
+    Ok(())
+}
+
+fn write_gbt22239_sheets(book: &mut Spreadsheet, results: &[GuardCell]) -> Result<(), String> {
+    let mut reqs = HashMap::new();
+    for r in results {
+        for (id, status) in r.statuses() {
+            reqs.insert(id, status);
+        }
+    }
+
+    let sheet = book.new_sheet("等保对照汇总").map_err(|e| format!("cannot create sheet: {}", e))?;
+    set_row(sheet, 1, &["条款", "控制点", "要求项", "检查项数", "测评结论"].iter().map(|x| x.to_string()).collect::<Vec<String>>());
+    for (idx, clause) in gbt22239::clauses().iter().enumerate() {
+        let passed = clause.requirements.iter()
+            .filter_map(|id| reqs.get(id).map(|&x| x))
+            .collect::<Vec<Status>>();
+        set_row(sheet, idx + 2, &[
+            clause.id.to_string(),
+            clause.control.to_string(),
+            clause.text.to_string(),
+            passed.len().to_string(),
+            gbt22239::verdict(&passed).to_string(),
+        ]);
+    }
+
+    let sheet = book.new_sheet("等保对照明细").map_err(|e| format!("cannot create sheet: {}", e))?;
+    set_row(sheet, 1, &["要求ID", "要求内容", "检查结果", "条款", "控制点"].iter().map(|x| x.to_string()).collect::<Vec<String>>());
+    let mut row = 2;
+    for r in results {
+        for (id, status) in r.statuses() {
+            let (clause_id, control) = match gbt22239::clause_of(&id) {
+                Some(clause) => (clause.id.to_string(), clause.control.to_string()),
+                None => ("".to_string(), "".to_string()),
+            };
+            set_row(sheet, row, &[
+                id.to_string(),
+                i18n::tr(&id).to_string(),
+                gbt22239::verdict(&[status]).to_string(),
+                clause_id,
+                control,
+            ]);
+            row += 1;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let dst = dir.path().join("report.xlsx");
+    assert_eq!(None, backup(&dst).unwrap());
+    std::fs::write(&dst, "first").unwrap();
+    assert_eq!(Some(dir.path().join("report.bak-1.xlsx")), backup(&dst).unwrap());
+    std::fs::write(&dst, "second").unwrap();
+    assert_eq!(Some(dir.path().join("report.bak-2.xlsx")), backup(&dst).unwrap());
+    assert!(!dst.exists());
+    assert_eq!("first", std::fs::read_to_string(dir.path().join("report.bak-1.xlsx")).unwrap());
+}
+
+#[test]
+fn test_yaml() {
+    let report = ScanReport::demo();
+    let yaml = to_yaml(&report, false).unwrap();
+    assert!(yaml.starts_with("schema_version: 2\nmeta:\n  hostname: demo-host\n"));
+    assert!(!yaml.contains(&report.meta.started_at));
+    assert!(yaml.contains("- id: acct.expired\n"));
+    assert!(yaml.contains("status: partial\n"));
+    assert_eq!(yaml, to_yaml(&report, false).unwrap());
+    assert!(to_yaml(&report, true).unwrap().contains(&report.meta.started_at));
+}
@@ -0,0 +1,66 @@
+//! GB/T 22239-2019 等级保护基本要求条款对照
+
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+
+use crate::schema::Status;
+
+static MAPPING: Lazy<Mapping> = Lazy::new(|| {
+    toml::from_str(include_str!("../assets/gbt22239.toml")).unwrap()
+});
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Mapping {
+    pub clause: Vec<Clause>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Clause {
+    // 条款编号, 例如 "8.1.4.1 a)"
+    pub id: String,
+    // 控制点, 例如 "身份鉴别"
+    pub control: String,
+    pub text: String,
+    pub requirements: Vec<String>,
+}
+
+pub fn clauses() -> &'static [Clause] {
+    &MAPPING.clause
+}
+
+/// 查找安全要求所属的条款
+pub fn clause_of(req: &str) -> Option<&'static Clause> {
+    clauses().iter().find(|x| x.requirements.iter().any(|r| r == req))
+}
+
+/// 按测评结论的习惯用语汇总一个条款下各要求的检查结果, 部分自动检查的要求其余条件需人工核查
+pub fn verdict(results: &[Status]) -> &'static str {
+    let checked = results.iter().filter(|&&x| x != Status::Manual).collect::<Vec<&Status>>();
+    if checked.len() == 0 {
+        "需人工核查"
+    } else if checked.iter().all(|&&x| x != Status::Fail) {
+        if results.iter().all(|&x| x == Status::Pass) {
+            "符合"
+        } else {
+            "部分符合(其余需人工核查)"
+        }
+    } else if checked.iter().any(|&&x| x != Status::Fail) {
+        "部分符合"
+    } else {
+        "不符合"
+    }
+}
+
+#[test]
+fn test_mapping() {
+    assert!(clauses().len() > 0);
+    assert_eq!("8.1.4.4 b)", clause_of("net.port.445").unwrap().id);
+    assert!(clause_of("no.such.id").is_none());
+
+    assert_eq!("符合", verdict(&[Status::Pass, Status::Pass]));
+    assert_eq!("部分符合", verdict(&[Status::Pass, Status::Fail]));
+    assert_eq!("不符合", verdict(&[Status::Fail]));
+    assert_eq!("需人工核查", verdict(&[Status::Manual]));
+    assert_eq!("部分符合(其余需人工核查)", verdict(&[Status::Pass, Status::Manual]));
+    assert_eq!("部分符合(其余需人工核查)", verdict(&[Status::Pass, Status::Partial]));
+}
@@ -0,0 +1,258 @@
+//! 安全要求文本目录
+//!
+//! 每条安全要求都使用稳定的 ID 作为键(例如 `acct.umask`, `net.port.445`), 导出的表格、界面与
+//! JSON 结果都通过 ID 引用要求, 显示文本统一在这里查找, 以保证各处及各语言版本一致.
+
+use once_cell::sync::Lazy;
+
+use crate::policy;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+// 随 policy.toml 变化的阈值在文本中写为 "{passwd.min_len}" 等占位符, 首次查找时按策略替换
+static RENDERED: Lazy<Vec<(String, String)>> = Lazy::new(|| {
+    let (zh, en) = (placeholders(Lang::Zh), placeholders(Lang::En));
+    CATALOG.iter().map(|&(_, z, e)| (render(z, &zh), render(e, &en))).collect()
+});
+
+// (ID, 中文, English)
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("category.account", "身份鉴别与访问控制", "Identification and access control"),
+    ("category.network", "网络与服务", "Network and services"),
+    ("category.audit", "安全审计", "Security audit"),
+    ("category.system", "系统配置", "System configuration"),
+
+    ("os.title", "操作系统", "Operating system"),
+
+    ("net.ip.title", "设备 IP", "Device IP"),
+
+    ("acct.title", "用户管理", "User management"),
+    ("acct.expired", "应删除或锁定过期帐户、无用帐户和隐藏账号", "Expired, unused and hidden accounts are removed or locked"),
+    ("acct.umask", "每个用户是否按要求开展权限设置", "Permissions of every user are set as required"),
+    ("acct.default_name", "不能使用默认用户名，例如：root、superadmin、administrator等", "Default user names such as root, superadmin or administrator are not used"),
+    ("acct.system_shell", "系统账户(UID小于1000, root除外)的shell为nologin或false", "System accounts (UID below 1000 except root) use a nologin or false shell"),
+
+    ("passwd.title", "密码复杂度配置", "Password complexity"),
+    ("passwd.min_len", "密码长度不小于{passwd.min_len}位", "Password length is at least {passwd.min_len}"),
+    ("passwd.combination", "采取字母、数字和特殊字符的混合组合", "Password mixes letters, digits and special characters"),
+    ("passwd.username", "密码与用户名不相同", "Password differs from the user name"),
+    ("passwd.max_days", "密码更新周期{passwd.max_days}天", "Password is changed every {passwd.max_days} days"),
+    ("passwd.min_days", "密码最短使用期限不少于{passwd.min_days}天(PASS_MIN_DAYS)", "Password minimum age is at least {passwd.min_days} day(s) (PASS_MIN_DAYS)"),
+    ("passwd.warn_age", "密码过期前至少提前{passwd.warn_age}天提醒(PASS_WARN_AGE)", "Users are warned at least {passwd.warn_age} days before password expiry (PASS_WARN_AGE)"),
+
+    ("session.title", "登录终端的操作超时锁定", "Login session timeout lock"),
+    ("session.tmout", "设置操作超时为小于或等于{session.tmout}", "Session timeout is {session.tmout} or less"),
+    ("session.readonly", "TMOUT设为只读, 用户不能自行取消", "TMOUT is read-only and cannot be unset by users"),
+    ("session.ssh_idle", "SSH空闲连接{session.tmout}内断开(ClientAliveInterval/CountMax)", "Idle SSH connections are closed within {session.tmout} (ClientAliveInterval/CountMax)"),
+
+    ("net.port.title", "高危端口封闭", "High-risk ports closed"),
+    ("net.port.135", "关闭135", "Port 135 closed"),
+    ("net.port.137", "关闭137", "Port 137 closed"),
+    ("net.port.138", "关闭138", "Port 138 closed"),
+    ("net.port.139", "关闭139", "Port 139 closed"),
+    ("net.port.445", "关闭445", "Port 445 closed"),
+    ("net.port.3389", "关闭3389", "Port 3389 closed"),
+
+    ("svc.title", "关闭服务", "Services disabled"),
+    ("svc.email", "E-Mail", "E-Mail"),
+    ("svc.ftp", "FTP", "FTP"),
+    ("svc.ftp_hardened", "FTP未关闭时已加固(禁止匿名、限制目录、启用SSL、登录提示)", "FTP, if enabled, is hardened (no anonymous login, chroot, SSL, banner)"),
+    ("svc.telnet", "telnet", "telnet"),
+    ("svc.rlogin", "rlogin", "rlogin"),
+    ("svc.netbios", "NetBIOS", "NetBIOS"),
+    ("svc.dhcp", "DHCP", "DHCP"),
+    ("svc.smb", "SMB", "SMB"),
+    ("svc.snmp", "SNMPV3以下版本", "SNMP below v3"),
+    ("svc.remote_desktop", "远程桌面", "Remote desktop"),
+    ("svc.minimum", "最小服务原则关闭其他非必要服务", "Other unnecessary services are disabled (least service)"),
+
+    ("audit.title", "远程访问/系统审计/审计内容", "Remote access / system audit / audit content"),
+    ("audit.syslog", "开启系统日志进程(syslog)", "System log daemon (syslog) is running"),
+    ("audit.auditd", "开启审计进程(auditd)", "Audit daemon (auditd) is running"),
+    ("audit.ssh_log", "开启SSH日志审计", "SSH logging is enabled"),
+    ("audit.retention", "审计内容保存6个月", "Audit records are kept for 6 months"),
+    ("audit.forward", "将审计内容发送到其他日志审计设备存储", "Audit records are forwarded to a separate log server"),
+    ("audit.content", "至少包括：用户的添加和删除、审计功能的启动和关闭、审计策略的调整、权限变更、系统资源的异常使用、重要的系统操作（如用户登录、退出）等", "Audit covers at least user changes, audit start/stop, audit policy changes, permission changes, abnormal resource usage and important operations such as login/logout"),
+    ("audit.sshd", "启用SSH", "SSH is enabled"),
+    ("audit.ssh_port", "修改SSH默认端口", "SSH default port is changed"),
+    ("audit.immutable", "审计配置已锁定不可修改(-e 2)", "Audit configuration is immutable (-e 2)"),
+    ("audit.space_left", "审计存储空间不足时告警或停机(space_left_action=email/halt)", "Low audit storage triggers email or halt (space_left_action=email/halt)"),
+    ("audit.conf_perm", "/etc/audit下的文件属于root且权限不宽于640", "Files under /etc/audit are owned by root with mode 640 or stricter"),
+    ("audit.ssh_hostbased", "SSH禁用基于主机的认证(HostbasedAuthentication no)", "SSH host-based authentication is disabled (HostbasedAuthentication no)"),
+    ("audit.ssh_ignore_rhosts", "SSH忽略.rhosts和.shosts文件(IgnoreRhosts yes)", "SSH ignores .rhosts and .shosts files (IgnoreRhosts yes)"),
+    ("audit.ssh_strict_modes", "SSH登录前检查用户文件的属主与权限(StrictModes yes)", "SSH checks ownership and modes of user files before login (StrictModes yes)"),
+
+    ("net.listen.title", "管理服务监听地址", "Management service listen addresses"),
+    ("net.listen.local", "数据库、缓存、容器API、监控采集等管理服务仅监听本机或管理网地址", "Databases, caches, container APIs and exporters listen only on localhost or the management address"),
+
+    ("mount.title", "临时目录挂载选项", "Temporary directory mount options"),
+    ("mount.separate", "/tmp、/var/tmp、/dev/shm为独立挂载的文件系统", "/tmp, /var/tmp and /dev/shm are separate mounts"),
+    ("mount.options", "/tmp、/var/tmp、/dev/shm挂载时启用noexec、nosuid、nodev", "/tmp, /var/tmp and /dev/shm are mounted with noexec, nosuid and nodev"),
+
+    ("cloud.title", "云主机元数据服务", "Cloud metadata service"),
+    ("cloud.imds", "普通用户不能未经鉴权访问云主机元数据服务(169.254.169.254)", "Unprivileged users cannot read the cloud metadata service (169.254.169.254) without authentication"),
+
+    ("ssh.title", "SSH默认口令", "SSH default credentials"),
+    ("ssh.default_cred", "不能使用默认口令登录SSH(如root/root、admin/admin)", "SSH login with default credentials (e.g. root/root, admin/admin) fails"),
+
+    ("kmod.title", "内核模块禁用", "Disabled kernel modules"),
+    ("kmod.dccp", "禁用并未加载dccp模块", "dccp module is disabled and not loaded"),
+    ("kmod.sctp", "禁用并未加载sctp模块", "sctp module is disabled and not loaded"),
+    ("kmod.rds", "禁用并未加载rds模块", "rds module is disabled and not loaded"),
+    ("kmod.tipc", "禁用并未加载tipc模块", "tipc module is disabled and not loaded"),
+    ("kmod.cramfs", "禁用并未加载cramfs模块", "cramfs module is disabled and not loaded"),
+    ("kmod.usb-storage", "禁用并未加载usb-storage模块", "usb-storage module is disabled and not loaded"),
+    ("kmod.firewire-core", "禁用并未加载firewire模块", "firewire module is disabled and not loaded"),
+
+    ("x11.title", "X11远程显示", "X11 remote display"),
+    ("x11.forwarding", "SSH关闭X11转发(X11Forwarding no)", "SSH X11 forwarding is disabled (X11Forwarding no)"),
+    ("x11.tcp", "X服务不监听TCP端口(6000+)", "X server does not listen on TCP (6000+)"),
+    ("x11.xhost", "X服务开启访问控制(xhost)", "X server access control is enabled (xhost)"),
+
+    ("kernel.title", "内核安全参数", "Kernel hardening parameters"),
+    ("kernel.panic", "内核崩溃后自动重启(kernel.panic)", "System reboots after a kernel panic (kernel.panic)"),
+    ("kernel.kptr_restrict", "隐藏内核符号地址(kernel.kptr_restrict)", "Kernel pointers are hidden (kernel.kptr_restrict)"),
+    ("kernel.dmesg_restrict", "普通用户不能读取内核日志(kernel.dmesg_restrict)", "Unprivileged users cannot read the kernel log (kernel.dmesg_restrict)"),
+    ("kernel.kdump", "启用kdump保存内核崩溃转储", "kdump is enabled to save kernel crash dumps"),
+
+    ("time.title", "时区与语言环境", "Timezone and locale"),
+    ("time.timezone", "系统时区符合要求(如Asia/Shanghai)", "System timezone is as required (e.g. Asia/Shanghai)"),
+    ("time.locale", "语言环境符合要求(默认要求UTF-8编码)", "Locale is as required (UTF-8 by default)"),
+    ("time.rtc", "硬件时钟按要求使用UTC或本地时间", "Hardware clock uses UTC or local time as required"),
+
+    ("pam.title", "PAM配置一致性", "PAM configuration consistency"),
+    ("pam.unique", "口令复杂度模块(pam_cracklib/pam_pwquality)未重复配置", "Password quality module (pam_cracklib/pam_pwquality) is configured only once"),
+    ("pam.order", "复杂度与登录失败处理模块不会被之前的sufficient规则跳过", "Password quality and login failure modules are not skipped by an earlier sufficient rule"),
+    ("pam.include", "sshd与login包含公共认证配置(common-auth/system-auth)", "sshd and login include the common auth stack (common-auth/system-auth)"),
+
+    ("sudo.title", "sudo提权记录", "sudo privilege escalation log"),
+    ("sudo.syslog", "sudo操作记录到系统日志(未配置Defaults !syslog)", "sudo usage is logged to syslog (no Defaults !syslog)"),
+
+    ("reboot.title", "更新后重启", "Reboot after updates"),
+    ("reboot.kernel", "运行的是已安装的最新内核", "The newest installed kernel is running"),
+    ("reboot.required", "没有需要重启才能生效的更新", "No installed updates are waiting for a reboot"),
+    ("limits.title", "资源限制", "Resource limits"),
+    ("limits.maxlogins", "限制每个用户的并发登录数(maxlogins)", "Concurrent logins per user are limited (maxlogins)"),
+    ("limits.nproc", "限制每个用户的最大进程数(nproc)", "Processes per user are limited (nproc)"),
+    ("limits.nofile", "限制每个用户打开的文件数(nofile)", "Open files per user are limited (nofile)"),
+    ("limits.systemd", "systemd 服务的默认资源限制未设为 infinity", "systemd default service limits are not infinity"),
+    ("sshkey.title", "SSH主机密钥", "SSH host keys"),
+    ("sshkey.dsa", "没有DSA主机密钥", "No DSA host keys"),
+    ("sshkey.rsa_size", "RSA主机密钥不少于2048位", "RSA host keys have at least 2048 bits"),
+    ("sshkey.algorithms", "主机密钥算法不允许ssh-rsa/ssh-dss(SHA-1)", "Host key algorithms exclude ssh-rsa/ssh-dss (SHA-1)"),
+    ("path.title", "root及服务账户的PATH", "PATH of root and service accounts"),
+    ("path.writable", "PATH中没有所有用户可写的目录", "PATH contains no world-writable directories"),
+    ("path.dot", "PATH中没有当前目录(.)或相对路径", "PATH contains no current directory (.) or relative paths"),
+    ("path.home", "PATH中没有普通用户主目录下的目录", "PATH contains no directories under user home directories"),
+    ("integrity.title", "关键文件完整性", "Integrity of critical files"),
+    ("integrity.files", "关键程序及配置文件与完整性基线一致", "Critical binaries and configuration files match the integrity baseline"),
+
+    ("fw.title", "设定终端接入方式、网络地址范围", "Terminal access method and address range"),
+    ("fw.whitelist", "iptables白名单(whitelist链)限制了允许接入的地址范围", "The iptables whitelist chain limits the allowed address range"),
+
+    ("hist.title", "his命令", "Command history"),
+    ("hist.delete", "删除系统his命令", "Shell history is disabled"),
+    ("hist.retain", "保留命令历史并记录时间戳(HISTTIMEFORMAT)", "Shell history is retained with timestamps (HISTTIMEFORMAT)"),
+    ("hist.auditd", "命令执行记录发送到审计系统(auditd execve)", "Command execution is recorded by auditd (execve)"),
+
+    ("console.title", "控制台访问限制", "Console access restriction"),
+    ("console.securetty", "root仅允许从指定终端登录(securetty)", "root login is limited to approved terminals (securetty)"),
+    ("console.ctrlaltdel", "禁用Ctrl+Alt+Del重启", "Ctrl+Alt+Del reboot is disabled"),
+
+    ("home.title", "用户主目录权限", "Home directory permissions"),
+    ("home.owner_mode", "用户主目录属主为用户本人且权限不宽于750", "Home directories are owned by their user with mode 750 or stricter"),
+    ("home.dotfiles", "用户配置文件(.bashrc/.profile等)不允许组或其他用户写入", "Dotfiles (.bashrc/.profile etc.) are not group or world writable"),
+    ("home.trust_files", "用户主目录中不存在.rhosts、.forward文件", "No .rhosts or .forward files in home directories"),
+
+    ("devtools.title", "开发工具", "Development tools"),
+    ("devtools.absent", "生产主机未安装编译器、调试器等开发工具(gcc、make、gdb、cpan等)", "No compilers or development tools (gcc, make, gdb, cpan etc.) on production hosts"),
+];
+
+/// 按指定语言查找 ID 对应的文本, 目录中不存在时返回 ID 本身
+pub fn text(id: &str, lang: Lang) -> &str {
+    for (idx, &(key, _, _)) in CATALOG.iter().enumerate() {
+        if key == id {
+            let (zh, en) = &RENDERED[idx];
+            return match lang {
+                Lang::Zh => zh,
+                Lang::En => en,
+            };
+        }
+    }
+    id
+}
+
+fn placeholders(lang: Lang) -> Vec<(&'static str, String)> {
+    let policy = policy::get();
+    let tmout = policy.session.tmout;
+    let tmout = match (tmout % 60, lang) {
+        (0, Lang::Zh) => format!("{}分钟", tmout / 60),
+        (0, Lang::En) => format!("{} minutes", tmout / 60),
+        (_, Lang::Zh) => format!("{}秒", tmout),
+        (_, Lang::En) => format!("{} seconds", tmout),
+    };
+    vec![
+        ("{passwd.min_len}", policy.passwd.min_len.to_string()),
+        ("{passwd.max_days}", policy.passwd.max_days.to_string()),
+        ("{passwd.min_days}", policy.passwd.min_days.to_string()),
+        ("{passwd.warn_age}", policy.passwd.warn_age.to_string()),
+        ("{session.tmout}", tmout),
+    ]
+}
+
+fn render(text: &str, placeholders: &[(&str, String)]) -> String {
+    placeholders.iter().fold(text.to_string(), |text, (key, value)| text.replace(key, value))
+}
+
+/// ID 是否在目录中, 可在常量中调用, 供 `req!` 在编译时检查
+pub const fn known(id: &str) -> bool {
+    let mut i = 0;
+    while i < CATALOG.len() {
+        if same(CATALOG[i].0.as_bytes(), id.as_bytes()) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn same(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// 查找 ID 对应的中文文本
+pub fn tr(id: &str) -> &str {
+    text(id, Lang::Zh)
+}
+
+#[test]
+fn test_catalog() {
+    assert_eq!("关闭445", tr("net.port.445"));
+    assert_eq!("Port 445 closed", text("net.port.445", Lang::En));
+    assert_eq!("no.such.id", tr("no.such.id"));
+    assert_eq!("密码长度不小于8位", tr("passwd.min_len"));
+    assert_eq!("Session timeout is 10 minutes or less", text("session.tmout", Lang::En));
+    assert!(RENDERED.iter().all(|(zh, en)| !zh.contains("{") && !en.contains("{")));
+    assert!(known("acct.umask"));
+    assert!(!known("acct.umas"));
+
+    let mut ids = CATALOG.iter().map(|x| x.0).collect::<Vec<&str>>();
+    let total = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(total, ids.len());
+}
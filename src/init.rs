@@ -0,0 +1,177 @@
+//! 识别系统的服务管理方式, 并以统一的接口查询服务状态
+//!
+//! 支持 systemd、SysV(chkconfig/service)及 OpenRC, 服务检查与审计检查都通过这里查询.
+
+use std::path::Path;
+
+use crate::util;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InitSystem {
+    Systemd,
+    SysV,
+    OpenRC,
+}
+
+pub trait ServiceManager {
+    /// 开机自动启动的服务名, 不含 ".service" 等后缀
+    fn list_enabled_services(&self) -> Result<Vec<String>, String>;
+    /// 服务是否正在运行
+    fn is_active(&self, service: &str) -> bool;
+}
+
+impl InitSystem {
+    /// 与 sd_booted() 相同, 以 /run/systemd/system 判断是否由 systemd 启动; 设置了备用根目录时按其中安装的 init 判断
+    pub fn detect() -> InitSystem {
+        if let Some(root) = util::root() {
+            return InitSystem::installed(root);
+        }
+        if Path::new("/run/systemd/system").is_dir() {
+            InitSystem::Systemd
+        } else if Path::new("/run/openrc").is_dir() {
+            InitSystem::OpenRC
+        } else {
+            InitSystem::SysV
+        }
+    }
+
+    // 未运行的系统只能看磁盘上的文件: /sbin/init 指向 systemd 或只安装了 systemd 时为 systemd, 安装了 openrc 时为 OpenRC
+    fn installed(root: &Path) -> InitSystem {
+        let init = root.join("sbin/init");
+        let systemd = ["lib/systemd/systemd", "usr/lib/systemd/systemd"].iter().any(|x| root.join(x).exists());
+        match std::fs::read_link(&init) {
+            Ok(target) if target.to_string_lossy().contains("systemd") => InitSystem::Systemd,
+            _ if root.join("sbin/openrc").exists() || root.join("sbin/openrc-init").exists() => InitSystem::OpenRC,
+            Err(_) if systemd && !init.exists() => InitSystem::Systemd,
+            _ => InitSystem::SysV,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InitSystem::Systemd => "systemd",
+            InitSystem::SysV => "SysV",
+            InitSystem::OpenRC => "OpenRC",
+        }
+    }
+
+    pub fn manager(&self) -> Box<dyn ServiceManager> {
+        match self {
+            InitSystem::Systemd => Box::new(Systemd),
+            InitSystem::SysV => Box::new(SysV),
+            InitSystem::OpenRC => Box::new(OpenRC),
+        }
+    }
+}
+
+struct Systemd;
+struct SysV;
+struct OpenRC;
+
+impl ServiceManager for Systemd {
+    // 包括 socket 激活的服务, 例如 telnet.socket
+    fn list_enabled_services(&self) -> Result<Vec<String>, String> {
+        let cmd = "systemctl list-unit-files --type=service,socket --state=enabled --no-legend --no-pager";
+        let r = util::runcmd(cmd, None).map_err(|e| format!("cannot run '{}': {}", cmd, e))?;
+        Ok(parse_unit_files(&r))
+    }
+
+    fn is_active(&self, service: &str) -> bool {
+        match util::runcmd(&format!("systemctl is-active {}", service), None) {
+            Ok(r) => r.trim() == "active",
+            Err(_) => false,
+        }
+    }
+}
+
+impl ServiceManager for SysV {
+    fn list_enabled_services(&self) -> Result<Vec<String>, String> {
+        let r = util::runcmd("chkconfig --list", None).map_err(|e| format!("cannot run 'chkconfig --list': {}", e))?;
+        Ok(parse_chkconfig(&r))
+    }
+
+    // LSB 规定 status 退出码为 0 表示正在运行, 与输出的语言无关
+    fn is_active(&self, service: &str) -> bool {
+        util::runcmd(&format!("service {} status", service), None).is_ok()
+    }
+}
+
+impl ServiceManager for OpenRC {
+    fn list_enabled_services(&self) -> Result<Vec<String>, String> {
+        let r = util::runcmd("rc-update show", None).map_err(|e| format!("cannot run 'rc-update show': {}", e))?;
+        Ok(parse_rc_update(&r))
+    }
+
+    fn is_active(&self, service: &str) -> bool {
+        util::runcmd(&format!("rc-service {} status", service), None).is_ok()
+    }
+}
+
+// 每行形如 "sshd.service enabled enabled", 模板单元保留 "@"
+fn parse_unit_files(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|x| x.split_whitespace().next())
+        .map(|x| x.trim_end_matches(".service").trim_end_matches(".socket").to_string())
+        .collect()
+}
+
+// 独立服务形如 "sshd  0:off 1:off 2:on 3:on 4:on 5:on 6:off", 在 2 至 5 运行级别都启用时视为启用;
+// xinetd 管理的服务形如 "  telnet:  on". 中文环境下状态为 "启用"/"关闭"
+fn parse_chkconfig(text: &str) -> Vec<String> {
+    let is_on = |status: &str| status == "on" || status == "启用" || status == "开";
+    let mut services = vec![];
+    for line in text.lines() {
+        let items = line.split_whitespace().collect::<Vec<&str>>();
+        if items.len() == 8 {
+            let enabled = items[3..7].iter().all(|x| x.split(":").nth(1).map_or(false, |x| is_on(x)));
+            if enabled {
+                services.push(items[0].to_string());
+            }
+        } else if items.len() == 2 && items[0].ends_with(":") && is_on(items[1]) {
+            services.push(items[0].trim_end_matches(":").to_string());
+        }
+    }
+    services
+}
+
+// 每行形如 "  sshd | default", 只列出已加入运行级别的服务
+fn parse_rc_update(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|x| x.split_once("|"))
+        .filter(|(_, levels)| levels.trim().len() > 0)
+        .map(|(name, _)| name.trim().to_string())
+        .collect()
+}
+
+#[test]
+fn test_parse_services() {
+    let text = "sshd.service enabled enabled\ngetty@.service enabled enabled\ntelnet.socket enabled disabled\n";
+    assert_eq!(vec!["sshd", "getty@", "telnet"], parse_unit_files(text));
+
+    let text = "auditd         \t0:off\t1:off\t2:on\t3:on\t4:on\t5:on\t6:off\n\
+                postfix        \t0:off\t1:off\t2:on\t3:on\t4:off\t5:on\t6:off\n\
+                snmpd          \t0:关闭\t1:关闭\t2:启用\t3:启用\t4:启用\t5:启用\t6:关闭\n\
+                \n\
+                xinetd based services:\n\
+                \ttelnet:        \ton\n\
+                \trsync:         \toff\n";
+    assert_eq!(vec!["auditd", "snmpd", "telnet"], parse_chkconfig(text));
+
+    let text = "             sshd | default\n            local |      \n         sysfs | sysinit\n";
+    assert_eq!(vec!["sshd", "sysfs"], parse_rc_update(text));
+}
+
+#[test]
+fn test_installed() {
+    let root = tempfile::tempdir().unwrap();
+    assert_eq!(InitSystem::SysV, InitSystem::installed(root.path()));
+    std::fs::create_dir_all(root.path().join("sbin")).unwrap();
+    std::fs::create_dir_all(root.path().join("lib/systemd")).unwrap();
+    std::fs::write(root.path().join("lib/systemd/systemd"), "").unwrap();
+    assert_eq!(InitSystem::Systemd, InitSystem::installed(root.path()));
+    std::fs::write(root.path().join("sbin/init"), "").unwrap();
+    assert_eq!(InitSystem::SysV, InitSystem::installed(root.path()));
+    std::fs::remove_file(root.path().join("sbin/init")).unwrap();
+    std::os::unix::fs::symlink("../lib/systemd/systemd", root.path().join("sbin/init")).unwrap();
+    assert_eq!(InitSystem::Systemd, InitSystem::installed(root.path()));
+}
@@ -0,0 +1,146 @@
+//! 关键文件完整性基线
+//!
+//! `baseline create` 记录策略 `[integrity]` 中关键路径下各文件的 SHA-256, 保存到数据目录下的 `integrity.json`;
+//! `baseline verify` 及 integrity 检查项重新计算并与之比较, 列出内容变化、缺失及新增的文件. 目录递归记录,
+//! 符号链接不跟随也不记录. 软件包升级等预期的变更后需重新建立基线.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+
+use crate::config;
+use crate::policy;
+use crate::report;
+
+/// 建立时的关键路径及其中各文件的 SHA-256, 无法读取的文件记录为空值
+#[derive(Serialize, Deserialize)]
+pub struct IntegrityBaseline {
+    pub created_at: String,
+    pub paths: Vec<String>,
+    pub files: BTreeMap<String, String>,
+}
+
+/// 与基线相比变化的文件
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub changed: Vec<String>,
+    pub missing: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.changed.len() == 0 && self.missing.len() == 0 && self.added.len() == 0
+    }
+}
+
+/// 基线文件位置
+pub fn path() -> PathBuf {
+    config::data_dir().join("integrity.json")
+}
+
+// 递归列出路径下的普通文件
+fn walk(path: &Path, files: &mut Vec<PathBuf>) {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return,
+    };
+    if meta.is_file() {
+        files.push(path.to_path_buf());
+    } else if meta.is_dir() {
+        let mut entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|x| x.ok()).map(|x| x.path()).collect::<Vec<PathBuf>>(),
+            Err(e) => {
+                eprintln!("cannot read {}: {:?}", path.display(), e);
+                return;
+            },
+        };
+        entries.sort();
+        for entry in entries {
+            walk(&entry, files);
+        }
+    }
+}
+
+fn sha256(path: &Path) -> Option<String> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).ok()?;
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// 计算关键路径下各文件的 SHA-256
+pub fn hash(paths: &[String]) -> BTreeMap<String, String> {
+    let mut files = vec![];
+    for path in paths {
+        walk(Path::new(path), &mut files);
+    }
+    files.into_iter()
+        .map(|x| {
+            let hash = sha256(&x).unwrap_or_default();
+            (x.display().to_string(), hash)
+        })
+        .collect()
+}
+
+/// 按策略建立基线并保存, 覆盖已有的基线
+pub fn create() -> Result<IntegrityBaseline, String> {
+    let paths = policy::get().integrity.paths.clone();
+    let files = hash(&paths);
+    let baseline = IntegrityBaseline { created_at: report::now(), paths, files };
+    let unreadable = baseline.files.values().filter(|x| x.len() == 0).count();
+    if unreadable > 0 {
+        eprintln!("{} 个文件无法读取, 以空值记录", unreadable);
+    }
+    let path = path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {:?}", dir.display(), e))?;
+    }
+    let text = serde_json::to_string_pretty(&baseline).map_err(|e| format!("cannot serialize baseline: {:?}", e))?;
+    std::fs::write(&path, text).map_err(|e| format!("cannot write {}: {:?}", path.display(), e))?;
+    Ok(baseline)
+}
+
+/// 读取保存的基线, 尚未建立时为 None
+pub fn load() -> Result<Option<IntegrityBaseline>, String> {
+    let path = path();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("cannot read {}: {:?}", path.display(), e))?;
+    serde_json::from_str(&text).map(Some).map_err(|e| format!("invalid baseline {}: {:?}", path.display(), e))
+}
+
+/// 按基线建立时的关键路径重新计算并比较
+pub fn verify(baseline: &IntegrityBaseline) -> Changes {
+    compare(&baseline.files, &hash(&baseline.paths))
+}
+
+fn compare(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Changes {
+    let mut changes = Changes::default();
+    for (path, hash) in old.iter() {
+        match new.get(path) {
+            Some(x) if x == hash => {},
+            Some(_) => changes.changed.push(path.to_string()),
+            None => changes.missing.push(path.to_string()),
+        }
+    }
+    changes.added = new.keys().filter(|x| !old.contains_key(*x)).cloned().collect();
+    changes
+}
+
+#[test]
+fn test_compare() {
+    let old = vec![("/bin/a", "1"), ("/bin/b", "2"), ("/bin/c", "3")].into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<String, String>>();
+    let new = vec![("/bin/a", "1"), ("/bin/b", "9"), ("/bin/d", "4")].into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string())).collect::<BTreeMap<String, String>>();
+    let changes = compare(&old, &new);
+    assert_eq!(vec!["/bin/b".to_string()], changes.changed);
+    assert_eq!(vec!["/bin/c".to_string()], changes.missing);
+    assert_eq!(vec!["/bin/d".to_string()], changes.added);
+    assert!(compare(&old, &old).is_empty());
+    assert_eq!(Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string()), sha256(Path::new("/dev/null")));
+}
@@ -0,0 +1,58 @@
+//! 防止同一主机上同时运行多个扫描
+//!
+//! 通过对临时目录下的锁文件加排他锁(flock)实现, 进程退出时锁会被自动释放, 不会因异常退出而残留.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use fs2::FileExt;
+
+pub struct ScanLock {
+    // 持有文件即持有锁
+    file: File,
+}
+
+pub fn path() -> PathBuf {
+    std::env::temp_dir().join("sh-sds.lock")
+}
+
+/// 获取扫描锁, 已有扫描在进行时返回描述该扫描的错误信息; `force` 为 true 时忽略已有的扫描
+pub fn acquire(force: bool) -> Result<Option<ScanLock>, String> {
+    acquire_at(&path(), force)
+}
+
+fn acquire_at(path: &Path, force: bool) -> Result<Option<ScanLock>, String> {
+    // 其他用户创建的锁文件可能没有写权限, 只读打开同样可以加锁
+    let file = OpenOptions::new().read(true).write(true).create(true).open(path)
+        .or_else(|_| File::open(path))
+        .map_err(|e| format!("cannot open lock file {}: {:?}", path.display(), e))?;
+
+    if let Err(_) = file.try_lock_exclusive() {
+        if force {
+            return Ok(None);
+        }
+        let mut pid = String::new();
+        let _ = (&file).read_to_string(&mut pid);
+        return Err(format!("扫描已在进行中(pid: {}), 请等待其完成, 或使用 --force 强制扫描", pid.trim()));
+    }
+
+    let mut lock = ScanLock { file };
+    let _ = lock.file.set_len(0);
+    let _ = lock.file.seek(SeekFrom::Start(0));
+    let _ = write!(lock.file, "{}", std::process::id());
+    Ok(Some(lock))
+}
+
+#[test]
+fn test_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sh-sds.lock");
+    let lock = acquire_at(&path, false).unwrap();
+    assert!(lock.is_some());
+    // 同一进程中重新打开文件得到新的文件描述, 同样会被排他锁阻止
+    assert!(acquire_at(&path, false).is_err());
+    assert!(acquire_at(&path, true).unwrap().is_none());
+    drop(lock);
+    assert!(acquire_at(&path, false).unwrap().is_some());
+}
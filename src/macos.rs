@@ -0,0 +1,60 @@
+//! macOS 上的部分检查实现
+//!
+//! 仅用于在 Mac 上开发与演示界面, 其余依赖 Linux 文件与命令的检查项在这里只输出标题并提示不支持.
+
+use std::collections::HashMap;
+
+use crate::policy;
+use crate::sysguard::{req, GuardItem, ReportRow};
+use crate::util;
+
+pub fn check(item: &GuardItem, row: ReportRow) -> ReportRow {
+    match item {
+        GuardItem::OS => {
+            // sw_vers 输出形如 "ProductName:\tmacOS\nProductVersion:\t13.4\nBuildVersion:\t22F66"
+            if let Ok(r) = util::runcmd("sw_vers", None) {
+                let version = r.lines()
+                    .filter_map(|x| x.split(":").nth(1))
+                    .map(|x| x.trim())
+                    .collect::<Vec<&str>>();
+                row.info(&version.join(" "))
+            } else {
+                eprintln!("cannot run command 'sw_vers'");
+                row
+            }
+        },
+        GuardItem::PasswdComplexity => {
+            // pwpolicy 输出形如 "minChars=8 requiresAlpha=1 requiresNumeric=1 maxMinutesUntilChangePassword=259200"
+            let mut settings = HashMap::new();
+            if let Ok(r) = util::runcmd("pwpolicy -getglobalpolicy", None) {
+                for item in r.split_whitespace() {
+                    let kv = item.split("=").collect::<Vec<&str>>();
+                    if kv.len() == 2 {
+                        if let Ok(v) = kv[1].parse::<u32>() {
+                            settings.insert(kv[0].to_string(), v);
+                        }
+                    }
+                }
+            } else {
+                eprintln!("cannot run command 'pwpolicy -getglobalpolicy'");
+            }
+            let get = |key: &str| settings.get(key).map(|x| *x).unwrap_or(0);
+            let combination = get("requiresAlpha") > 0 && get("requiresNumeric") > 0 && get("requiresSymbol") > 0;
+            // maxMinutesUntilChangePassword 以分钟为单位
+            let max_minutes = get("maxMinutesUntilChangePassword");
+            let keys = vec!["minChars", "requiresAlpha", "requiresNumeric", "requiresSymbol", "maxMinutesUntilChangePassword"];
+            let values = keys.into_iter()
+                .map(|k| match settings.get(k) {
+                    Some(v) => format!("{}={}", k, v),
+                    None => format!("{} 未设置", k),
+                })
+                .collect::<Vec<String>>();
+            row.status(req!("passwd.min_len"), Some(get("minChars") >= policy::get().passwd.min_len))
+                .status(req!("passwd.combination"), Some(combination))
+                .status(req!("passwd.username"), None)
+                .status(req!("passwd.max_days"), Some(max_minutes > 0 && max_minutes <= policy::get().passwd.max_days.saturating_mul(24 * 60)))
+                .evidence(&values.join("\n"))
+        },
+        _ => row.evidence("macOS 暂不支持此项检查"),
+    }
+}
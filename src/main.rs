@@ -1,33 +1,174 @@
 mod util;
+mod parse;
+mod patterns;
+mod config;
+mod policy;
+mod i18n;
+mod init;
 mod sysguard;
+mod check;
+#[cfg(target_os = "macos")]
+mod macos;
+mod report;
+mod baseline;
+mod gbt22239;
+mod reference;
+mod export;
+mod schema;
+mod store;
+mod tui;
+mod lock;
+mod prereq;
+mod cli;
+mod upload;
+mod secrets;
+mod digest;
+mod integrity;
+#[cfg(target_os = "linux")]
+mod watch;
 
-use std::io::{Write};
-use std::fs::File;
-use std::path::Path;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use clap::Parser;
 
-use tempfile;
-use umya_spreadsheet;
 use fltk::{app, prelude::*, window::Window, button::Button, frame::Frame, *};
 use fltk::dialog::FileDialog;
 use fltk_theme::{widget_themes, WidgetTheme, ThemeType};
 
+use report::{ReportHeader, ScanReport};
+use policy::{AuditPolicy, HistoryMode, HistoryPolicy, HostPolicy, HostRole, KernelPolicy, LimitsPolicy, NetworkPolicy, PasswdPolicy, Policy, RtcMode, SessionPolicy, SshPolicy, TimePolicy};
+use schema::Status;
+use sysguard::Category;
+
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 static WIN_WIDTH: i32 = 512;
 static WIN_HEIGHT: i32 = 512;
 
+// 结果界面当前是否为操作员视图, 初始值来自配置, 在结果界面中切换
+static OPERATOR_VIEW: AtomicBool = AtomicBool::new(false);
+
+// 保存扫描结果界面的位置, 再次点击"扫描"时直接显示
+type PanelSlot = Rc<RefCell<Option<group::Scroll>>>;
+
 fn text_area(text: &str) -> text::TextDisplay {
+    let text = match util::truncate(text, config::get().max_evidence_chars()) {
+        Some(text) => format!("{}\n…(内容已截断, 完整内容请导出查看)", text),
+        None => text.to_string(),
+    };
     let mut textbuf = text::TextBuffer::default();
-    textbuf.set_text(text);
+    textbuf.set_text(&text);
     let mut disp = text::TextDisplay::default();
     disp.set_buffer(textbuf);
     disp.set_text_size(10);
     disp
 }
 
+// 常见发行版中文字体的安装位置
+static CJK_FONTS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-zenhei.ttc",
+    "/usr/share/fonts/wqy-zenhei/wqy-zenhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/truetype/arphic/uming.ttc",
+];
+
+// 随附的中文字体子集(Noto Sans SC, SIL OFL 1.1), 只含界面及报告中用到的汉字, 在系统中找不到中文字体时使用;
+// 以 embedded-font 特性构建, 字体文件由 `make font` 生成
+#[cfg(feature = "embedded-font")]
+static EMBEDDED_FONT: &[u8] = include_bytes!("../assets/fonts/NotoSansSC-subset.otf");
+
+// FLTK 只能从文件加载字体, 随附的字体写入数据目录后加载
+#[cfg(feature = "embedded-font")]
+fn embedded_font() -> Option<String> {
+    let path = config::data_dir().join("fonts").join("NotoSansSC-subset.otf");
+    if std::fs::metadata(&path).map_or(true, |x| x.len() != EMBEDDED_FONT.len() as u64) {
+        let written = std::fs::create_dir_all(path.parent().unwrap()).and_then(|_| std::fs::write(&path, EMBEDDED_FONT));
+        if let Err(e) = written {
+            eprintln!("cannot write {}: {:?}", path.display(), e);
+            return None;
+        }
+    }
+    Some(path.to_string_lossy().to_string())
+}
+
+#[cfg(not(feature = "embedded-font"))]
+fn embedded_font() -> Option<String> {
+    None
+}
+
+// 随附的 FLTK 只有 X11 后端, Wayland 会话中经由 XWayland 运行; `--x11` 时文件对话框(GTK)同样使用 X11,
+// 与窗口位于同一显示并使用相同的缩放
+fn setup_display(x11: bool) -> Result<(), String> {
+    if x11 {
+        std::env::set_var("FLTK_BACKEND", "x11");
+        std::env::set_var("GDK_BACKEND", "x11");
+    }
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").map_or(false, |x| x == "wayland");
+    if wayland && std::env::var_os("DISPLAY").is_none() {
+        return Err("当前为 Wayland 会话但没有可用的 XWayland(未设置 DISPLAY), 请启用 XWayland 后重试, 或使用 tui 子命令".to_string());
+    }
+    Ok(())
+}
+
+// 在所有显示器上应用配置的缩放比例
+fn setup_scale() {
+    let scale = config::get().ui.scale;
+    if scale > 0.0 {
+        for n in 0..app::screen_count() {
+            app::set_screen_scale(n, scale);
+        }
+    }
+}
+
+// 在鼠标所在显示器的工作区内居中, 多显示器时不会出现在远处的显示器上或横跨两个显示器
+fn place_window(win: &mut Window) {
+    let (mx, my) = app::get_mouse();
+    let (x, y, w, h) = app::screen_work_area(app::screen_num(mx, my));
+    win.set_pos(x + (w - win.w()).max(0) / 2, y + (h - win.h()).max(0) / 2);
+}
+
+// 在英文等最小化安装的系统上, 默认字体不含中文字形, 界面中的中文会显示为方块. 依次尝试配置的字体、
+// 常见位置的中文字体、fontconfig 找到的中文字体以及随附的字体, 并替换界面的默认字体
+fn setup_font() {
+    let mut candidates = vec![];
+    let font = &config::get().ui.font;
+    if font.len() > 0 {
+        candidates.push(font.to_string());
+    }
+    candidates.extend(CJK_FONTS.iter().map(|x| x.to_string()));
+    if let Ok(r) = util::runcmd("fc-list :lang=zh file", None) {
+        candidates.extend(r.lines().map(|x| x.trim().trim_end_matches(":").to_string()).filter(|x| x.len() > 0));
+    }
+    candidates.extend(embedded_font());
+
+    for path in candidates {
+        if !std::path::Path::new(&path).exists() {
+            continue;
+        }
+        match enums::Font::load_font(&path) {
+            Ok(name) => {
+                enums::Font::set_font(enums::Font::Helvetica, &name);
+                return;
+            },
+            Err(e) => eprintln!("cannot load font {}: {:?}", path, e),
+        }
+    }
+    eprintln!("cannot find a CJK font, set ui.font in config.toml if Chinese text is not displayed");
+}
+
 struct TableCell {
     val: String,
     size: i32,
+    tooltip: String,
 }
 
 impl TableCell {
@@ -36,8 +177,26 @@ impl TableCell {
         TableCell {
             val,
             size,
+            tooltip: "".to_string(),
         }
     }
+
+    // 安全要求所在的单元格, 鼠标悬停时显示其中各要求的完整说明及对应条款
+    fn reqs(report: &ScanReport, pos: &str, size: i32) -> Self {
+        let mut cell = TableCell::new(report.get(pos), size);
+        let mut details = vec![];
+        for r in report.cells.iter().filter(|x| x.mp.contains_key(pos)) {
+            for (id, _) in r.reqs.iter().filter(|x| cell.val.contains(i18n::tr(&x.0))) {
+                let mut text = reference::details(id);
+                if r.partial.contains(id) {
+                    text.push_str("\n◐ 只能自动检查部分条件, 其余条件需人工核查");
+                }
+                details.push(text);
+            }
+        }
+        cell.tooltip = details.join("\n\n");
+        cell
+    }
 }
 
 enum TableBlockType {
@@ -51,14 +210,18 @@ fn table_block(cells: Vec<TableCell>, typ: TableBlockType) -> group::Flex {
         TableBlockType::Row => group::Flex::default().row(),
     };
     for cell in cells {
-        let text = text_area(&cell.val);
+        let mut text = text_area(&cell.val);
+        if cell.tooltip.len() > 0 {
+            text.set_tooltip(&cell.tooltip);
+        }
         block.set_size(&text, cell.size);
     }
     block.end();
     block
 }
 
-fn compound_row(subject: Vec<TableCell>, chklst: Vec<TableCell>, comments: Vec<TableCell>) -> group::Flex {
+// `sources` 为证据来源, 不为空时以小号灰色文字显示在备注下方
+fn compound_row(subject: Vec<TableCell>, chklst: Vec<TableCell>, comments: Vec<TableCell>, sources: &[String]) -> group::Flex {
     let mut row = group::Flex::default().row();
 
     // 第一列: 安全类型
@@ -74,9 +237,19 @@ fn compound_row(subject: Vec<TableCell>, chklst: Vec<TableCell>, comments: Vec<T
 
     // 第三列: 安全备注
     let mut seccmt = group::Flex::default().column();
-    for comment in comments {
+    let count = comments.len();
+    for (i, comment) in comments.into_iter().enumerate() {
         let text = text_area(&comment.val);
-        seccmt.set_size(&text, comment.size);
+        let caption = if i + 1 == count && sources.len() > 0 { 16 } else { 0 };
+        seccmt.set_size(&text, comment.size - caption);
+    }
+    if sources.len() > 0 {
+        let mut caption = Frame::default().with_label(&format!("来源：{}", sources.join("、")));
+        caption.set_label_size(10);
+        caption.set_label_color(enums::Color::from_rgb(128, 128, 128));
+        caption.set_align(enums::Align::Left | enums::Align::Inside | enums::Align::Clip);
+        caption.set_tooltip(&sources.join("\n"));
+        seccmt.set_size(&caption, 16);
     }
     seccmt.end();
     row.set_size(&seccmt, 150);
@@ -87,212 +260,853 @@ fn compound_row(subject: Vec<TableCell>, chklst: Vec<TableCell>, comments: Vec<T
     row
 }
 
-fn row(c1: TableCell, c2 :TableCell, c3: TableCell) -> group::Flex {
-    compound_row(vec![c1], vec![c2], vec![c3])
+// 结果界面中的一个检查项: 标题所在行, 及各安全要求单元格所在的行与高度(以单元格高度为单位)
+struct PanelRow {
+    row: u32,
+    cells: Vec<(u32, i32)>,
 }
 
-fn host_security_panel(scanbtn: Button) -> group::Scroll {
-    let cell_height = 45i32;
-    let bar_width = 10;
+// 由检查项注册表生成结果界面的布局: 按分类分组, 组内按台账中的行号排列, 报告中没有的检查项不显示;
+// 安全要求单元格每两条要求占一个单元格高度, 新增检查项时无需修改界面代码
+fn panel_layout(report: &ScanReport) -> Vec<(Category, Vec<PanelRow>)> {
+    let mut groups = vec![];
+    for category in Category::all() {
+        let mut rows = vec![];
+        for item in check::all().into_iter().filter(|x| x.category() == category) {
+            let cell = match report.cells.iter().find(|x| x.id == item.id()) {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let mut lines = cell.mp.keys()
+                .filter(|x| x.starts_with("B"))
+                .filter_map(|x| x[1..].parse::<u32>().ok())
+                .collect::<Vec<u32>>();
+            if lines.len() == 0 {
+                lines.push(item.row());
+            }
+            lines.sort();
+            let cells = lines.into_iter()
+                .map(|n| {
+                    let count = report.get(format!("B{}", n)).lines().count() as i32;
+                    (n, std::cmp::max(1, (count + 1) / 2))
+                })
+                .collect();
+            rows.push(PanelRow { row: item.row(), cells });
+        }
+        rows.sort_by_key(|x| x.row);
+        if rows.len() > 0 {
+            groups.push((category, rows));
+        }
+    }
+    groups
+}
 
-    let mut scroll = group::Scroll::default().with_size(WIN_WIDTH, WIN_HEIGHT - 20);
-    let mut parent = group::Flex::default_fill().column().with_size(WIN_WIDTH, cell_height * 25);
+// 将填充后的结果界面完整绘制到离屏表面(包括滚动区域外的部分), 作为打包导出的截图
+fn capture_panel(panel: &group::Flex) -> Result<(), String> {
+    let (w, h) = (panel.w(), panel.h());
+    let surface = surface::ImageSurface::new(w, h, false);
+    surface::ImageSurface::push_current(&surface);
+    draw::set_draw_color(enums::Color::White);
+    draw::draw_rectf(0, 0, w, h);
+    surface.draw(panel, 0, 0);
+    let image = surface.image();
+    surface::ImageSurface::pop_current();
+    let image = image.ok_or("cannot capture the results panel".to_string())?;
+    export::set_screenshot(image.data_w() as u32, image.data_h() as u32, &image.to_rgb_data())
+}
 
-    let mut button_group = group::Flex::default_fill().row();
-    let mut btn = Button::new(0, 0, 40, 40, "导出");
+// `panel` 不为空时在导出前对其截图
+// `ext` 为导出文件的扩展名, 用于在覆盖已存在的文件前确认
+fn export_button(label: &'static str, ext: &'static str, report: Rc<ScanReport>, panel: Option<group::Flex>, export: fn(&ScanReport, String) -> Result<String, String>) -> Button {
+    let mut btn = Button::new(0, 0, 40, 40, label);
     btn.set_callback(move |_| {
+        if let Some(warning) = report.stale_warning() {
+            if dialog::choice_default(&format!("{}\n是否仍然导出?", warning), "取消", "导出", "") != 1 {
+                return;
+            }
+        }
+        // 每次导出前确认操作人员及表头信息, 默认值来自配置或当前登录用户
+        let (operator, header) = match header_editor(&report) {
+            Some(x) => x,
+            None => return,
+        };
         let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseSaveFile);
-        dlg.set_option(dialog::FileDialogOptions::SaveAsConfirm);
+        dlg.set_preset_file(&export::default_name(&report));
         dlg.show();
         let filename = dlg.filename().to_string_lossy().to_string();
-        saveas(filename);
+        if filename.len() == 0 {
+            return;
+        }
+        // 显示已有文件的修改时间, 避免误覆盖之前已签字的报告
+        let target = PathBuf::from(export::with_extension(filename.clone(), ext));
+        if target.is_file() {
+            let action = if config::get().export.backup { "原文件将改名为 .bak-N 备份" } else { "原文件将被删除" };
+            let msg = format!("{} 已存在, 修改时间: {}\n{}, 是否继续?",
+                target.display(), util::mtime(&target).unwrap_or_default(), action);
+            if dialog::choice_default(&msg, "取消", "覆盖", "") != 1 {
+                return;
+            }
+        }
+        if let Some(panel) = panel.as_ref() {
+            if let Err(e) = capture_panel(panel) {
+                println!("{}", e);
+            }
+        }
+        let mut report = (*report).clone();
+        report.meta.operator = operator;
+        report.meta.header = header;
+        match export(&report, filename) {
+            Ok(path) => if let Err(e) = upload::upload(Path::new(&path)) {
+                println!("{}", e);
+                dialog::alert_default(&format!("报告已保存到 {}, 但上传失败: {}", path, e));
+            },
+            Err(e) => println!("{}", e),
+        }
     });
+    btn
+}
 
-    button_group.set_size(&btn, WIN_WIDTH / 2 - bar_width);
-    let mut btn = Button::new(0, 0, 40, 40, "返回");
+// 后台扫描的结果, 以及与该主机上一次记录的扫描相比新增的不符合项
+struct ScanResult {
+    report: ScanReport,
+    new_failures: Vec<String>,
+}
+
+// 需在记录本次扫描之前调用; 未启用结果数据库或没有历史扫描时为空
+fn new_failures(report: &ScanReport) -> Vec<String> {
+    let last = store::last_statuses(&report.meta.hostname);
+    if last.len() == 0 {
+        return vec![];
+    }
+    report.cells.iter()
+        .flat_map(|x| x.reqs.iter())
+        .filter(|(id, passed)| *passed == Some(false) && !last.iter().any(|(x, status)| x == id && *status == Status::Fail))
+        .map(|(id, _)| id.to_string())
+        .collect()
+}
+
+fn notify_scan(result: &ScanResult) {
+    let failed = result.report.cells.iter().flat_map(|x| x.reqs.iter()).filter(|x| x.1 == Some(false)).count();
+    let mut body = format!("{} 项安全要求不符合", failed);
+    if result.new_failures.len() > 0 {
+        let titles = result.new_failures.iter().map(|x| i18n::tr(x)).collect::<Vec<&str>>();
+        body = format!("{}, 其中新增 {} 项:\n{}", body, titles.len(), titles.join("\n"));
+    }
+    if let Err(e) = notify_rust::Notification::new().appname("SH-SDS").summary("安全加固检查完成").body(&body).show() {
+        println!("cannot show notification: {:?}", e);
+    }
+}
+
+// 策略设置中的一行: 左侧为说明, 右侧为输入控件
+fn policy_row<W: WidgetExt + Default>(parent: &mut group::Flex, label: &str) -> W {
+    let mut row = group::Flex::default().row();
+    let frame = Frame::default().with_label(label).with_align(enums::Align::Left | enums::Align::Inside);
+    row.set_size(&frame, 260);
+    let widget = W::default();
+    row.end();
+    parent.set_size(&row, 28);
+    widget
+}
+
+fn policy_input(parent: &mut group::Flex, label: &str, value: &str) -> input::Input {
+    let mut input: input::Input = policy_row(parent, label);
+    input.set_value(value);
+    input
+}
+
+fn policy_choice(parent: &mut group::Flex, label: &str, choices: &str, value: i32) -> menu::Choice {
+    let mut choice: menu::Choice = policy_row(parent, label);
+    choice.add_choice(choices);
+    choice.set_value(value);
+    choice
+}
+
+fn policy_check(parent: &mut group::Flex, label: &str, value: bool) -> button::CheckButton {
+    let check: button::CheckButton = policy_row(parent, label);
+    check.set_checked(value);
+    check
+}
+
+// 以表单编辑当前基线策略, 保存时校验并写入策略文件; 保存成功时返回 true
+fn policy_editor() -> bool {
+    let current = policy::get();
+    let mut win = Window::default().with_size(560, 770).with_label("基线策略设置");
+    place_window(&mut win);
+    win.make_modal(true);
+    let mut form = group::Flex::default_fill().column();
+    form.set_margin(10);
+
+    let role = policy_choice(&mut form, "主机角色", "生产主机|开发测试主机", if current.host.role == HostRole::Production { 0 } else { 1 });
+    let min_len = policy_input(&mut form, "口令最小长度(PASS_MIN_LEN)", &current.passwd.min_len.to_string());
+    let max_days = policy_input(&mut form, "口令最长使用天数(PASS_MAX_DAYS)", &current.passwd.max_days.to_string());
+    let min_days = policy_input(&mut form, "口令最短使用天数(PASS_MIN_DAYS)", &current.passwd.min_days.to_string());
+    let warn_age = policy_input(&mut form, "口令过期提前提醒天数(PASS_WARN_AGE)", &current.passwd.warn_age.to_string());
+    let tmout = policy_input(&mut form, "登录超时上限(秒, TMOUT)", &current.session.tmout.to_string());
+    let allowlist = policy_input(&mut form, "允许监听所有地址的服务(逗号分隔)", &current.network.listen_allowlist.join(","));
+    let history = policy_choice(&mut form, "命令历史", "尽量不保留|保留并审计", if current.history.mode == HistoryMode::Minimize { 0 } else { 1 });
+    let retain_size = policy_input(&mut form, "保留的历史记录条数", &current.history.retain_size.to_string());
+    let weak_test = policy_check(&mut form, "测试 SSH 默认口令", current.ssh.weak_credential_test);
+    let credentials = policy_input(&mut form, "测试的 用户名:口令(逗号分隔)", &current.ssh.credentials.join(","));
+    let panic_timeout = policy_input(&mut form, "内核崩溃后重启等待上限(秒)", &current.kernel.panic_timeout.to_string());
+    let kptr_restrict = policy_input(&mut form, "kernel.kptr_restrict 最小值", &current.kernel.kptr_restrict.to_string());
+    let dmesg_restrict = policy_input(&mut form, "kernel.dmesg_restrict 最小值", &current.kernel.dmesg_restrict.to_string());
+    let kdump = policy_check(&mut form, "要求启用 kdump", current.kernel.kdump);
+    let timezone = policy_input(&mut form, "时区", &current.time.timezone);
+    let locale = policy_input(&mut form, "语言环境(为空时只要求 UTF-8)", &current.time.locale);
+    let rtc = policy_choice(&mut form, "硬件时钟", "UTC|本地时间", if current.time.rtc == RtcMode::Utc { 0 } else { 1 });
+    let extra_watches = policy_input(&mut form, "附加的审计监视文件(逗号分隔)", &current.audit.extra_watches.join(","));
+    let maxlogins = policy_input(&mut form, "每个用户并发登录数上限(maxlogins)", &current.limits.maxlogins.to_string());
+    let nproc = policy_input(&mut form, "每个用户进程数上限(nproc)", &current.limits.nproc.to_string());
+    let nofile = policy_input(&mut form, "每个用户打开文件数上限(nofile)", &current.limits.nofile.to_string());
+
+    Frame::default();
+    let mut buttons = group::Flex::default().row();
+    Frame::default();
+    let mut save = Button::default().with_label("保存");
+    let mut cancel = Button::default().with_label("取消");
+    buttons.set_size(&save, 80);
+    buttons.set_size(&cancel, 80);
+    buttons.end();
+    form.set_size(&buttons, 30);
+    form.end();
+    win.end();
+
+    let saved = Rc::new(RefCell::new(false));
     {
-        let mut scroll = scroll.clone();
-        let mut scanbtn = scanbtn.clone();
-        btn.set_callback(move |_| {
-            scroll.hide();
-            scanbtn.show();
+        let mut win = win.clone();
+        let saved = saved.clone();
+        save.set_callback(move |_| {
+            let number = |input: &input::Input, name: &str| {
+                input.value().trim().parse::<u32>().map_err(|_| format!("{}应为非负整数", name))
+            };
+            let list = |input: &input::Input| {
+                input.value().split(",").map(|x| x.trim().to_string()).filter(|x| x.len() > 0).collect::<Vec<String>>()
+            };
+            let policy = (|| -> Result<Policy, String> {
+                Ok(Policy {
+                    host: HostPolicy {
+                        role: if role.value() == 0 { HostRole::Production } else { HostRole::Development },
+                    },
+                    passwd: PasswdPolicy {
+                        min_len: number(&min_len, "口令最小长度")?,
+                        max_days: number(&max_days, "口令最长使用天数")?,
+                        min_days: number(&min_days, "口令最短使用天数")?,
+                        warn_age: number(&warn_age, "口令过期提前提醒天数")?,
+                    },
+                    session: SessionPolicy {
+                        tmout: number(&tmout, "登录超时上限")?,
+                    },
+                    network: NetworkPolicy {
+                        listen_allowlist: list(&allowlist),
+                    },
+                    history: HistoryPolicy {
+                        mode: if history.value() == 0 { HistoryMode::Minimize } else { HistoryMode::Audit },
+                        retain_size: number(&retain_size, "保留的历史记录条数")? as usize,
+                    },
+                    ssh: SshPolicy {
+                        weak_credential_test: weak_test.is_checked(),
+                        credentials: list(&credentials),
+                    },
+                    kernel: KernelPolicy {
+                        panic_timeout: number(&panic_timeout, "内核崩溃后重启等待上限")?,
+                        kptr_restrict: number(&kptr_restrict, "kernel.kptr_restrict")?,
+                        dmesg_restrict: number(&dmesg_restrict, "kernel.dmesg_restrict")?,
+                        kdump: kdump.is_checked(),
+                    },
+                    time: TimePolicy {
+                        timezone: timezone.value().trim().to_string(),
+                        locale: locale.value().trim().to_string(),
+                        rtc: if rtc.value() == 0 { RtcMode::Utc } else { RtcMode::Local },
+                    },
+                    audit: AuditPolicy {
+                        watches: current.audit.watches.clone(),
+                        extra_watches: list(&extra_watches),
+                    },
+                    limits: LimitsPolicy {
+                        maxlogins: number(&maxlogins, "并发登录数上限")? as u64,
+                        nproc: number(&nproc, "进程数上限")? as u64,
+                        nofile: number(&nofile, "打开文件数上限")? as u64,
+                    },
+                })
+            })();
+            match policy.and_then(|x| policy::save(&x)) {
+                Ok(path) => {
+                    dialog::message_default(&format!("已保存到 {}, 点击\"扫描\"按新策略重新检查", path.display()));
+                    *saved.borrow_mut() = true;
+                    win.hide();
+                },
+                Err(e) => dialog::alert_default(&e),
+            }
         });
     }
-    button_group.set_size(&btn, WIN_WIDTH / 2 - bar_width);
-    button_group.end();
-    parent.set_size(&button_group, 30);
+    {
+        let mut win = win.clone();
+        cancel.set_callback(move |_| win.hide());
+    }
 
-    let cell = sysguard::GuardItem::OS.check();
-    let r = row(
-        TableCell::new(cell.get("A4"), cell_height),
-        TableCell::new(cell.get("B4"), cell_height),
-        TableCell::new("", cell_height),
-    );
-    parent.set_size(&r, cell_height);
-
-    let cell = sysguard::GuardItem::IP.check();
-    let r = row(
-        TableCell::new(cell.get("A5"), cell_height),
-        TableCell::new(cell.get("B5"), cell_height),
-        TableCell::new("", cell_height),
-    );
-    parent.set_size(&r, cell_height);
-
-    let cell = sysguard::GuardItem::UserMgmt.check();
-    let r = compound_row(
-        vec![
-            TableCell::new(cell.get("A8"), cell_height * 4),
-        ],
-        vec![
-            TableCell::new(cell.get("B8"), cell_height * 2),
-            TableCell::new(cell.get("B9"), cell_height * 2),
-        ],
-        vec![
-            TableCell::new(cell.get("C8"), cell_height * 2),
-            TableCell::new(cell.get("C9"), cell_height * 2),
-        ],
-    );
-    parent.set_size(&r, cell_height * 4);
-
-    let cell = sysguard::GuardItem::PasswdComplexity.check();
-    let r = row(
-        TableCell::new(cell.get("A10"), cell_height * 2),
-        TableCell::new(cell.get("B10"), cell_height * 2),
-        TableCell::new(cell.get("C10"), cell_height * 2),
-    );
-    parent.set_size(&r, cell_height * 2);
-
-
-    let cell = sysguard::GuardItem::OperationTimeout.check();
-    let r = row(
-        TableCell::new(cell.get("A11"), cell_height * 1),
-        TableCell::new(cell.get("B11"), cell_height * 1),
-        TableCell::new(cell.get("C11"), cell_height * 1),
-    );
-    parent.set_size(&r, cell_height * 1);
-
-    let cell = sysguard::GuardItem::Port.check();
-    let r = row(
-        TableCell::new(cell.get("A14"), cell_height * 2),
-        TableCell::new(cell.get("B14"), cell_height * 2),
-        TableCell::new(cell.get("C14"), cell_height * 2),
-    );
-    parent.set_size(&r, cell_height * 2);
-
-    let cell = sysguard::GuardItem::Service.check();
-    let r = row(
-        TableCell::new(cell.get("A15"), cell_height * 4),
-        TableCell::new(cell.get("B15"), cell_height * 4),
-        TableCell::new(cell.get("C15"), cell_height * 4),
-    );
-    parent.set_size(&r, cell_height * 4);
-
-    let cell = sysguard::GuardItem::Audit.check();
-    let r = row(
-        TableCell::new(cell.get("A19"), cell_height * 4),
-        TableCell::new(cell.get("B19"), cell_height * 4),
-        TableCell::new(cell.get("C19"), cell_height * 4),
-    );
-    parent.set_size(&r, cell_height * 4);
-
-    let cell = sysguard::GuardItem::IPTables.check();
-    let r = row(
-        TableCell::new(cell.get("A21"), cell_height * 2),
-        TableCell::new(cell.get("B21"), cell_height * 2),
-        TableCell::new(cell.get("C21"), cell_height * 2),
-    );
-    parent.set_size(&r, cell_height * 2);
-
-    let cell = sysguard::GuardItem::CommandHistory.check();
-    let r = row(
-        TableCell::new(cell.get("A25"), cell_height * 1),
-        TableCell::new(cell.get("B25"), cell_height * 1),
-        TableCell::new(cell.get("C25"), cell_height * 1),
-    );
-    parent.set_size(&r, cell_height * 1);
+    win.show();
+    while win.shown() {
+        app::wait();
+    }
+    let saved = *saved.borrow();
+    saved
+}
 
+// 导出前确认操作人员及写入报告表头的单位信息, 取消时返回 None
+fn header_editor(report: &ScanReport) -> Option<(String, ReportHeader)> {
+    let header = report::default_header(&report.meta);
+    let mut win = Window::default().with_size(480, 240).with_label("报告信息");
+    place_window(&mut win);
+    win.make_modal(true);
+    let mut form = group::Flex::default_fill().column();
+    form.set_margin(10);
+
+    let operator = policy_input(&mut form, "操作人员", &report.meta.operator);
+    let unit = policy_input(&mut form, "单位名称", &header.unit);
+    let asset_id = policy_input(&mut form, "资产编号", &header.asset_id);
+    let responsible = policy_input(&mut form, "责任人", &header.responsible);
+    let audit_date = policy_input(&mut form, "检查日期", &header.audit_date);
+
+    Frame::default();
+    let mut buttons = group::Flex::default().row();
+    Frame::default();
+    let mut ok = Button::default().with_label("确定");
+    let mut cancel = Button::default().with_label("取消");
+    buttons.set_size(&ok, 80);
+    buttons.set_size(&cancel, 80);
+    buttons.end();
+    form.set_size(&buttons, 30);
+    form.end();
+    win.end();
+
+    let result = Rc::new(RefCell::new(None));
+    {
+        let mut win = win.clone();
+        let result = result.clone();
+        ok.set_callback(move |_| {
+            let header = ReportHeader {
+                unit: unit.value().trim().to_string(),
+                asset_id: asset_id.value().trim().to_string(),
+                responsible: responsible.value().trim().to_string(),
+                audit_date: audit_date.value().trim().to_string(),
+            };
+            *result.borrow_mut() = Some((operator.value().trim().to_string(), header));
+            win.hide();
+        });
+    }
+    {
+        let mut win = win.clone();
+        cancel.set_callback(move |_| win.hide());
+    }
+
+    win.show();
+    while win.shown() {
+        app::wait();
+    }
+    let result = result.borrow_mut().take();
+    result
+}
+
+// 打开的报告文件: 一次扫描, 或数据库中多台主机的汇总
+enum Opened {
+    Report(ScanReport),
+    Findings(Vec<store::Finding>),
+}
+
+// 打开导出的 JSON 报告或结果数据库中的一次扫描, 用户取消时返回 None
+fn open_report(filename: &str) -> Result<Option<Opened>, String> {
+    if filename.ends_with(".db") {
+        let store = store::Store::open(filename).map_err(|e| format!("{:?}", e))?;
+        let scans = store.scans(None).map_err(|e| format!("{:?}", e))?;
+        if scans.len() == 0 {
+            return Err("数据库中没有扫描记录".to_string());
+        }
+        let mut msg = vec!["输入要查看的扫描 ID, 或输入 0 查看各主机不符合要求的汇总; 最近的扫描:".to_string()];
+        for scan in scans.iter().take(10) {
+            msg.push(format!("{}  {}  {}  ✓{} ✗{} ◐{}", scan.id, scan.started_at, scan.hostname, scan.passed, scan.failed, scan.partial));
+        }
+        let id = match dialog::input_default(&msg.join("\n"), &scans[0].id.to_string()) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        let id = id.trim().parse::<i64>().map_err(|_| format!("无效的扫描 ID: {}", id))?;
+        if id == 0 {
+            return store.findings().map(|x| Some(Opened::Findings(x))).map_err(|e| format!("{:?}", e));
+        }
+        return match store.load(id).map_err(|e| format!("{:?}", e))? {
+            Some(report) => Ok(Some(Opened::Report(report))),
+            None => Err(format!("扫描 {} 不存在", id)),
+        };
+    }
+    let text = std::fs::read_to_string(filename).map_err(|e| format!("cannot read {}: {:?}", filename, e))?;
+    ScanReport::from_json(&text).map(|x| Some(Opened::Report(x)))
+}
+
+// 各主机不符合要求的汇总, 每项要求列出不符合的主机数及主机名, 按主机数从多到少排列
+fn findings_panel(home: group::Pack, findings: &[store::Finding]) -> group::Flex {
+    let mut parent = group::Flex::default().with_size(WIN_WIDTH, WIN_HEIGHT).column();
+    let mut btn = Button::default().with_label("返回");
+    parent.set_size(&btn, 30);
+    let title = format!("各主机最近一次扫描中不符合的要求, 共 {} 项", findings.len());
+    let frame = Frame::default().with_label(&title);
+    parent.set_size(&frame, 30);
+
+    let mut browser = browser::HoldBrowser::default();
+    browser.set_column_widths(&[50, 220]);
+    browser.set_column_char('\t');
+    for finding in findings.iter() {
+        browser.add(&format!("{}\t{}\t{}", finding.hosts.len(), finding.text, finding.hosts.join(", ")));
+    }
+    // 条目较长时在下方显示选中要求的完整内容及全部主机
+    let detail = text_area("");
+    parent.set_size(&detail, 120);
+    {
+        let findings = findings.to_vec();
+        browser.set_callback(move |b| {
+            if let Some(finding) = findings.get((b.value() - 1) as usize) {
+                let text = format!("{}\n{}\n不符合的主机({}): {}",
+                    finding.requirement_id, finding.text, finding.hosts.len(), finding.hosts.join(", "));
+                detail.buffer().unwrap().set_text(&text);
+            }
+        });
+    }
     parent.end();
-    scroll.end();
 
+    {
+        let mut parent = parent.clone();
+        let mut home = home.clone();
+        btn.set_callback(move |_| {
+            parent.hide();
+            home.show();
+        });
+    }
+    parent
+}
+
+// 拖放的文件以换行分隔, 部分桌面环境使用 file:// URI 并对路径中的特殊字符进行百分号编码
+fn dropped_paths(text: &str) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    for line in text.lines().map(|x| x.trim()).filter(|x| x.len() > 0) {
+        let path = match line.strip_prefix("file://") {
+            Some(path) => util::percent_decode(path),
+            None => line.to_string(),
+        };
+        paths.push(PathBuf::from(path));
+    }
+    paths
+}
+
+fn style_scroll(scroll: &mut group::Scroll, bar_width: i32) {
     scroll.set_scrollbar_size(bar_width);
     scroll.set_type(group::ScrollType::Vertical);
     let mut scrollbar = scroll.scrollbar();
     scrollbar.set_type(valuator::ScrollbarType::VerticalNice);
     scrollbar.set_color(enums::Color::from_u32(0x757575));
     scrollbar.set_selection_color(enums::Color::Red);
+}
 
-    scroll
+fn back_button(scroll: &group::Scroll, home: &group::Pack) -> Button {
+    let mut btn = Button::new(0, 0, 40, 40, "返回");
+    let mut scroll = scroll.clone();
+    let mut home = home.clone();
+    btn.set_callback(move |_| {
+        scroll.hide();
+        home.show();
+    });
+    btn
 }
 
-fn saveas(dst: String) -> Result<String, String> {
-    let cells = vec![
-        sysguard::GuardItem::OS,
-        sysguard::GuardItem::IP,
-        sysguard::GuardItem::UserMgmt,
-        sysguard::GuardItem::PasswdComplexity,
-        sysguard::GuardItem::OperationTimeout,
-        sysguard::GuardItem::Port,
-        sysguard::GuardItem::Audit,
-        sysguard::GuardItem::IPTables,
-        sysguard::GuardItem::Service,
-        sysguard::GuardItem::CommandHistory,
-    ];
-
-    let dst = if !dst.ends_with(".xlsx") {
-        dst + ".xlsx"
+// 在审计视图与操作员视图之间切换: 按另一视图重新生成结果界面并删除当前界面
+fn view_button(win: &Window, home: &group::Pack, report: &Rc<ScanReport>, title: &Option<String>, slot: &Option<PanelSlot>, scroll: &group::Scroll) -> Button {
+    let label = if OPERATOR_VIEW.load(Ordering::Relaxed) { "审计视图" } else { "操作员视图" };
+    let mut btn = Button::new(0, 0, 40, 40, label);
+    let mut win = win.clone();
+    let home = home.clone();
+    let report = report.clone();
+    let title = title.clone();
+    let slot = slot.clone();
+    let mut scroll = scroll.clone();
+    btn.set_callback(move |_| {
+        OPERATOR_VIEW.store(!OPERATOR_VIEW.load(Ordering::Relaxed), Ordering::Relaxed);
+        win.begin();
+        let mut panel = results_panel(&win, &home, report.clone(), title.clone(), slot.clone());
+        win.end();
+        if let Some(slot) = slot.as_ref() {
+            *slot.borrow_mut() = Some(panel.clone());
+        }
+        panel.show();
+        scroll.hide();
+        app::delete_widget(scroll.clone());
+        win.redraw();
+    });
+    btn
+}
+
+/// 按当前视图生成结果界面; `title` 不为空时在顶部显示, 用于标明正在查看的是已保存的报告
+fn results_panel(win: &Window, home: &group::Pack, report: Rc<ScanReport>, title: Option<String>, slot: Option<PanelSlot>) -> group::Scroll {
+    if OPERATOR_VIEW.load(Ordering::Relaxed) {
+        operator_panel(win, home, report, title, slot)
     } else {
-        dst
-    };
-    let dst = Path::new(&dst);
-    if dst.exists() {
-        let _ = std::fs::remove_file(dst);
+        host_security_panel(win, home, report, title, slot)
     }
+}
 
-    let tplbytes = include_bytes!("../assets/附件2：网络安全台账（原件）.xlsx");
-    let tmpdir = tempfile::tempdir().map_err(|e| format!("cannot create temporary directory: {:?}", e))?;
-    let tplpath = tmpdir.path().join("tpl.xlsx");
-    let mut tplfile = File::create(&tplpath).map_err(|e| format!("cannot create template file: {:?}", e))?;
-    let _ = tplfile.write_all(&tplbytes[..]);
+// 操作员视图: 只以醒目的标记列出各项安全要求是否符合, 不显示证据与备注; 不符合或部分符合的要求可查看整改说明
+fn operator_panel(win: &Window, home: &group::Pack, report: Rc<ScanReport>, title: Option<String>, slot: Option<PanelSlot>) -> group::Scroll {
+    let row_height = 50;
+    let bar_width = 10;
 
-    let mut book = umya_spreadsheet::reader::xlsx::read(&tplpath).unwrap();
-    let sheet = book.get_sheet_by_name_mut("工作站").unwrap();
-    for cell in cells {
-        let r = cell.check();
-        for (k, v) in r.mp.iter() {
-            sheet.get_cell_mut(k.to_string()).set_value(v.to_string());
+    let mut scroll = group::Scroll::default().with_size(WIN_WIDTH, WIN_HEIGHT - 20);
+    let mut groups = vec![];
+    for category in Category::all() {
+        let mut statuses = vec![];
+        for item in check::all().into_iter().filter(|x| x.category() == category) {
+            if let Some(cell) = report.cells.iter().find(|x| x.id == item.id()) {
+                statuses.extend(cell.statuses());
+            }
         }
+        if statuses.len() > 0 {
+            groups.push((category, statuses));
+        }
+    }
+    let height = groups.iter().map(|(_, statuses)| 30 + statuses.len() as i32 * row_height).sum::<i32>();
+    let mut parent = group::Flex::default_fill().column().with_size(WIN_WIDTH, height + 60);
+
+    let mut button_group = group::Flex::default_fill().row();
+    let btn = view_button(win, home, &report, &title, &slot, &scroll);
+    button_group.set_size(&btn, WIN_WIDTH / 2 - bar_width);
+    let btn = back_button(&scroll, home);
+    button_group.set_size(&btn, WIN_WIDTH / 2 - bar_width);
+    button_group.end();
+    parent.set_size(&button_group, 30);
+
+    if let Some(title) = title {
+        let frame = Frame::default().with_label(&title);
+        parent.set_size(&frame, 30);
     }
 
-    if let Err(e) = umya_spreadsheet::writer::xlsx::write(&book, &dst) {
-        return Err(format!("failed to write xlsx with error: {:?}", e));
+    for (category, statuses) in groups.iter() {
+        let frame = Frame::default().with_label(category.title());
+        parent.set_size(&frame, 30);
+        for (id, status) in statuses.iter() {
+            let mut row = group::Flex::default().row();
+            let (mark, color) = match status {
+                Status::Pass => ("✓", enums::Color::from_rgb(0, 150, 0)),
+                Status::Fail => ("✗", enums::Color::Red),
+                Status::Partial => ("◐", enums::Color::from_rgb(230, 140, 0)),
+                Status::Manual => ("?", enums::Color::from_rgb(120, 120, 120)),
+            };
+            let mut indicator = Frame::default().with_label(mark);
+            indicator.set_label_size(32);
+            indicator.set_label_color(color);
+            row.set_size(&indicator, 50);
+            let mut text = Frame::default().with_label(i18n::tr(id));
+            text.set_align(enums::Align::Left | enums::Align::Inside | enums::Align::Wrap);
+            if *status == Status::Fail || *status == Status::Partial {
+                let mut fix = Button::default().with_label("整改");
+                row.set_size(&fix, 60);
+                let mut details = reference::details(id);
+                if *status == Status::Partial {
+                    details.push_str("\n◐ 只能自动检查部分条件, 其余条件需人工核查");
+                }
+                fix.set_callback(move |_| dialog::message_default(&details));
+            }
+            row.end();
+            parent.set_size(&row, row_height);
+        }
     }
-    Ok("save successfully".to_string())
+
+    parent.end();
+    scroll.end();
+    style_scroll(&mut scroll, bar_width);
+    scroll
+}
+
+// 审计视图: 完整的检查结果、证据与备注及各种导出
+fn host_security_panel(win: &Window, home: &group::Pack, report: Rc<ScanReport>, title: Option<String>, slot: Option<PanelSlot>) -> group::Scroll {
+    let cell_height = 45i32;
+    let bar_width = 10;
+
+    let mut scroll = group::Scroll::default().with_size(WIN_WIDTH, WIN_HEIGHT - 20);
+    let layout = panel_layout(&report);
+    // 按钮、标题与各分类标题各占 30
+    let height = layout.iter()
+        .map(|(_, rows)| 30 + rows.iter().flat_map(|r| r.cells.iter()).map(|(_, h)| h * cell_height).sum::<i32>())
+        .sum::<i32>();
+    let mut parent = group::Flex::default_fill().column().with_size(WIN_WIDTH, height + 60);
+
+    let mut button_group = group::Flex::default_fill().row();
+    let btn = export_button("导出", "xlsx", report.clone(), None, export::saveas);
+    button_group.set_size(&btn, WIN_WIDTH / 6 - bar_width);
+    let btn = export_button("等保导出", "xlsx", report.clone(), None, export::saveas_gbt22239);
+    button_group.set_size(&btn, WIN_WIDTH / 6 - bar_width);
+    let btn = export_button("Word导出", "docx", report.clone(), None, export::saveas_docx);
+    button_group.set_size(&btn, WIN_WIDTH / 6 - bar_width);
+    let btn = export_button("打包导出", "zip", report.clone(), Some(parent.clone()), export::saveas_bundle);
+    button_group.set_size(&btn, WIN_WIDTH / 6 - bar_width);
+    let btn = view_button(win, home, &report, &title, &slot, &scroll);
+    button_group.set_size(&btn, WIN_WIDTH / 6 - bar_width);
+    let btn = back_button(&scroll, home);
+    button_group.set_size(&btn, WIN_WIDTH / 6 - bar_width);
+    button_group.end();
+    parent.set_size(&button_group, 30);
+
+    if let Some(title) = title {
+        let frame = Frame::default().with_label(&title);
+        parent.set_size(&frame, 30);
+    }
+
+    for (category, rows) in layout.iter() {
+        let frame = Frame::default().with_label(category.title());
+        parent.set_size(&frame, 30);
+        for r in rows.iter() {
+            let height = r.cells.iter().map(|(_, h)| h).sum::<i32>() * cell_height;
+            let sources = report.cells.iter()
+                .find(|x| x.mp.contains_key(&format!("A{}", r.row)))
+                .map_or(vec![], |x| x.sources.clone());
+            let block = compound_row(
+                vec![TableCell::new(report.get(format!("A{}", r.row)), height)],
+                r.cells.iter().map(|(n, h)| TableCell::reqs(&report, &format!("B{}", n), h * cell_height)).collect(),
+                r.cells.iter().map(|(n, h)| TableCell::new(report.get(format!("C{}", n)), h * cell_height)).collect(),
+                &sources,
+            );
+            parent.set_size(&block, height);
+        }
+    }
+
+    parent.end();
+    scroll.end();
+    style_scroll(&mut scroll, bar_width);
+    scroll
 }
 
 fn main() {
     println!("Running sysguard version: {}", VERSION);
 
+    let cli = cli::Cli::parse();
+    cli::setup(&cli);
+    // 演示模式: 不扫描本机, 使用内置的演示数据
+    let demo = cli.demo;
+
+    let force = cli.force;
+
+    if let Some(command) = cli.command {
+        // 扫描的子命令在运行结束前一直持有锁; 界面只在每次扫描期间持有
+        let lock = if demo || !command.scans() {
+            Ok(None)
+        } else {
+            lock::acquire(force)
+        };
+        let _lock = match lock {
+            Ok(lock) => lock,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            },
+        };
+        match cli::run(command, demo) {
+            Ok(0) => {},
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            },
+        }
+        return;
+    }
+
+    if let Err(e) = setup_display(cli.x11) {
+        println!("{}", e);
+        std::process::exit(1);
+    }
     let app = app::App::default();
+    setup_scale();
+    setup_font();
+    OPERATOR_VIEW.store(config::get().ui.view == config::UiView::Operator, Ordering::Relaxed);
+    // 上传报告时在界面中询问凭据存储的主口令
+    secrets::set_prompt(|msg| dialog::password_default(msg, ""));
     let widget_theme = WidgetTheme::new(ThemeType::AquaClassic);
     widget_theme.apply();
 
     let mut win = Window::default()
         .with_size(WIN_WIDTH, WIN_HEIGHT)
-        .with_label("安全加固检查")
-        .center_screen();
-
-    let mut scanbtn = Button::new(0, 0, 40, 40, "扫描").center_of(&win);
-    let mut panel = host_security_panel(scanbtn.clone());
-    panel.hide();
-    let mut btndup = scanbtn.clone();
-    scanbtn.set_callback(move |_| {
-        panel.show();
-        btndup.clone().hide();
-    });
+        .with_label(if demo { "安全加固检查(演示数据)" } else { "安全加固检查" });
+    place_window(&mut win);
+
+    let mut home = group::Pack::new(WIN_WIDTH / 2 - 50, WIN_HEIGHT / 2 - 70, 100, 180, "");
+    home.set_spacing(10);
+    let mut scanbtn = Button::default().with_size(100, 40).with_label("扫描");
+    let mut openbtn = Button::default().with_size(100, 40).with_label("打开报告");
+    let mut settingsbtn = Button::default().with_size(100, 40).with_label("策略设置");
+    // 提示扫描会用到哪些网络功能, 便于在隔离网络中确认; 标签比按钮宽, 居中显示在按钮下方
+    let mut network = Frame::default()
+        .with_size(100, 30)
+        .with_label(&format!("网络功能: {}", sysguard::network_features().join(", ")));
+    network.set_label_size(12);
+    home.end();
+
+    // 首次点击"扫描"时在后台扫描, 完成后生成结果面板; 拖入新的策略文件后清空, 下次点击时按新策略重新扫描
+    let panel: Rc<RefCell<Option<group::Scroll>>> = Rc::new(RefCell::new(None));
+    let (sender, receiver) = app::channel::<ScanResult>();
+    {
+        let mut home = home.clone();
+        let panel = panel.clone();
+        scanbtn.set_callback(move |btn| {
+            if let Some(panel) = panel.borrow_mut().as_mut() {
+                panel.show();
+                home.hide();
+                return;
+            }
+            if demo {
+                sender.send(ScanResult { report: ScanReport::demo(), new_failures: vec![] });
+                return;
+            }
+            // 已有扫描在进行时提示, 锁在后台扫描结束后释放
+            let lock = match lock::acquire(force) {
+                Ok(lock) => lock,
+                Err(e) => {
+                    println!("{}", e);
+                    dialog::alert_default(&e);
+                    return;
+                },
+            };
+            // 扫描期间界面保持响应, 可以最小化窗口, 完成后以桌面通知提示
+            btn.deactivate();
+            btn.set_label("扫描中...");
+            std::thread::spawn(move || {
+                let report = ScanReport::scan();
+                drop(lock);
+                let new_failures = new_failures(&report);
+                store::record(&report);
+                sender.send(ScanResult { report, new_failures });
+            });
+        });
+    }
+
+    // 拖入 .xlsx 报告模板或 .toml 策略文件以加载
+    {
+        let mut home = home.clone();
+        let panel = panel.clone();
+        let mut network = network.clone();
+        win.handle(move |_, ev| match ev {
+            enums::Event::DndEnter | enums::Event::DndDrag | enums::Event::DndLeave | enums::Event::DndRelease => true,
+            enums::Event::Paste => {
+                for path in dropped_paths(&app::event_text()) {
+                    let name = path.display().to_string();
+                    let ext = path.extension().map(|x| x.to_string_lossy().to_lowercase()).unwrap_or_default();
+                    let result = match ext.as_str() {
+                        "xlsx" => export::set_template(&path).map(|_| format!("已加载报告模板 {}, 之后导出的 xlsx 将使用该模板", name)),
+                        "toml" => policy::reload(&path).map(|_| {
+                            if let Some(panel) = panel.borrow_mut().take() {
+                                if panel.visible() {
+                                    home.show();
+                                }
+                                app::delete_widget(panel);
+                            }
+                            network.set_label(&format!("网络功能: {}", sysguard::network_features().join(", ")));
+                            format!("已加载策略文件 {}, 点击\"扫描\"按新策略重新检查", name)
+                        }),
+                        _ => Err(format!("不支持的文件 {}, 请拖入 .xlsx 报告模板或 .toml 策略文件", name)),
+                    };
+                    match result {
+                        Ok(msg) => dialog::message_default(&msg),
+                        Err(e) => {
+                            println!("{}", e);
+                            dialog::alert_default(&e);
+                        },
+                    }
+                }
+                true
+            },
+            _ => false,
+        });
+    }
+
+    // 保存新的策略后清空结果面板, 与拖入策略文件相同
+    {
+        let panel = panel.clone();
+        let mut network = network.clone();
+        settingsbtn.set_callback(move |_| {
+            if !policy_editor() {
+                return;
+            }
+            if let Some(panel) = panel.borrow_mut().take() {
+                app::delete_widget(panel);
+            }
+            network.set_label(&format!("网络功能: {}", sysguard::network_features().join(", ")));
+        });
+    }
+
+    // 以只读方式查看之前保存的报告, 并显示其原始扫描时间
+    {
+        let mut win = win.clone();
+        let mut home = home.clone();
+        openbtn.set_callback(move |_| {
+            let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseFile);
+            dlg.set_filter("*.{json,db}");
+            dlg.show();
+            let filename = dlg.filename().to_string_lossy().to_string();
+            if filename.len() == 0 {
+                return;
+            }
+            let report = match open_report(&filename) {
+                Ok(Some(Opened::Report(report))) => report,
+                Ok(Some(Opened::Findings(findings))) => {
+                    win.begin();
+                    let mut panel = findings_panel(home.clone(), &findings);
+                    win.end();
+                    panel.show();
+                    home.hide();
+                    win.redraw();
+                    return;
+                },
+                Ok(None) => return,
+                Err(e) => {
+                    println!("{}", e);
+                    dialog::alert_default(&e);
+                    return;
+                },
+            };
+            let title = format!("报告查看(只读)  主机: {}  扫描时间: {}", report.meta.hostname, report.meta.started_at);
+            let warning = report.stale_warning();
+            win.begin();
+            let mut panel = results_panel(&win, &home, Rc::new(report), Some(title), None);
+            win.end();
+            panel.show();
+            home.hide();
+            win.redraw();
+            if let Some(warning) = warning {
+                dialog::message_default(&warning);
+            }
+        });
+    }
 
     win.set_color(enums::Color::from_rgb(250, 250, 250));
     win.end();
     win.show();
 
-    app.run().unwrap();
+    while app.wait() {
+        let result = match receiver.recv() {
+            Some(result) => result,
+            None => continue,
+        };
+        scanbtn.activate();
+        scanbtn.set_label("扫描");
+        // 窗口最小化时隐藏, 此时以桌面通知提示扫描结果
+        if !win.visible() {
+            notify_scan(&result);
+        }
+        let missing = result.report.meta.missing.clone();
+        win.begin();
+        let mut scroll = results_panel(&win, &home, Rc::new(result.report), None, Some(panel.clone()));
+        win.end();
+        scroll.show();
+        *panel.borrow_mut() = Some(scroll);
+        home.hide();
+        win.redraw();
+        // 提前说明哪些检查项因缺少命令或文件而无法完成, 而不是只看到不通过的结果
+        if missing.len() > 0 {
+            dialog::message_default(&format!("以下依赖缺失, 相关检查项的结果可能不准确:\n{}", missing.join("\n")));
+        }
+    }
 }
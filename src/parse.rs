@@ -0,0 +1,302 @@
+//! 配置文件解析的公共工具
+//!
+//! 被检查的配置文件可能异常巨大或包含超长的行, 这里以流的方式逐行读取, 并限制单行长度与总行数,
+//! 避免一次性读入整个文件以及在超长的行上执行耗时的正则匹配.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use errlog::{elog, AnyResult, AnyContext};
+
+use crate::util;
+
+// 单行最多保留的字节数, 超出部分被丢弃
+pub const MAX_LINE_LEN: usize = 4096;
+// 单个文件最多读取的行数
+pub const MAX_LINES: usize = 100000;
+
+pub struct Lines<R> {
+    reader: R,
+    count: usize,
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count >= MAX_LINES {
+            return None;
+        }
+
+        let mut line = vec![];
+        let mut eof = true;
+        loop {
+            let (found, used) = {
+                let buf = match self.reader.fill_buf() {
+                    Ok(buf) => buf,
+                    Err(_) => return None,
+                };
+                if buf.len() == 0 {
+                    break;
+                }
+                eof = false;
+                let (found, data, used) = match buf.iter().position(|&b| b == b'\n') {
+                    Some(idx) => (true, &buf[..idx], idx + 1),
+                    None => (false, buf, buf.len()),
+                };
+                // 超出长度限制的部分直接丢弃, 但仍然要消费掉直到行尾
+                let room = MAX_LINE_LEN.saturating_sub(line.len());
+                line.extend_from_slice(&data[..std::cmp::min(room, data.len())]);
+                (found, used)
+            };
+            self.reader.consume(used);
+            if found {
+                break;
+            }
+        }
+        if eof {
+            return None;
+        }
+
+        self.count += 1;
+        Some(String::from_utf8_lossy(&line).trim_end_matches('\r').to_string())
+    }
+}
+
+pub fn lines<R: BufRead>(reader: R) -> Lines<R> {
+    Lines {
+        reader,
+        count: 0,
+    }
+}
+
+/// 逐行读取文件, 设置了备用根目录时读取其下的文件
+pub fn read_lines<P: AsRef<Path>>(path: P) -> AnyResult<Lines<BufReader<File>>> {
+    let path = util::rooted(path);
+    let file = File::open(&path).context(elog!("failed to open {}", path.display()))?;
+    util::add_source(&path.display().to_string());
+    Ok(lines(BufReader::new(file)))
+}
+
+/// PAM 配置中的一条规则, 例如 "password requisite pam_pwquality.so retry=3"
+#[derive(Debug, PartialEq)]
+pub struct PamRule {
+    // 类型, 例如 auth、password, 已去掉模块不存在时忽略该行的前缀 "-"; Debian 的包含语句为 "@include"
+    pub typ: String,
+    // 控制标志, 例如 "required"、"include" 或 "[success=1 default=ignore]"
+    pub control: String,
+    // 不含路径的模块名; include、substack 及 @include 为被包含的文件名
+    pub module: String,
+    // 模块参数, 例如 "retry=3"
+    pub args: Vec<String>,
+}
+
+/// 解析 PAM 配置, 忽略注释与空行
+pub fn pam_rules<I: Iterator<Item = String>>(lines: I) -> Vec<PamRule> {
+    let mut rules = vec![];
+    for line in lines {
+        let line = line.split("#").next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let typ = match tokens.next() {
+            Some(typ) => typ.trim_start_matches("-").to_string(),
+            None => continue,
+        };
+        let mut control = "".to_string();
+        if typ != "@include" {
+            control = tokens.next().unwrap_or("").to_string();
+            if control.starts_with("[") {
+                while !control.ends_with("]") {
+                    match tokens.next() {
+                        Some(token) => {
+                            control.push(' ');
+                            control.push_str(token);
+                        },
+                        None => break,
+                    }
+                }
+            }
+        }
+        let module = tokens.next().unwrap_or("").rsplit("/").next().unwrap_or("").to_string();
+        let args = tokens.map(|x| x.to_string()).collect();
+        rules.push(PamRule { typ, control, module, args });
+    }
+    rules
+}
+
+/// limits.conf 中的一条资源限制, 例如 "*  hard  nproc  4096"
+#[derive(Debug, PartialEq)]
+pub struct LimitRule {
+    // 适用的用户, 例如 "*"、"@wheel"、"alice"
+    pub domain: String,
+    // soft、hard, 或同时设置两者的 "-"
+    pub typ: String,
+    pub item: String,
+    pub value: String,
+}
+
+/// 解析 limits.conf 及 limits.d 下的文件, 忽略注释、空行及不完整的行
+pub fn limit_rules<I: Iterator<Item = String>>(lines: I) -> Vec<LimitRule> {
+    let mut rules = vec![];
+    for line in lines {
+        let line = line.split("#").next().unwrap_or("");
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        if tokens.len() != 4 {
+            continue;
+        }
+        rules.push(LimitRule {
+            domain: tokens[0].to_string(),
+            typ: tokens[1].to_string(),
+            item: tokens[2].to_lowercase(),
+            value: tokens[3].to_string(),
+        });
+    }
+    rules
+}
+
+/// 对所有用户("*")生效的硬限制, 后出现的设置覆盖之前的; 未设置时为 None, 不限制(unlimited、infinity 或 -1)时为 Some(None)
+pub fn hard_limit(rules: &[LimitRule], item: &str) -> Option<Option<u64>> {
+    rules.iter()
+        .filter(|x| x.domain == "*" && (x.typ == "hard" || x.typ == "-") && x.item == item)
+        .last()
+        .map(|x| x.value.parse::<u64>().ok())
+}
+
+/// 从 /proc/<pid>/cgroup 中找出进程所属的 systemd 单元, 例如 "0::/system.slice/smb.service" 为 "smb.service"
+pub fn systemd_unit(cgroup: &str) -> Option<String> {
+    cgroup.lines()
+        .filter_map(|x| x.splitn(3, ":").nth(2))
+        .filter_map(|x| x.rsplit("/").next())
+        .find(|x| x.ends_with(".service") || x.ends_with(".socket") || x.ends_with(".scope"))
+        .map(|x| x.to_string())
+}
+
+/// 解析 `auditctl -s` 的输出. 新版本每行一项, 形如 "enabled 2"; 旧版本(audit 2.3 之前)只有一行,
+/// 形如 "AUDIT_STATUS: enabled=1 flag=1 pid=1020 ...". 值后面带有说明的行(如 "loginuid_immutable 0 unlocked")
+/// 只取第一个值
+pub fn audit_status(text: &str) -> HashMap<String, String> {
+    let mut status = HashMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(line) = line.strip_prefix("AUDIT_STATUS:") {
+            for item in line.split_whitespace() {
+                if let Some((key, value)) = item.split_once("=") {
+                    status.insert(key.to_string(), value.to_string());
+                }
+            }
+            continue;
+        }
+        let mut items = line.split_whitespace();
+        if let (Some(key), Some(value)) = (items.next(), items.next()) {
+            status.insert(key.to_string(), value.to_string());
+        }
+    }
+    status
+}
+
+/// 配置行中设置的 PATH 值: shell 的 "PATH=..." 或 "export PATH=...", login.defs 的 "ENV_SUPATH PATH=...",
+/// sudoers 的 "Defaults secure_path=..."
+pub fn path_value(line: &str) -> Option<String> {
+    let line = line.trim();
+    let value = if let Some(rest) = line.strip_prefix("Defaults") {
+        let (key, value) = rest.split_once("=")?;
+        if key.trim() != "secure_path" {
+            return None;
+        }
+        value
+    } else if let Some(rest) = line.strip_prefix("ENV_SUPATH").or(line.strip_prefix("ENV_PATH")) {
+        rest.trim().strip_prefix("PATH=")?
+    } else {
+        line.strip_prefix("export ").unwrap_or(line).trim().strip_prefix("PATH=")?
+    };
+    Some(value.trim().trim_matches(|c| c == '"' || c == '\'').to_string())
+}
+
+/// PATH 中的各个目录, 空项表示当前目录, 记为 "."; 忽略引用变量的项(如 "$PATH"、"${HOME}/bin")
+pub fn path_entries(value: &str) -> Vec<String> {
+    value.split(":")
+        .filter(|x| !x.contains("$"))
+        .map(|x| if x.len() == 0 { ".".to_string() } else { x.to_string() })
+        .collect()
+}
+
+/// 将版本号中的各段数字作为比较用的键, 例如 "5.14.0-362.8.1.el9_3" 为 [5, 14, 0, 362, 8, 1, 9, 3]
+pub fn version_key(version: &str) -> Vec<u64> {
+    version.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|x| x.parse::<u64>().ok())
+        .collect()
+}
+
+#[test]
+fn test_lines() {
+    let text = "a\r\nbc\n\nd";
+    let r = lines(text.as_bytes()).collect::<Vec<String>>();
+    assert_eq!(vec!["a", "bc", "", "d"], r);
+
+    let text = format!("{}\nnext", "x".repeat(MAX_LINE_LEN * 3));
+    let r = lines(BufReader::with_capacity(16, text.as_bytes())).collect::<Vec<String>>();
+    assert_eq!(2, r.len());
+    assert_eq!(MAX_LINE_LEN, r[0].len());
+    assert_eq!("next", r[1]);
+}
+
+#[test]
+fn test_pam_rules() {
+    let text = "# comment\n\nauth [success=1 default=ignore] /lib/security/pam_unix.so nullok\n-password sufficient pam_unix.so\n@include common-auth\nauth substack password-auth\n";
+    let r = pam_rules(lines(text.as_bytes()));
+    assert_eq!(4, r.len());
+    assert_eq!(PamRule { typ: "auth".to_string(), control: "[success=1 default=ignore]".to_string(), module: "pam_unix.so".to_string(), args: vec!["nullok".to_string()] }, r[0]);
+    assert_eq!(("password", "sufficient"), (r[1].typ.as_str(), r[1].control.as_str()));
+    assert_eq!(("@include", "", "common-auth"), (r[2].typ.as_str(), r[2].control.as_str(), r[2].module.as_str()));
+    assert_eq!("password-auth", r[3].module);
+}
+
+#[test]
+fn test_limit_rules() {
+    let text = "# <domain> <type> <item> <value>\n*  soft  nofile  1024\n*  hard  nofile  65536\n@student - maxlogins 4\n*  -  nproc  unlimited\n*  hard  nproc  4096  # 覆盖上一行\n* hard\n";
+    let rules = limit_rules(lines(text.as_bytes()));
+    assert_eq!(5, rules.len());
+    assert_eq!(Some(Some(65536)), hard_limit(&rules, "nofile"));
+    assert_eq!(Some(Some(4096)), hard_limit(&rules, "nproc"));
+    assert_eq!(None, hard_limit(&rules, "maxlogins"));
+    let rules = limit_rules(lines("* hard nproc unlimited\n".as_bytes()));
+    assert_eq!(Some(None), hard_limit(&rules, "nproc"));
+}
+
+#[test]
+fn test_version_key() {
+    assert!(version_key("5.14.0-362.8.1.el9_3.x86_64") > version_key("5.14.0-284.11.1.el9_2.x86_64"));
+    assert!(version_key("6.1.0-13-amd64") > version_key("6.1.0-9-amd64"));
+    assert_eq!(vec![4, 18, 0], version_key("4.18.0"));
+}
+
+#[test]
+fn test_systemd_unit() {
+    assert_eq!(Some("smb.service".to_string()), systemd_unit("0::/system.slice/smb.service\n"));
+    let cgroup = "11:cpuset:/\n2:cpu,cpuacct:/system.slice/xrdp.service\n1:name=systemd:/system.slice/xrdp.service\n";
+    assert_eq!(Some("xrdp.service".to_string()), systemd_unit(cgroup));
+    assert_eq!(Some("session-3.scope".to_string()), systemd_unit("0::/user.slice/user-1000.slice/session-3.scope\n"));
+    assert_eq!(None, systemd_unit("0::/\n"));
+}
+
+#[test]
+fn test_audit_status() {
+    let status = audit_status("enabled 2\nfailure 1\npid 912\nrate_limit 0\nloginuid_immutable 0 unlocked\n");
+    assert_eq!(Some("2"), status.get("enabled").map(|x| x.as_str()));
+    assert_eq!(Some("0"), status.get("loginuid_immutable").map(|x| x.as_str()));
+    let status = audit_status("AUDIT_STATUS: enabled=1 flag=1 pid=1020 rate_limit=0 backlog_limit=320 lost=0 backlog=0\n");
+    assert_eq!(Some("1"), status.get("enabled").map(|x| x.as_str()));
+    assert_eq!(Some("1020"), status.get("pid").map(|x| x.as_str()));
+    assert_eq!(0, audit_status("").len());
+}
+
+#[test]
+fn test_path_value() {
+    assert_eq!(Some("/usr/local/bin:$PATH".to_string()), path_value("export PATH=\"/usr/local/bin:$PATH\""));
+    assert_eq!(Some("/sbin:/bin".to_string()), path_value("ENV_SUPATH\tPATH=/sbin:/bin"));
+    assert_eq!(Some("/usr/sbin:/usr/bin".to_string()), path_value("Defaults    secure_path = /usr/sbin:/usr/bin"));
+    assert_eq!(None, path_value("Defaults env_reset"));
+    assert_eq!(None, path_value("MANPATH=/usr/share/man"));
+    assert_eq!(vec!["/usr/local/bin", ".", "/tmp", "."], path_entries("/usr/local/bin::$HOME/bin:/tmp:"));
+}
@@ -0,0 +1,118 @@
+//! 检查项使用的正则表达式
+//!
+//! 所有正则在首次使用时编译一次, 之后在各线程间共享; 需要适配其他发行版的写法时在这里扩展.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+// auditctl -l 输出的文件监视规则, 例如 "-w /etc/profile.d/ -p rwxa -k profile"
+pub static RE_AUDIT_WATCH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^-w\s+(\S+)\s+-p\s+(\S+)").unwrap()
+});
+
+// iptables 规则中的 IPv4 地址段
+pub static RE_IPV4_CIDR: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}/(\d{1,2})?)").unwrap()
+});
+
+// shell 脚本中的 umask 命令, 例如 "umask 027"
+pub static RE_UMASK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^umask\s+([0-7]{3,4})$").unwrap()
+});
+
+// pam_cracklib/pam_pwquality 中要求包含某类字符的参数, 例如 "dcredit=-1"
+pub static RE_PAM_CREDIT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"([dulo]credit\s*=\s*-\d+)").unwrap()
+});
+
+// pwquality.conf 中关闭用户名检查的配置
+pub static RE_USERCHECK_OFF: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^usercheck\s*=\s*0$").unwrap()
+});
+
+pub static RE_TMOUT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\bTMOUT=(\d+)").unwrap()
+});
+
+// 将 TMOUT 设为只读, 例如 "readonly TMOUT" 或 "declare -r TMOUT=600"
+pub static RE_TMOUT_READONLY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(readonly|(declare|typeset)\s+-\w*r\w*)\s.*\bTMOUT\b").unwrap()
+});
+
+pub static RE_HISTSIZE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"HISTSIZE=(\d+)").unwrap()
+});
+
+pub static RE_HISTFILESIZE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"HISTFILESIZE=(\d+)").unwrap()
+});
+
+pub static RE_HISTTIMEFORMAT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"HISTTIMEFORMAT=(.+)$").unwrap()
+});
+
+// securetty 中允许的物理控制台和虚拟终端
+pub static RE_CONSOLE_TTY: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(console|tty\d+)$").unwrap()
+});
+
+// ss -p 输出中的进程名, 例如 users:(("redis-server",pid=812,fd=6))
+pub static RE_SS_PROCESS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\("([^"]+)""#).unwrap()
+});
+
+// ss -p 输出中的各个进程名及 PID, 例如 users:(("smbd",pid=812,fd=30),("smbd",pid=815,fd=30))
+pub static RE_SS_PID: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"\("([^"]+)",pid=(\d+)"#).unwrap()
+});
+
+// sudoers 中关闭 syslog 记录的 Defaults, 例如 "Defaults !syslog" 或 "Defaults:alice !syslog"
+pub static RE_SUDO_NOSYSLOG: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^Defaults\S*\s.*!syslog\b").unwrap()
+});
+
+// ssh-keygen -l 输出的密钥长度与类型, 例如 "3072 SHA256:... root@host (RSA)"
+pub static RE_KEYGEN_BITS: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\d+)\s+\S+\s.*\((\w+)\)$").unwrap()
+});
+
+#[test]
+fn test_patterns() {
+    let caps = RE_AUDIT_WATCH.captures("-w /etc/passwd -p wa -k identity").unwrap();
+    assert_eq!(("/etc/passwd", "wa"), (&caps[1], &caps[2]));
+    assert!(!RE_AUDIT_WATCH.is_match("-a always,exit -F arch=b64 -S execve"));
+
+    assert_eq!("10.0.0.0/16", &RE_IPV4_CIDR.captures("-A whitelist -s 10.0.0.0/16 -j ACCEPT").unwrap()[1]);
+    assert!(!RE_IPV4_CIDR.is_match("-A whitelist -s 10a0b0c0/16"));
+
+    assert_eq!("027", &RE_UMASK.captures("umask 027").unwrap()[1]);
+    assert!(!RE_UMASK.is_match("umask u=rwx"));
+
+    let credits = RE_PAM_CREDIT.captures_iter("password requisite pam_pwquality.so dcredit=-1 ucredit = -2 ocredit=1")
+        .map(|x| x[1].to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(vec!["dcredit=-1", "ucredit = -2"], credits);
+    assert!(RE_USERCHECK_OFF.is_match("usercheck = 0"));
+
+    assert_eq!("600", &RE_TMOUT.captures("export TMOUT=600").unwrap()[1]);
+    assert!(!RE_TMOUT.is_match("MYTMOUT=600"));
+    assert!(RE_TMOUT_READONLY.is_match("readonly TMOUT"));
+    assert!(RE_TMOUT_READONLY.is_match("declare -xr TMOUT=600"));
+    assert!(!RE_TMOUT_READONLY.is_match("declare -x TMOUT=600"));
+
+    assert_eq!("5", &RE_HISTSIZE.captures("HISTSIZE=5").unwrap()[1]);
+    assert_eq!("2000", &RE_HISTFILESIZE.captures("export HISTFILESIZE=2000").unwrap()[1]);
+    assert_eq!("\"%F %T \"", &RE_HISTTIMEFORMAT.captures("export HISTTIMEFORMAT=\"%F %T \"").unwrap()[1]);
+
+    assert!(RE_CONSOLE_TTY.is_match("tty1"));
+    assert!(!RE_CONSOLE_TTY.is_match("pts/0"));
+    assert_eq!("redis-server", &RE_SS_PROCESS.captures("users:((\"redis-server\",pid=812,fd=6))").unwrap()[1]);
+    let pids = RE_SS_PID.captures_iter("users:((\"smbd\",pid=812,fd=30),(\"smbd\",pid=815,fd=30))")
+        .map(|caps| caps[2].to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(vec!["812", "815"], pids);
+    assert!(RE_SUDO_NOSYSLOG.is_match("Defaults:alice !syslog"));
+    assert!(!RE_SUDO_NOSYSLOG.is_match("Defaults syslog=auth"));
+    let caps = RE_KEYGEN_BITS.captures("1024 SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8 root@db-01 (RSA)").unwrap();
+    assert_eq!(("1024", "RSA"), (&caps[1], &caps[2]));
+}
@@ -0,0 +1,426 @@
+//! 基线策略, 由各站点按自身要求在 `policy.toml` 中调整
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Serialize, Deserialize};
+
+use crate::config;
+
+// 界面中可以拖入新的策略文件替换当前策略, 旧策略可能仍被引用, 因此不释放
+static POLICY: Lazy<RwLock<&'static Policy>> = Lazy::new(|| RwLock::new(Box::leak(Box::new(Policy::load()))));
+// 命令行指定的策略文件, 需在首次读取策略前设置
+static POLICY_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    pub host: HostPolicy,
+    pub passwd: PasswdPolicy,
+    pub session: SessionPolicy,
+    pub network: NetworkPolicy,
+    pub history: HistoryPolicy,
+    pub ssh: SshPolicy,
+    pub kernel: KernelPolicy,
+    pub time: TimePolicy,
+    pub audit: AuditPolicy,
+    pub limits: LimitsPolicy,
+    pub integrity: IntegrityPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostRole {
+    // 生产主机, 按基线要求不得安装编译器等开发工具
+    Production,
+    // 开发或测试主机
+    Development,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostPolicy {
+    pub role: HostRole,
+    // 因业务需要保留的传统服务, 可以是 "ftp"、"telnet"; 保留时改为检查登录警示信息及匿名访问
+    pub legacy_services: Vec<String>,
+    // 登录警示信息须包含其中之一, 不区分大小写
+    pub banner_keywords: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswdPolicy {
+    // /etc/login.defs 中 PASS_MIN_LEN 的最小值
+    pub min_len: u32,
+    // /etc/login.defs 中 PASS_MAX_DAYS 的最大值
+    pub max_days: u32,
+    // /etc/login.defs 中 PASS_MIN_DAYS 与 PASS_WARN_AGE 的最小值
+    pub min_days: u32,
+    pub warn_age: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionPolicy {
+    // 登录终端(TMOUT)及 SSH 空闲连接的最长超时秒数
+    pub tmout: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkPolicy {
+    // 允许监听在所有地址上的管理服务, 可以是端口号或进程名, 例如 "9100"、"redis-server"
+    pub listen_allowlist: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SshPolicy {
+    // 是否尝试用默认口令登录本机 SSH, 可能触发登录失败锁定, 默认关闭
+    pub weak_credential_test: bool,
+    // 尝试的 "用户名:口令" 列表
+    pub credentials: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KernelPolicy {
+    // 内核崩溃(kernel.panic)后最多等待的秒数, 超过或不自动重启均不符合要求
+    pub panic_timeout: u32,
+    // kernel.kptr_restrict 与 kernel.dmesg_restrict 的最小值
+    pub kptr_restrict: u32,
+    pub dmesg_restrict: u32,
+    // 是否要求启用 kdump 保存崩溃转储
+    pub kdump: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RtcMode {
+    // 硬件时钟保存 UTC 时间
+    Utc,
+    // 硬件时钟保存本地时间, 通常用于与 Windows 双系统共存的主机
+    Local,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimePolicy {
+    // 时区, 例如 "Asia/Shanghai"
+    pub timezone: String,
+    // 语言环境(LANG), 为空时只要求使用 UTF-8 编码
+    pub locale: String,
+    pub rtc: RtcMode,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditPolicy {
+    // 必须有写入或属性修改(-p wa)审计监视规则的文件
+    pub watches: Vec<String>,
+    // 站点附加的监视文件, 例如 /etc/hosts 或应用配置, 无需重复列出默认文件
+    pub extra_watches: Vec<String>,
+}
+
+impl AuditPolicy {
+    /// 要求监视的全部文件, 去除重复项
+    pub fn required_watches(&self) -> Vec<String> {
+        let mut watches: Vec<String> = vec![];
+        for x in self.watches.iter().chain(self.extra_watches.iter()) {
+            if !watches.contains(x) {
+                watches.push(x.to_string());
+            }
+        }
+        watches
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsPolicy {
+    // limits.conf 中对所有用户的硬限制上限: 每个用户的并发登录数、进程数及打开的文件数
+    pub maxlogins: u64,
+    pub nproc: u64,
+    pub nofile: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntegrityPolicy {
+    // 完整性基线记录的文件或目录(递归), 符号链接不跟随
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryMode {
+    // 尽量减少 shell 历史记录(HISTSIZE/HISTFILESIZE 不超过 5)
+    Minimize,
+    // 保留带时间戳的历史记录, 并由 auditd 记录命令执行
+    Audit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryPolicy {
+    pub mode: HistoryMode,
+    // audit 模式下 HISTSIZE/HISTFILESIZE 的最小值
+    pub retain_size: usize,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            host: HostPolicy::default(),
+            passwd: PasswdPolicy::default(),
+            session: SessionPolicy::default(),
+            network: NetworkPolicy::default(),
+            history: HistoryPolicy::default(),
+            ssh: SshPolicy::default(),
+            kernel: KernelPolicy::default(),
+            time: TimePolicy::default(),
+            audit: AuditPolicy::default(),
+            limits: LimitsPolicy::default(),
+            integrity: IntegrityPolicy::default(),
+        }
+    }
+}
+
+impl Default for AuditPolicy {
+    fn default() -> Self {
+        AuditPolicy {
+            watches: vec![
+                "/etc/group", "/etc/passwd", "/etc/ssh/sshd_config", "/etc/shadow",
+                "/etc/sudoers", "/var/log/lastlog", "/etc/profile", "/etc/sysctl.conf",
+            ].into_iter().map(|x| x.to_string()).collect(),
+            extra_watches: vec![],
+        }
+    }
+}
+
+impl Default for IntegrityPolicy {
+    fn default() -> Self {
+        IntegrityPolicy {
+            paths: vec![
+                "/bin", "/sbin", "/usr/bin", "/usr/sbin", "/usr/local/bin", "/usr/local/sbin",
+                "/etc/ssh/sshd_config", "/etc/sudoers", "/etc/pam.d",
+            ].into_iter().map(|x| x.to_string()).collect(),
+        }
+    }
+}
+
+impl Default for LimitsPolicy {
+    fn default() -> Self {
+        LimitsPolicy {
+            maxlogins: 10,
+            nproc: 4096,
+            nofile: 65536,
+        }
+    }
+}
+
+impl Default for TimePolicy {
+    fn default() -> Self {
+        TimePolicy {
+            timezone: "Asia/Shanghai".to_string(),
+            locale: "".to_string(),
+            rtc: RtcMode::Utc,
+        }
+    }
+}
+
+impl Default for PasswdPolicy {
+    fn default() -> Self {
+        PasswdPolicy {
+            min_len: 8,
+            max_days: 180,
+            min_days: 1,
+            warn_age: 7,
+        }
+    }
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        SessionPolicy {
+            tmout: 600,
+        }
+    }
+}
+
+impl Default for KernelPolicy {
+    fn default() -> Self {
+        KernelPolicy {
+            panic_timeout: 60,
+            kptr_restrict: 1,
+            dmesg_restrict: 1,
+            kdump: true,
+        }
+    }
+}
+
+impl Default for SshPolicy {
+    fn default() -> Self {
+        SshPolicy {
+            weak_credential_test: false,
+            credentials: vec!["root:root".to_string(), "admin:admin".to_string()],
+        }
+    }
+}
+
+impl Default for HostPolicy {
+    fn default() -> Self {
+        HostPolicy {
+            role: HostRole::Production,
+            legacy_services: vec![],
+            banner_keywords: vec!["authorized".to_string(), "warning".to_string(), "授权".to_string(), "警告".to_string()],
+        }
+    }
+}
+
+impl Default for HistoryPolicy {
+    fn default() -> Self {
+        HistoryPolicy {
+            mode: HistoryMode::Minimize,
+            retain_size: 1000,
+        }
+    }
+}
+
+impl Policy {
+    fn load() -> Self {
+        let path = path();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                if POLICY_PATH.get().is_some() {
+                    eprintln!("cannot read {}: {}", path.display(), e);
+                }
+                return Policy::default();
+            },
+        };
+        match toml::from_str::<Policy>(&text) {
+            Ok(policy) => policy,
+            Err(e) => {
+                eprintln!("cannot parse {}: {}", path.display(), e);
+                Policy::default()
+            },
+        }
+    }
+
+    /// 校验 TOML 无法表达的取值范围, 错误信息用于在界面中提示
+    pub fn validate(&self) -> Result<(), String> {
+        if self.passwd.min_len == 0 {
+            return Err("口令最小长度(passwd.min_len)应大于 0".to_string());
+        }
+        if self.passwd.max_days == 0 || self.passwd.max_days < self.passwd.min_days {
+            return Err("口令最长使用天数(passwd.max_days)应大于 0 且不小于 passwd.min_days".to_string());
+        }
+        if self.session.tmout == 0 {
+            return Err("超时时间(session.tmout)应大于 0".to_string());
+        }
+        if self.history.retain_size == 0 {
+            return Err("保留的历史记录条数(history.retain_size)应大于 0".to_string());
+        }
+        if self.kernel.kptr_restrict > 2 {
+            return Err("kernel.kptr_restrict 的取值为 0 至 2".to_string());
+        }
+        if self.kernel.dmesg_restrict > 1 {
+            return Err("kernel.dmesg_restrict 的取值为 0 或 1".to_string());
+        }
+        if self.time.timezone.trim().len() == 0 {
+            return Err("时区(time.timezone)不能为空".to_string());
+        }
+        if self.limits.maxlogins == 0 || self.limits.nproc == 0 || self.limits.nofile == 0 {
+            return Err("资源限制(limits)的上限应大于 0".to_string());
+        }
+        for x in self.network.listen_allowlist.iter() {
+            if x.trim().len() == 0 || x.contains(char::is_whitespace) {
+                return Err(format!("无效的监听白名单项 '{}'", x));
+            }
+        }
+        for x in self.audit.watches.iter().chain(self.audit.extra_watches.iter()) {
+            if !x.starts_with("/") || x.contains(char::is_whitespace) {
+                return Err(format!("审计监视文件 '{}' 应为不含空白的绝对路径", x));
+            }
+        }
+        for x in self.ssh.credentials.iter() {
+            if !x.contains(":") || x.starts_with(":") {
+                return Err(format!("口令测试项 '{}' 应为 \"用户名:口令\"", x));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 当前使用的策略文件, 默认为配置目录下的 policy.toml
+pub fn path() -> PathBuf {
+    match POLICY_PATH.get() {
+        Some(path) => path.to_path_buf(),
+        None => config::dir().join("policy.toml"),
+    }
+}
+
+pub fn set_path(path: PathBuf) {
+    let _ = POLICY_PATH.set(path);
+}
+
+/// 读取并校验策略文件, 成功后替换当前策略, 之后的扫描按新策略检查
+pub fn reload(path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    let policy = toml::from_str::<Policy>(&text).map_err(|e| format!("cannot parse {}: {}", path.display(), e))?;
+    policy.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+    *POLICY.write().unwrap() = Box::leak(Box::new(policy));
+    Ok(())
+}
+
+/// 校验并将策略写入策略文件, 然后按其重新加载
+pub fn save(policy: &Policy) -> Result<PathBuf, String> {
+    policy.validate()?;
+    let path = path();
+    let text = toml::to_string(policy).map_err(|e| format!("cannot serialize policy: {}", e))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, text).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    reload(&path)?;
+    Ok(path)
+}
+
+pub fn get() -> &'static Policy {
+    *POLICY.read().unwrap()
+}
+
+#[test]
+fn test_policy() {
+    let policy = toml::from_str::<Policy>("[history]\nmode = \"audit\"\n").unwrap();
+    assert_eq!(HistoryMode::Audit, policy.history.mode);
+    assert_eq!(1000, policy.history.retain_size);
+
+    let policy = toml::from_str::<Policy>("").unwrap();
+    assert_eq!(HistoryMode::Minimize, policy.history.mode);
+    assert_eq!(HostRole::Production, policy.host.role);
+
+    let policy = toml::from_str::<Policy>("[host]\nrole = \"development\"\n").unwrap();
+    assert_eq!(HostRole::Development, policy.host.role);
+
+    let mut policy = Policy::default();
+    assert!(policy.validate().is_ok());
+    let text = toml::to_string(&policy).unwrap();
+    assert_eq!(600, toml::from_str::<Policy>(&text).unwrap().session.tmout);
+    policy.ssh.credentials = vec!["root".to_string()];
+    assert!(policy.validate().is_err());
+
+    let mut policy = toml::from_str::<Policy>("[passwd]\nmax_days = 90\n").unwrap();
+    assert_eq!((90, 8), (policy.passwd.max_days, policy.passwd.min_len));
+    assert!(policy.validate().is_ok());
+    policy.passwd.min_days = 91;
+    assert!(policy.validate().is_err());
+
+    let policy = toml::from_str::<Policy>("[audit]\nextra_watches = [\"/etc/hosts\", \"/etc/passwd\"]\n").unwrap();
+    let watches = policy.audit.required_watches();
+    assert_eq!(9, watches.len());
+    assert_eq!("/etc/hosts", watches[8]);
+    assert!(policy.validate().is_ok());
+}
@@ -0,0 +1,82 @@
+//! 扫描前检查依赖的命令与配置文件
+//!
+//! 检查项在命令或文件缺失时只会在终端输出一行错误, 结果则表现为不通过或空白. 扫描前先统一检查一遍,
+//! 在界面和报告中列出缺失的依赖及受影响的检查项, 便于区分"配置不合规"与"无法检查".
+
+use std::path::PathBuf;
+
+use crate::util;
+
+enum Kind {
+    // 可执行文件, 在 PATH 及 sbin 目录中查找
+    Binary,
+    File,
+}
+
+struct Prereq {
+    kind: Kind,
+    // 存在其中任意一个即可
+    names: &'static [&'static str],
+    // 受影响的检查项 ID
+    checks: &'static [&'static str],
+}
+
+const PREREQS: &'static [Prereq] = &[
+    Prereq { kind: Kind::Binary, names: &["bash"], checks: &["acct"] },
+    Prereq { kind: Kind::File, names: &["/etc/login.defs"], checks: &["passwd"] },
+    Prereq { kind: Kind::File, names: &["/etc/pam.d/system-auth", "/etc/pam.d/common-password"], checks: &["passwd"] },
+    Prereq { kind: Kind::Binary, names: &["systemctl", "chkconfig", "rc-update"], checks: &["svc"] },
+    Prereq { kind: Kind::Binary, names: &["auditctl"], checks: &["audit", "devtools"] },
+    Prereq { kind: Kind::Binary, names: &["systemctl", "service", "rc-service"], checks: &["audit"] },
+    Prereq { kind: Kind::File, names: &["/etc/audit/auditd.conf"], checks: &["audit"] },
+    Prereq { kind: Kind::File, names: &["/etc/sysconfig/iptables"], checks: &["fw"] },
+    Prereq { kind: Kind::Binary, names: &["ss"], checks: &["net.port", "net.listen"] },
+    Prereq { kind: Kind::Binary, names: &["curl"], checks: &["cloud"] },
+    Prereq { kind: Kind::File, names: &["/etc/security/limits.conf"], checks: &["limits"] },
+    Prereq { kind: Kind::Binary, names: &["ssh-keygen"], checks: &["sshkey"] },
+];
+
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = match std::env::var_os("PATH") {
+        Some(path) => std::env::split_paths(&path).collect::<Vec<PathBuf>>(),
+        None => vec![],
+    };
+    // 普通用户的 PATH 中通常没有 sbin 目录, 而 auditctl 等命令位于其中
+    for dir in vec!["/sbin", "/usr/sbin", "/usr/local/sbin"] {
+        let dir = PathBuf::from(dir);
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+fn present(prereq: &Prereq, dirs: &[PathBuf]) -> bool {
+    prereq.names.iter().any(|name| match prereq.kind {
+        Kind::Binary => dirs.iter().any(|dir| dir.join(name).is_file()),
+        Kind::File => util::rooted(name).exists(),
+    })
+}
+
+/// 缺失的依赖, 每项形如 "auditctl (audit, devtools)", 括号中为受影响的检查项
+pub fn missing() -> Vec<String> {
+    if !cfg!(target_os = "linux") {
+        return vec![];
+    }
+    let dirs = search_dirs();
+    PREREQS.iter()
+        // 检查备用根目录时不在本机执行命令, 只检查其中的配置文件
+        .filter(|x| util::root().is_none() || matches!(x.kind, Kind::File))
+        .filter(|x| !present(x, &dirs))
+        .map(|x| format!("{} ({})", x.names.join(" / "), x.checks.join(", ")))
+        .collect()
+}
+
+#[test]
+fn test_prereq() {
+    let dirs = vec![PathBuf::from("/no/such/dir")];
+    let prereq = Prereq { kind: Kind::Binary, names: &["sh"], checks: &["acct"] };
+    assert!(!present(&prereq, &dirs));
+    let prereq = Prereq { kind: Kind::File, names: &["/no/such/file", "/"], checks: &["acct"] };
+    assert!(present(&prereq, &dirs));
+}
@@ -0,0 +1,92 @@
+//! 安全要求的完整说明
+//!
+//! 界面与表格中的要求文本很简短, 这里给出每条要求具体要求什么及如何判断, 并附上对应的等保条款.
+
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+
+use crate::gbt22239;
+use crate::i18n;
+
+static REFERENCE: Lazy<Reference> = Lazy::new(|| {
+    toml::from_str(include_str!("../assets/reference.toml")).unwrap()
+});
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reference {
+    pub requirement: Vec<Requirement>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Requirement {
+    pub id: String,
+    pub description: String,
+    // 不符合时的严重程度, 未注明的为中
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    // 可被直接利用, 如默认口令、明文远程登录、基于主机信任的认证
+    High,
+    Medium,
+    // 不直接影响安全, 如时区、内核崩溃转储
+    Low,
+}
+
+impl Default for Severity {
+    fn default() -> Self {
+        Severity::Medium
+    }
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::High => "high",
+            Severity::Medium => "medium",
+            Severity::Low => "low",
+        }
+    }
+}
+
+pub fn requirements() -> &'static [Requirement] {
+    &REFERENCE.requirement
+}
+
+/// 查找安全要求的完整说明
+pub fn description(req: &str) -> Option<&'static str> {
+    requirements().iter().find(|x| x.id == req).map(|x| x.description.as_str())
+}
+
+/// 安全要求不符合时的严重程度, 没有说明的要求为中
+pub fn severity(req: &str) -> Severity {
+    requirements().iter().find(|x| x.id == req).map_or(Severity::Medium, |x| x.severity)
+}
+
+/// 要求文本、完整说明及对应条款, 用于界面中的提示
+pub fn details(req: &str) -> String {
+    let mut lines = vec![i18n::tr(req).to_string()];
+    if let Some(desc) = description(req) {
+        lines.push(desc.to_string());
+    }
+    if let Some(clause) = gbt22239::clause_of(req) {
+        lines.push(format!("GB/T 22239-2019 {} {}: {}", clause.id, clause.control, clause.text));
+    }
+    lines.join("\n")
+}
+
+#[test]
+fn test_reference() {
+    assert!(description("passwd.min_len").unwrap().contains("PASS_MIN_LEN"));
+    assert!(description("no.such.id").is_none());
+    assert_eq!(Severity::High, severity("ssh.default_cred"));
+    assert_eq!(Severity::Medium, severity("passwd.min_len"));
+    assert!(details("net.port.445").contains("8.1.4.4 b)"));
+    // 每条说明都对应目录中的要求
+    for req in requirements() {
+        assert_ne!(req.id, i18n::tr(&req.id));
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use schemars::JsonSchema;
+use serde::{Serialize, Deserialize};
+
+use crate::baseline;
+use crate::check::{self, Check};
+use crate::config;
+use crate::prereq;
+use crate::schema;
+use crate::sysguard::GuardCell;
+use crate::util;
+
+const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// 报告元数据, 用于追溯报告由谁、在何时、以何种方式生成
+#[derive(Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReportMeta {
+    pub operator: String,
+    pub hostname: String,
+    pub version: String,
+    /// 带时区的扫描开始/结束时间, 例如 "2022-05-01 10:00:00 +0800"
+    pub started_at: String,
+    pub finished_at: String,
+    pub args: Vec<String>,
+    /// 扫描时缺失的命令或配置文件及受影响的检查项, 例如 "auditctl (audit, devtools)"
+    #[serde(default)]
+    pub missing: Vec<String>,
+    #[serde(default)]
+    pub header: ReportHeader,
+    /// 扫描时关键配置文件的修改时间(Unix 时间戳, 秒), 之后导出或查看时据此提示结果可能已过时
+    #[serde(default)]
+    pub mtimes: BTreeMap<String, i64>,
+}
+
+/// 报告表头中的单位信息, 导出前在界面中确认, 默认值来自配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReportHeader {
+    /// 单位名称
+    pub unit: String,
+    /// 资产编号
+    pub asset_id: String,
+    /// 责任人
+    pub responsible: String,
+    /// 检查日期, 形如 "2022-05-01"
+    pub audit_date: String,
+}
+
+impl ReportHeader {
+    /// 各字段的名称与值, 按表头中的顺序排列
+    pub fn fields(&self) -> Vec<(&'static str, &str)> {
+        vec![
+            ("单位名称", self.unit.as_str()),
+            ("资产编号", self.asset_id.as_str()),
+            ("责任人", self.responsible.as_str()),
+            ("检查日期", self.audit_date.as_str()),
+        ]
+    }
+}
+
+/// 一次扫描的完整结果, 界面展示与各种导出都基于同一份结果
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub meta: ReportMeta,
+    pub cells: Vec<GuardCell>,
+}
+
+// 记录修改时间的关键配置文件, 目录记录其中的各文件
+const KEY_FILES: &[&str] = &[
+    "/etc/passwd", "/etc/shadow", "/etc/group", "/etc/login.defs", "/etc/profile",
+    "/etc/ssh/sshd_config", "/etc/ssh/sshd_config.d", "/etc/sudoers", "/etc/sudoers.d", "/etc/pam.d",
+    "/etc/sysctl.conf", "/etc/logrotate.conf", "/etc/audit/auditd.conf", "/etc/sysconfig/iptables",
+];
+
+/// 关键配置文件当前的修改时间, 不存在的文件不记录
+pub fn key_file_mtimes() -> BTreeMap<String, i64> {
+    let mut mtimes = BTreeMap::new();
+    for path in KEY_FILES {
+        let rooted = util::rooted(path);
+        let files = if rooted.is_dir() {
+            match std::fs::read_dir(&rooted) {
+                Ok(entries) => entries.filter_map(|x| x.ok())
+                    .map(|x| (format!("{}/{}", path, x.file_name().to_string_lossy()), x.path()))
+                    .collect::<Vec<_>>(),
+                Err(_) => vec![],
+            }
+        } else {
+            vec![(path.to_string(), rooted)]
+        };
+        for (name, file) in files {
+            if let Ok(Ok(mtime)) = std::fs::metadata(&file).and_then(|x| x.modified()).map(|x| x.duration_since(UNIX_EPOCH)) {
+                mtimes.insert(name, mtime.as_secs() as i64);
+            }
+        }
+    }
+    mtimes
+}
+
+// 本机或备用根目录中配置的主机名, 后者未配置时使用目录名
+fn hostname() -> String {
+    match util::root() {
+        Some(root) => match std::fs::read_to_string(root.join("etc/hostname")) {
+            Ok(r) if r.trim().len() > 0 => r.trim().to_string(),
+            _ => root.display().to_string(),
+        },
+        None => match util::runcmd("hostname", None) {
+            Ok(r) => r.trim().to_string(),
+            Err(_) => "".to_string(),
+        },
+    }
+}
+
+/// 带时区的当前时间
+pub fn now() -> String {
+    match util::runcmd("date '+%Y-%m-%d %H:%M:%S %z'", None) {
+        Ok(r) => r.trim().to_string(),
+        Err(_) => {
+            eprintln!("cannot run command 'date'");
+            "".to_string()
+        },
+    }
+}
+
+/// 配置中未指定操作人员时, 使用当前登录用户(经 sudo 运行时取原用户)
+pub fn default_operator() -> String {
+    let operator = &config::get().operator;
+    if operator.len() > 0 {
+        return operator.to_string();
+    }
+    for var in vec!["SUDO_USER", "USER", "LOGNAME"] {
+        if let Ok(user) = std::env::var(var) {
+            if user.len() > 0 {
+                return user;
+            }
+        }
+    }
+    "".to_string()
+}
+
+/// 报告中未填写的表头字段取配置中的默认值, 检查日期默认为扫描日期
+pub fn default_header(meta: &ReportMeta) -> ReportHeader {
+    let defaults = &config::get().header;
+    let pick = |value: &str, default: &str| {
+        if value.len() > 0 { value.to_string() } else { default.to_string() }
+    };
+    let scan_date = meta.started_at.split_whitespace().next().unwrap_or("");
+    ReportHeader {
+        unit: pick(&meta.header.unit, &defaults.unit),
+        asset_id: pick(&meta.header.asset_id, &defaults.asset_id),
+        responsible: pick(&meta.header.responsible, &defaults.responsible),
+        audit_date: pick(&meta.header.audit_date, &pick(&defaults.audit_date, scan_date)),
+    }
+}
+
+impl ScanReport {
+    pub fn scan() -> Self {
+        Self::scan_checks(baseline::get().items())
+    }
+
+    /// 只执行指定的检查项
+    pub fn scan_checks(items: Vec<Arc<dyn Check>>) -> Self {
+        let started_at = now();
+        let missing = prereq::missing();
+        for item in missing.iter() {
+            eprintln!("[!] 缺少依赖: {}", item);
+        }
+        let cells = items.iter().map(|x| check::run(x.as_ref())).collect::<Vec<GuardCell>>();
+        let finished_at = now();
+        let mut meta = ReportMeta {
+            operator: default_operator(),
+            hostname: hostname(),
+            version: VERSION.to_string(),
+            started_at,
+            finished_at,
+            args: std::env::args().skip(1).collect(),
+            missing,
+            header: ReportHeader::default(),
+            mtimes: key_file_mtimes(),
+        };
+        meta.header = default_header(&meta);
+        ScanReport {
+            meta,
+            cells,
+        }
+    }
+
+    /// 内置的演示数据, 用于在非被检查主机上展示界面与导出效果
+    pub fn demo() -> Self {
+        let text = include_str!("../assets/demo_report.json");
+        let mut report = serde_json::from_str::<ScanReport>(text).expect("invalid demo report");
+        report.meta.version = VERSION.to_string();
+        report.meta.args = std::env::args().skip(1).collect();
+        report.meta.header = default_header(&report.meta);
+        report
+    }
+
+    /// 读取保存的 JSON 结果, 支持导出的 JSON 报告及数据库中保存的完整扫描结果
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let mut report = match serde_json::from_str::<ScanReport>(text) {
+            Ok(report) => report,
+            Err(_) => match serde_json::from_str::<schema::Report>(text) {
+                Ok(report) => schema::to_scan_report(report),
+                Err(e) => return Err(format!("无法识别的报告文件: {}", e)),
+            },
+        };
+        schema::migrate(&mut report);
+        Ok(report)
+    }
+
+    /// 扫描后修改、删除或新增的关键配置文件; 报告来自其他主机或没有记录时为空
+    pub fn changed_files(&self) -> Vec<String> {
+        if self.meta.mtimes.len() == 0 || self.meta.hostname != hostname() {
+            return vec![];
+        }
+        let current = key_file_mtimes();
+        let mut changed = self.meta.mtimes.iter()
+            .filter(|(path, mtime)| current.get(*path) != Some(mtime))
+            .map(|(path, _)| path.to_string())
+            .collect::<Vec<String>>();
+        changed.extend(current.keys().filter(|x| !self.meta.mtimes.contains_key(*x)).cloned());
+        changed.sort();
+        changed
+    }
+
+    /// 关键配置文件在扫描后有变化时的提示
+    pub fn stale_warning(&self) -> Option<String> {
+        let changed = self.changed_files();
+        if changed.len() == 0 {
+            return None;
+        }
+        Some(format!("以下配置文件在扫描({})后有变化, 结果可能已不能反映主机当前的状态, 建议重新扫描:\n{}", self.meta.started_at, changed.join("\n")))
+    }
+
+    /// 在所有检查结果中查找指定单元格的内容
+    pub fn get<S>(&self, pos: S) -> String where S: AsRef<str> {
+        for cell in self.cells.iter() {
+            if cell.mp.contains_key(pos.as_ref()) {
+                return cell.get(pos);
+            }
+        }
+        "".to_string()
+    }
+}
+
+#[test]
+fn test_demo() {
+    let report = ScanReport::demo();
+    let checks = check::all();
+    let ids = checks.iter().map(|x| x.id()).collect::<Vec<&str>>();
+    assert_eq!(ids, report.cells.iter().map(|x| x.id.as_str()).collect::<Vec<&str>>());
+    assert_eq!("演示数据", report.meta.operator);
+    assert!(report.changed_files().is_empty());
+}
@@ -0,0 +1,328 @@
+//! 对外发布的 JSON 结果格式
+//!
+//! 供 SIEM、看板等下游系统使用, 结构见 `assets/report.schema.json`, 也可由 `schema` 子命令从代码生成;
+//! 另有每个安全要求一行的 CSV 格式, 列见 `CSV_COLUMNS`. 格式的演进遵循以下约定:
+//!
+//! - 只新增字段, 不删除或重命名已有字段, 也不改变已有字段的含义与类型;
+//! - 检查项与安全要求的 ID 一经发布保持不变, 废弃的要求不再输出, 但其 ID 不会被复用;
+//! - 调整检查项时如需将要求改名或合并, 在 `MIGRATIONS` 中登记旧 ID 到新 ID 的对应关系;
+//! - 出现不兼容的变更时递增 `SCHEMA_VERSION`, 消费方应忽略不认识的字段.
+
+use schemars::JsonSchema;
+use schemars::gen::SchemaSettings;
+use serde::{Serialize, Deserialize};
+
+use crate::check::{self, Check as _};
+use crate::i18n;
+use crate::report::{ReportMeta, ScanReport};
+use crate::sysguard::{GuardCell, GuardItem, Req, ReportRow};
+
+// 2: 安全要求新增状态 partial
+pub const SCHEMA_VERSION: u32 = 2;
+
+// 安全要求 ID 的迁移表(旧 ID, 新 ID, 不能成环), 读取历史扫描结果时按此更新, 使对比与趋势中旧结果仍能对应到新的要求
+const MIGRATIONS: &[(&str, &str)] = &[];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    // 无法自动判断, 需人工核查
+    Manual,
+    // 只能自动检查部分条件, 自动检查的部分符合, 其余条件需人工核查
+    Partial,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Fail => "fail",
+            Status::Manual => "manual",
+            Status::Partial => "partial",
+        }
+    }
+}
+
+impl From<Option<bool>> for Status {
+    fn from(passed: Option<bool>) -> Self {
+        match passed {
+            Some(true) => Status::Pass,
+            Some(false) => Status::Fail,
+            None => Status::Manual,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Requirement {
+    /// 安全要求 ID, 例如 "acct.umask"
+    pub id: String,
+    pub text: String,
+    pub status: Status,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Check {
+    /// 检查项 ID, 例如 "acct"
+    pub id: String,
+    pub title: String,
+    /// 没有安全要求的检查项(例如操作系统、设备 IP)的采集结果
+    #[serde(default)]
+    pub info: Vec<String>,
+    #[serde(default)]
+    pub requirements: Vec<Requirement>,
+    #[serde(default)]
+    pub evidence: Vec<String>,
+    /// 证据来源: 检查时读取的文件及执行的命令, 例如 "/etc/login.defs"、"auditctl -l"
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+/// SH-SDS 扫描结果
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Report {
+    pub schema_version: u32,
+    pub meta: ReportMeta,
+    pub checks: Vec<Check>,
+}
+
+impl From<&GuardCell> for Check {
+    fn from(cell: &GuardCell) -> Self {
+        let info = if cell.reqs.len() == 0 {
+            cell.column('B')
+        } else {
+            vec![]
+        };
+        Check {
+            id: cell.id.to_string(),
+            title: cell.column('A').join(" "),
+            info,
+            requirements: cell.statuses().into_iter().map(|(id, status)| Requirement {
+                text: i18n::tr(&id).to_string(),
+                id,
+                status,
+            }).collect(),
+            evidence: cell.column('C'),
+            sources: cell.sources.clone(),
+        }
+    }
+}
+
+impl From<&ScanReport> for Report {
+    fn from(report: &ScanReport) -> Self {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            meta: report.meta.clone(),
+            checks: report.cells.iter().map(|x| Check::from(x)).collect(),
+        }
+    }
+}
+
+/// 将 JSON 结果还原为扫描结果以便重新查看, 每个检查项的要求与备注分别合并到其标题所在行
+pub fn to_scan_report(report: Report) -> ScanReport {
+    let mut cells = vec![];
+    for check in report.checks {
+        let row = match check::find(&check.id) {
+            Some(item) => item.row(),
+            None => continue,
+        };
+        let mut result = ReportRow::new(check.id);
+        for info in check.info {
+            result = result.info(info);
+        }
+        for req in check.requirements {
+            let id = Req::from(req.id);
+            result = match req.status {
+                Status::Pass => result.status(id, Some(true)),
+                Status::Fail => result.status(id, Some(false)),
+                Status::Manual => result.status(id, None),
+                Status::Partial => result.partial(id, Some(true)),
+            };
+        }
+        for evidence in check.evidence {
+            result = result.evidence(evidence);
+        }
+        let mut cell = result.into_cell(row, &check.title);
+        cell.sources = check.sources;
+        cells.push(cell);
+    }
+    ScanReport {
+        meta: report.meta,
+        cells,
+    }
+}
+
+/// 查找旧 ID 对应的当前安全要求 ID, 未迁移时返回原 ID
+pub fn migrate_id(id: &str) -> &str {
+    migrate_id_in(id, MIGRATIONS)
+}
+
+fn migrate_id_in<'a>(id: &'a str, migrations: &[(&'a str, &'a str)]) -> &'a str {
+    let mut id = id;
+    // 可能经过多次改名, 依次查找直到最新的 ID
+    while let Some(&(_, new)) = migrations.iter().find(|x| x.0 == id) {
+        id = new;
+    }
+    id
+}
+
+/// 将历史扫描结果中的检查项与安全要求 ID 更新为当前版本
+pub fn migrate(report: &mut ScanReport) {
+    migrate_in(report, MIGRATIONS)
+}
+
+fn migrate_in(report: &mut ScanReport, migrations: &[(&str, &str)]) {
+    for cell in report.cells.iter_mut() {
+        // 早期版本的结果中没有检查项 ID, 按标题所在行推断
+        if cell.id.len() == 0 {
+            if let Some(item) = GuardItem::all().into_iter().find(|x| cell.mp.contains_key(&format!("A{}", x.row()))) {
+                cell.id = item.id().to_string();
+            }
+        }
+        for req in cell.reqs.iter_mut() {
+            let id = migrate_id_in(&req.0, migrations).to_string();
+            req.0 = id;
+        }
+    }
+}
+
+pub fn to_json(report: &ScanReport) -> Result<String, String> {
+    serde_json::to_string_pretty(&Report::from(report)).map_err(|e| format!("cannot serialize report: {:?}", e))
+}
+
+/// 由 `Report` 的定义生成的 JSON Schema(draft-07), 子结构直接内联
+pub fn json_schema() -> serde_json::Value {
+    let generator = SchemaSettings::draft07().with(|x| x.inline_subschemas = true).into_generator();
+    let mut schema = serde_json::to_value(generator.into_root_schema_for::<Report>()).expect("invalid schema");
+    schema["$id"] = "https://github.com/YoungD96/SH-SDS/assets/report.schema.json".into();
+    schema["properties"]["schema_version"]["const"] = SCHEMA_VERSION.into();
+    schema
+}
+
+/// CSV 结果的列名及说明
+pub const CSV_COLUMNS: &[(&str, &str)] = &[
+    ("hostname", "主机名"),
+    ("started_at", "带时区的扫描开始时间, 例如 \"2022-05-01 10:00:00 +0800\""),
+    ("check_id", "检查项 ID, 例如 \"acct\""),
+    ("check_title", "检查项标题"),
+    ("requirement_id", "安全要求 ID, 例如 \"acct.umask\""),
+    ("requirement_text", "安全要求"),
+    ("status", "pass、fail、manual 或 partial"),
+];
+
+// 按 RFC 4180 在需要时加引号
+fn csv_field(text: &str) -> String {
+    if text.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", text.replace("\"", "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// 列数由 `CSV_COLUMNS` 决定, 增减列时两处必须一起修改
+fn csv_row(meta: &ReportMeta, check: &Check, req: &Requirement) -> [String; CSV_COLUMNS.len()] {
+    [
+        meta.hostname.to_string(),
+        meta.started_at.to_string(),
+        check.id.to_string(),
+        check.title.to_string(),
+        req.id.to_string(),
+        req.text.to_string(),
+        req.status.as_str().to_string(),
+    ]
+}
+
+/// 每个安全要求一行的 CSV, 第一行为列名; 只有采集信息的检查项不输出
+pub fn to_csv(report: &ScanReport) -> String {
+    let report = Report::from(report);
+    let mut lines = vec![CSV_COLUMNS.iter().map(|x| x.0).collect::<Vec<&str>>().join(",")];
+    for check in report.checks.iter() {
+        for req in check.requirements.iter() {
+            lines.push(csv_row(&report.meta, check, req).iter().map(|x| csv_field(x)).collect::<Vec<String>>().join(","));
+        }
+    }
+    lines.push("".to_string());
+    lines.join("\r\n")
+}
+
+#[test]
+fn test_schema() {
+    let report = ScanReport::demo();
+    let json = to_json(&report).unwrap();
+    let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+    assert_eq!(SCHEMA_VERSION as u64, value["schema_version"].as_u64().unwrap());
+    assert_eq!("os", value["checks"][0]["id"]);
+    assert_eq!("acct.expired", value["checks"][2]["requirements"][0]["id"]);
+    assert_eq!("partial", value["checks"][2]["requirements"][0]["status"]);
+
+    // 消费方按旧版本结构解析时忽略新增字段
+    let report = serde_json::from_str::<Report>(&json).unwrap();
+    assert_eq!(Status::Pass, report.checks[2].requirements[1].status);
+
+    let mut report = to_scan_report(report);
+    assert_eq!("acct", report.cells[2].id);
+    assert_eq!(vec!["acct.expired".to_string()], report.cells[2].partial);
+    assert!(report.cells[2].get("B8").starts_with("[◐]"));
+
+    assert_eq!("acct.umask", migrate_id("acct.umask"));
+    // 迁移后的 ID 必须是当前目录中的要求, 旧 ID 不能被复用
+    for (old, new) in MIGRATIONS {
+        assert_eq!(*old, i18n::tr(old));
+        assert_ne!(*new, i18n::tr(new));
+    }
+    report.cells[2].id = "".to_string();
+    migrate(&mut report);
+    assert_eq!("acct", report.cells[2].id);
+    assert_eq!(Some(true), report.cells[2].reqs[1].1);
+    assert_eq!("demo-host", report.meta.hostname);
+
+    // 第 1 版结果中的要求经过两次改名: acct.perm -> acct.user_umask -> acct.umask
+    let migrations = [("acct.user_umask", "acct.umask"), ("acct.perm", "acct.user_umask")];
+    let mut value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+    value["schema_version"] = 1.into();
+    value["checks"][2]["requirements"][1]["id"] = "acct.perm".into();
+    let mut report = to_scan_report(serde_json::from_value::<Report>(value).unwrap());
+    assert_eq!("acct.perm", report.cells[2].reqs[1].0);
+    migrate_in(&mut report, &migrations);
+    assert_eq!(("acct.umask".to_string(), Some(true)), report.cells[2].reqs[1]);
+    assert_eq!("acct.expired", migrate_id_in("acct.expired", &migrations));
+}
+
+#[test]
+fn test_json_schema() {
+    // 字段路径及必需字段, 忽略说明、默认值等
+    fn fields(value: &serde_json::Value, path: &str, result: &mut Vec<String>) {
+        if let Some(properties) = value["properties"].as_object() {
+            for (name, value) in properties {
+                let path = format!("{}/{}", path, name);
+                result.push(path.to_string());
+                fields(value, &path, result);
+            }
+        }
+        if let Some(required) = value["required"].as_array() {
+            result.extend(required.iter().map(|x| format!("{}!{}", path, x.as_str().unwrap_or(""))));
+        }
+        if value["items"].is_object() {
+            fields(&value["items"], &format!("{}[]", path), result);
+        }
+    }
+    // 发布的 assets/report.schema.json 须与代码生成的一致
+    let mut generated = vec![];
+    fields(&json_schema(), "", &mut generated);
+    let mut published = vec![];
+    fields(&serde_json::from_str(include_str!("../assets/report.schema.json")).unwrap(), "", &mut published);
+    generated.sort();
+    published.sort();
+    assert_eq!(published, generated);
+    assert_eq!(2, json_schema()["properties"]["schema_version"]["const"]);
+
+    let report = ScanReport::demo();
+    let csv = to_csv(&report);
+    let lines = csv.trim_end().split("\r\n").collect::<Vec<&str>>();
+    assert_eq!("hostname,started_at,check_id,check_title,requirement_id,requirement_text,status", lines[0]);
+    assert_eq!(report.cells.iter().map(|x| x.reqs.len()).sum::<usize>() + 1, lines.len());
+    assert!(lines[1].starts_with("demo-host,") && lines[1].ends_with(",partial"));
+    assert_eq!("\"a,\"\"b\"\"\"", csv_field("a,\"b\""));
+}
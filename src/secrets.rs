@@ -0,0 +1,171 @@
+//! 口令等凭据的加密存储
+//!
+//! 上传及发送邮件所需的口令不写在 config.toml 中, 而是以 `secret set` 保存到配置目录下的 `secrets.age`:
+//! 内容为 TOML 格式的 "名称 = 口令", 以 age 的口令方式(scrypt)加密, 文件权限为 600. 读取时依次使用
+//! 环境变量 `SH_SDS_PASSPHRASE`、界面中的输入框或终端提示获取主口令, 同一进程中只询问一次.
+//! 无人值守运行时也可以直接以环境变量 `SH_SDS_<UPLOAD|MAIL>_PASSWORD` 提供口令.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use age::secrecy::SecretString;
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::config;
+
+/// 可以保存的凭据
+pub const NAMES: &[&str] = &["upload.password", "mail.password"];
+
+static PASSPHRASE: Lazy<Mutex<Option<SecretString>>> = Lazy::new(|| Mutex::new(None));
+// 图形界面中询问主口令的方式, 未设置时在终端中询问
+static PROMPT: OnceCell<fn(&str) -> Option<String>> = OnceCell::new();
+
+pub fn path() -> PathBuf {
+    config::dir().join("secrets.age")
+}
+
+pub fn set_prompt(prompt: fn(&str) -> Option<String>) {
+    let _ = PROMPT.set(prompt);
+}
+
+// 主口令; `confirm` 为 true 时用于新建存储, 在终端中要求输入两次
+fn passphrase(confirm: bool) -> Result<SecretString, String> {
+    let mut cached = PASSPHRASE.lock().unwrap();
+    if let Some(passphrase) = cached.as_ref() {
+        return Ok(passphrase.clone());
+    }
+    let passphrase = match std::env::var("SH_SDS_PASSPHRASE") {
+        Ok(passphrase) if passphrase.len() > 0 => passphrase,
+        _ => match PROMPT.get() {
+            Some(prompt) => prompt(&format!("输入凭据存储 {} 的主口令", path().display()))
+                .ok_or("未输入主口令".to_string())?,
+            None => {
+                let passphrase = rpassword::prompt_password(format!("{} 的主口令: ", path().display()))
+                    .map_err(|e| format!("cannot read passphrase (set SH_SDS_PASSPHRASE when running unattended): {:?}", e))?;
+                if confirm && rpassword::prompt_password("再次输入主口令: ").ok().as_deref() != Some(passphrase.as_str()) {
+                    return Err("两次输入的主口令不一致".to_string());
+                }
+                passphrase
+            },
+        },
+    };
+    if passphrase.len() == 0 {
+        return Err("主口令不能为空".to_string());
+    }
+    let passphrase = SecretString::from(passphrase);
+    *cached = Some(passphrase.clone());
+    Ok(passphrase)
+}
+
+fn seal(secrets: &BTreeMap<String, String>, passphrase: SecretString) -> Result<Vec<u8>, String> {
+    let text = toml::to_string(secrets).map_err(|e| format!("cannot serialize secrets: {:?}", e))?;
+    let mut sealed = vec![];
+    let mut writer = age::Encryptor::with_user_passphrase(passphrase)
+        .wrap_output(&mut sealed)
+        .map_err(|e| format!("cannot encrypt secrets: {:?}", e))?;
+    writer.write_all(text.as_bytes()).map_err(|e| format!("cannot encrypt secrets: {:?}", e))?;
+    writer.finish().map_err(|e| format!("cannot encrypt secrets: {:?}", e))?;
+    Ok(sealed)
+}
+
+fn unseal(sealed: &[u8], passphrase: SecretString) -> Result<BTreeMap<String, String>, String> {
+    let decryptor = age::Decryptor::new(sealed).map_err(|e| format!("invalid secrets file: {:?}", e))?;
+    let identity = age::scrypt::Identity::new(passphrase);
+    let mut reader = decryptor.decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|_| "主口令错误或凭据存储已损坏".to_string())?;
+    let mut text = String::new();
+    reader.read_to_string(&mut text).map_err(|e| format!("cannot decrypt secrets: {:?}", e))?;
+    toml::from_str(&text).map_err(|e| format!("invalid secrets: {:?}", e))
+}
+
+fn load(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let sealed = std::fs::read(path).map_err(|e| format!("cannot read {}: {:?}", path.display(), e))?;
+    let secrets = unseal(&sealed, passphrase(false)?);
+    // 主口令错误时不保留, 以便重新输入
+    if secrets.is_err() {
+        *PASSPHRASE.lock().unwrap() = None;
+    }
+    secrets
+}
+
+// 先写入同目录下的临时文件再改名, 文件在写入前即为 600
+fn save(path: &Path, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+    let sealed = seal(secrets, passphrase(!path.exists())?)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {:?}", dir.display(), e))?;
+    }
+    let tmp = path.with_extension("age.tmp");
+    let _ = std::fs::remove_file(&tmp);
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&tmp)
+        .map_err(|e| format!("cannot create {}: {:?}", tmp.display(), e))?;
+    file.write_all(&sealed).map_err(|e| format!("cannot write {}: {:?}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("cannot write {}: {:?}", path.display(), e))
+}
+
+/// 保存的凭据名称
+pub fn names() -> Result<Vec<String>, String> {
+    let path = path();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(load(&path)?.into_keys().collect())
+}
+
+pub fn set(name: &str, value: &str) -> Result<(), String> {
+    if !NAMES.contains(&name) {
+        return Err(format!("unknown secret '{}', expected one of: {}", name, NAMES.join(", ")));
+    }
+    let path = path();
+    let mut secrets = if path.exists() { load(&path)? } else { BTreeMap::new() };
+    secrets.insert(name.to_string(), value.to_string());
+    save(&path, &secrets)
+}
+
+/// 删除凭据, 不存在时返回 false
+pub fn remove(name: &str) -> Result<bool, String> {
+    let path = path();
+    if !path.exists() {
+        return Ok(false);
+    }
+    let mut secrets = load(&path)?;
+    if secrets.remove(name).is_none() {
+        return Ok(false);
+    }
+    save(&path, &secrets).map(|_| true)
+}
+
+/// `[upload]` 或 `[mail]` 使用的口令: 依次取环境变量 `SH_SDS_<SECTION>_PASSWORD`、config.toml 中旧的
+/// `password`(只在配置文件权限为 600 时读取)及加密存储中的 `<section>.password`, 都没有时为空
+pub fn password(section: &str, legacy: &str) -> Result<String, String> {
+    if let Ok(password) = std::env::var(format!("SH_SDS_{}_PASSWORD", section.to_uppercase())) {
+        if password.len() > 0 {
+            return Ok(password);
+        }
+    }
+    if legacy.len() > 0 {
+        return Ok(legacy.to_string());
+    }
+    let path = path();
+    if !path.exists() {
+        return Ok("".to_string());
+    }
+    Ok(load(&path)?.remove(&format!("{}.password", section)).unwrap_or_default())
+}
+
+/// 文件是否可被属主以外的用户读取
+pub fn readable_by_others(path: &Path) -> bool {
+    std::fs::metadata(path).map_or(false, |x| x.permissions().mode() & 0o077 != 0)
+}
+
+#[test]
+fn test_seal() {
+    let mut secrets = BTreeMap::new();
+    secrets.insert("mail.password".to_string(), "s3cret \"quoted\"".to_string());
+    let sealed = seal(&secrets, SecretString::from("passphrase".to_string())).unwrap();
+    assert!(!String::from_utf8_lossy(&sealed).contains("s3cret"));
+    assert_eq!(secrets, unseal(&sealed, SecretString::from("passphrase".to_string())).unwrap());
+    assert!(unseal(&sealed, SecretString::from("wrong".to_string())).is_err());
+}
@@ -0,0 +1,479 @@
+//! 扫描结果数据库
+//!
+//! 可选地将每次扫描写入本地 SQLite 数据库, 以结构化的方式保存主机、扫描、安全要求及其检查结果,
+//! 历史查询、对比等功能都基于这里的数据.
+//!
+//! 图形界面、命令行与定时任务可能同时写入同一个数据库: 写入前对 `<数据库>.lock` 加排他锁(flock),
+//! 数据库使用 WAL 日志并在繁忙时等待, 每次扫描在一个立即获取写锁的事务中写入. 数据库损坏时将其改名保留,
+//! 并从中尽量恢复保存的完整扫描结果到新建的数据库.
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use errlog::{elog, AnyResult, AnyContext};
+use fs2::FileExt;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, TransactionBehavior};
+
+use crate::config;
+use crate::i18n;
+use crate::report::ScanReport;
+use crate::schema::{self, Status};
+
+const SCHEMA: &'static str = "
+CREATE TABLE IF NOT EXISTS hosts (
+    id INTEGER PRIMARY KEY,
+    hostname TEXT NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    host_id INTEGER NOT NULL REFERENCES hosts(id),
+    operator TEXT NOT NULL,
+    version TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    args TEXT NOT NULL,
+    -- 完整的扫描结果(JSON), 用于重新打开报告
+    report TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS requirements (
+    id TEXT PRIMARY KEY,
+    check_id TEXT NOT NULL,
+    text TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS statuses (
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    requirement_id TEXT NOT NULL REFERENCES requirements(id),
+    status TEXT NOT NULL,
+    PRIMARY KEY (scan_id, requirement_id)
+);
+-- 各主机最近一次发送的合规摘要所依据的扫描
+CREATE TABLE IF NOT EXISTS digests (
+    host_id INTEGER PRIMARY KEY REFERENCES hosts(id),
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    sent_at TEXT NOT NULL
+);
+";
+
+// 其他进程持有数据库写锁时的最长等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 一次扫描的概要
+#[derive(Debug, Clone)]
+pub struct ScanRow {
+    pub id: i64,
+    pub hostname: String,
+    pub operator: String,
+    pub started_at: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub manual: usize,
+    pub partial: usize,
+}
+
+impl ScanRow {
+    /// 合规率(百分比), 即自动判断的要求中符合的比例, 部分自动检查的要求按自动检查的部分计入; 没有可自动判断的要求时为 None
+    pub fn score(&self) -> Option<f64> {
+        let checked = self.passed + self.partial + self.failed;
+        if checked == 0 {
+            None
+        } else {
+            Some((self.passed + self.partial) as f64 * 100.0 / checked as f64)
+        }
+    }
+}
+
+/// 多台主机共同存在的一项不符合要求
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub requirement_id: String,
+    pub text: String,
+    pub hosts: Vec<String>,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+/// 数据库文件位置, 未配置时为配置目录下的 `results.db`
+pub fn path() -> PathBuf {
+    let path = &config::get().store.path;
+    if path.len() > 0 {
+        PathBuf::from(path)
+    } else {
+        config::dir().join("results.db")
+    }
+}
+
+/// 按配置将扫描结果写入数据库, 未启用时不做任何操作
+pub fn record(report: &ScanReport) {
+    if !config::get().store.enabled {
+        return;
+    }
+    let path = path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let result = write_lock(&path).and_then(|_lock| {
+        Store::open_or_recover(&path).and_then(|mut store| store.insert(report))
+    });
+    if let Err(e) = result {
+        eprintln!("cannot save scan to {}: {:?}", path.display(), e);
+    }
+}
+
+/// 获取数据库的写锁, 其他进程正在写入时等待其完成; 返回的文件关闭时释放锁
+pub fn write_lock(path: &Path) -> AnyResult<File> {
+    let lock = PathBuf::from(format!("{}.lock", path.display()));
+    let file = OpenOptions::new().read(true).write(true).create(true).open(&lock)
+        .or_else(|_| File::open(&lock))
+        .context(elog!("failed to open lock file {}", lock.display()))?;
+    file.lock_exclusive().context(elog!("failed to lock {}", lock.display()))?;
+    Ok(file)
+}
+
+// 能够读取且完整性检查通过
+fn intact(conn: &Connection) -> bool {
+    match conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0)) {
+        Ok(result) => result == "ok",
+        Err(_) => false,
+    }
+}
+
+// 将损坏的数据库改名保留, 新建数据库并写入其中仍能读出的扫描, 返回恢复的扫描数; 调用方需持有写锁
+fn recover(path: &Path) -> AnyResult<usize> {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    let backup = PathBuf::from(format!("{}.corrupt-{}", path.display(), secs));
+    std::fs::rename(path, &backup).context(elog!("failed to move {} to {}", path.display(), backup.display()))?;
+    // WAL 日志属于损坏的数据库, 一并移走
+    for suffix in ["-wal", "-shm"] {
+        let journal = format!("{}{}", path.display(), suffix);
+        if Path::new(&journal).exists() {
+            let _ = std::fs::rename(&journal, format!("{}{}", backup.display(), suffix));
+        }
+    }
+
+    let mut store = Store::open(path)?;
+    let mut reports = vec![];
+    if let Ok(old) = Connection::open_with_flags(&backup, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        if let Ok(mut stmt) = old.prepare("SELECT report FROM scans ORDER BY id") {
+            if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
+                reports = rows.flatten().collect::<Vec<String>>();
+            }
+        }
+    }
+    let mut recovered = 0;
+    for json in reports {
+        if let Ok(mut report) = serde_json::from_str::<ScanReport>(&json) {
+            schema::migrate(&mut report);
+            if store.insert(&report).is_ok() {
+                recovered += 1;
+            }
+        }
+    }
+    eprintln!("{} is corrupted, moved to {} and recovered {} scans", path.display(), backup.display(), recovered);
+    Ok(recovered)
+}
+
+/// 按配置读取指定主机的历史扫描, 按时间先后排列; 未启用或读取失败时为空
+pub fn history(hostname: &str) -> Vec<ScanRow> {
+    if !config::get().store.enabled {
+        return vec![];
+    }
+    let path = path();
+    if !path.exists() {
+        return vec![];
+    }
+    match Store::open(&path).and_then(|store| store.scans(Some(hostname))) {
+        Ok(mut scans) => {
+            scans.reverse();
+            scans
+        },
+        Err(e) => {
+            eprintln!("cannot read scans from {}: {:?}", path.display(), e);
+            vec![]
+        },
+    }
+}
+
+/// 按配置读取指定主机最近一次扫描中各要求的状态; 未启用、没有记录或读取失败时为空
+pub fn last_statuses(hostname: &str) -> Vec<(String, Status)> {
+    let last = match history(hostname).pop() {
+        Some(last) => last,
+        None => return vec![],
+    };
+    match Store::open(path()).and_then(|store| store.statuses(last.id)) {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            eprintln!("cannot read scan {}: {:?}", last.id, e);
+            vec![]
+        },
+    }
+}
+
+fn status_str(status: Status) -> &'static str {
+    match status {
+        Status::Pass => "pass",
+        Status::Fail => "fail",
+        Status::Manual => "manual",
+        Status::Partial => "partial",
+    }
+}
+
+fn parse_status(status: &str) -> Status {
+    match status {
+        "pass" => Status::Pass,
+        "fail" => Status::Fail,
+        "partial" => Status::Partial,
+        _ => Status::Manual,
+    }
+}
+
+impl Store {
+    pub fn open<P: AsRef<Path>>(path: P) -> AnyResult<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path).context(elog!("failed to open database {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    pub fn open_in_memory() -> AnyResult<Self> {
+        let conn = Connection::open_in_memory().context(elog!("failed to open in-memory database"))?;
+        Self::init(conn)
+    }
+
+    /// 打开数据库, 文件已损坏时先恢复; 调用方需持有写锁
+    pub fn open_or_recover<P: AsRef<Path>>(path: P) -> AnyResult<Self> {
+        let path = path.as_ref();
+        let corrupted = match Connection::open(path) {
+            Ok(conn) => !intact(&conn),
+            Err(_) => false,
+        };
+        if corrupted {
+            recover(path)?;
+        }
+        Self::open(path)
+    }
+
+    fn init(conn: Connection) -> AnyResult<Self> {
+        conn.busy_timeout(BUSY_TIMEOUT).context(elog!("failed to set busy timeout"))?;
+        // WAL 模式下读取不会阻塞写入; 内存数据库返回 "memory"
+        conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))
+            .context(elog!("failed to set journal mode"))?;
+        conn.execute_batch(SCHEMA).context(elog!("failed to create tables"))?;
+        Ok(Store { conn })
+    }
+
+    /// 写入一次扫描, 返回扫描 ID
+    pub fn insert(&mut self, report: &ScanReport) -> AnyResult<i64> {
+        let meta = &report.meta;
+        let json = serde_json::to_string(report).context(elog!("failed to serialize report"))?;
+        // 开始时即获取写锁, 避免与其他写入者在事务中途因锁升级失败
+        let tx = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)
+            .context(elog!("failed to begin transaction"))?;
+
+        tx.execute("INSERT OR IGNORE INTO hosts (hostname) VALUES (?1)", params![meta.hostname])
+            .context(elog!("failed to insert host"))?;
+        let host_id: i64 = tx.query_row("SELECT id FROM hosts WHERE hostname = ?1", params![meta.hostname], |row| row.get(0))
+            .context(elog!("failed to query host"))?;
+        tx.execute(
+            "INSERT INTO scans (host_id, operator, version, started_at, finished_at, args, report) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![host_id, meta.operator, meta.version, meta.started_at, meta.finished_at, meta.args.join(" "), json],
+        ).context(elog!("failed to insert scan"))?;
+        let scan_id = tx.last_insert_rowid();
+
+        for cell in report.cells.iter() {
+            for (id, status) in cell.statuses() {
+                tx.execute(
+                    "INSERT OR REPLACE INTO requirements (id, check_id, text) VALUES (?1, ?2, ?3)",
+                    params![id, cell.id, i18n::tr(&id)],
+                ).context(elog!("failed to insert requirement {}", id))?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO statuses (scan_id, requirement_id, status) VALUES (?1, ?2, ?3)",
+                    params![scan_id, id, status_str(status)],
+                ).context(elog!("failed to insert status of {}", id))?;
+            }
+        }
+
+        tx.commit().context(elog!("failed to commit scan"))?;
+        Ok(scan_id)
+    }
+
+    /// 按时间倒序列出扫描, 可以只列出指定主机的扫描
+    pub fn scans(&self, hostname: Option<&str>) -> AnyResult<Vec<ScanRow>> {
+        let mut stmt = self.conn.prepare("
+            SELECT scans.id, hosts.hostname, scans.operator, scans.started_at,
+                (SELECT COUNT(*) FROM statuses WHERE scan_id = scans.id AND status = 'pass'),
+                (SELECT COUNT(*) FROM statuses WHERE scan_id = scans.id AND status = 'fail'),
+                (SELECT COUNT(*) FROM statuses WHERE scan_id = scans.id AND status = 'manual'),
+                (SELECT COUNT(*) FROM statuses WHERE scan_id = scans.id AND status = 'partial')
+            FROM scans JOIN hosts ON scans.host_id = hosts.id
+            WHERE ?1 IS NULL OR hosts.hostname = ?1
+            ORDER BY scans.id DESC
+        ").context(elog!("failed to prepare query"))?;
+        let rows = stmt.query_map(params![hostname], |row| {
+            Ok(ScanRow {
+                id: row.get(0)?,
+                hostname: row.get(1)?,
+                operator: row.get(2)?,
+                started_at: row.get(3)?,
+                passed: row.get::<_, i64>(4)? as usize,
+                failed: row.get::<_, i64>(5)? as usize,
+                manual: row.get::<_, i64>(6)? as usize,
+                partial: row.get::<_, i64>(7)? as usize,
+            })
+        }).context(elog!("failed to query scans"))?;
+        let mut scans = vec![];
+        for row in rows {
+            scans.push(row.context(elog!("failed to read scan"))?);
+        }
+        Ok(scans)
+    }
+
+    /// 某次扫描中各安全要求的检查结果
+    pub fn statuses(&self, scan_id: i64) -> AnyResult<Vec<(String, Status)>> {
+        let mut stmt = self.conn.prepare("SELECT requirement_id, status FROM statuses WHERE scan_id = ?1 ORDER BY rowid")
+            .context(elog!("failed to prepare query"))?;
+        let rows = stmt.query_map(params![scan_id], |row| {
+            let id: String = row.get(0)?;
+            let status: String = row.get(1)?;
+            Ok((schema::migrate_id(&id).to_string(), parse_status(&status)))
+        }).context(elog!("failed to query statuses"))?;
+        let mut statuses = vec![];
+        for row in rows {
+            statuses.push(row.context(elog!("failed to read status"))?);
+        }
+        Ok(statuses)
+    }
+
+    /// 汇总各主机最近一次扫描中不符合的要求, 按不符合的主机数从多到少排列, 便于按问题安排整改
+    pub fn findings(&self) -> AnyResult<Vec<Finding>> {
+        let mut stmt = self.conn.prepare("
+            SELECT statuses.requirement_id, requirements.text, hosts.hostname
+            FROM statuses
+                JOIN scans ON statuses.scan_id = scans.id
+                JOIN hosts ON scans.host_id = hosts.id
+                JOIN requirements ON statuses.requirement_id = requirements.id
+            WHERE statuses.status = 'fail'
+                AND scans.id = (SELECT MAX(id) FROM scans AS latest WHERE latest.host_id = scans.host_id)
+            ORDER BY statuses.rowid
+        ").context(elog!("failed to prepare query"))?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        }).context(elog!("failed to query findings"))?;
+        let mut findings: Vec<Finding> = vec![];
+        for row in rows {
+            let (id, text, hostname) = row.context(elog!("failed to read finding"))?;
+            let id = schema::migrate_id(&id).to_string();
+            match findings.iter_mut().find(|x| x.requirement_id == id) {
+                Some(finding) => finding.hosts.push(hostname),
+                None => findings.push(Finding { requirement_id: id, text, hosts: vec![hostname] }),
+            }
+        }
+        for finding in findings.iter_mut() {
+            finding.hosts.sort();
+            finding.hosts.dedup();
+        }
+        // 主机数相同时保持要求在报告中的顺序
+        findings.sort_by(|a, b| b.hosts.len().cmp(&a.hosts.len()));
+        Ok(findings)
+    }
+
+    /// 指定主机最近一次发送的合规摘要所依据的扫描 ID
+    pub fn last_digest(&self, hostname: &str) -> AnyResult<Option<i64>> {
+        self.conn.query_row(
+            "SELECT digests.scan_id FROM digests JOIN hosts ON digests.host_id = hosts.id WHERE hosts.hostname = ?1",
+            params![hostname],
+            |row| row.get(0),
+        ).optional().context(elog!("failed to query digest of {}", hostname))
+    }
+
+    /// 记录合规摘要已包含到指定扫描, 下次摘要从这次扫描开始比较
+    pub fn set_digest(&self, scan_id: i64, sent_at: &str) -> AnyResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO digests (host_id, scan_id, sent_at) SELECT host_id, id, ?2 FROM scans WHERE id = ?1",
+            params![scan_id, sent_at],
+        ).context(elog!("failed to record digest of scan {}", scan_id))?;
+        Ok(())
+    }
+
+    /// 读取完整的扫描结果
+    pub fn load(&self, scan_id: i64) -> AnyResult<Option<ScanReport>> {
+        let json: Option<String> = self.conn.query_row("SELECT report FROM scans WHERE id = ?1", params![scan_id], |row| row.get(0))
+            .optional()
+            .context(elog!("failed to query scan {}", scan_id))?;
+        match json {
+            Some(json) => {
+                let mut report = serde_json::from_str(&json).context(elog!("invalid report of scan {}", scan_id))?;
+                schema::migrate(&mut report);
+                Ok(Some(report))
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+#[test]
+fn test_store() {
+    let mut store = Store::open_in_memory().unwrap();
+    let report = ScanReport::demo();
+    let first = store.insert(&report).unwrap();
+    let second = store.insert(&report).unwrap();
+
+    let scans = store.scans(Some("demo-host")).unwrap();
+    assert_eq!(vec![second, first], scans.iter().map(|x| x.id).collect::<Vec<i64>>());
+    let total = report.cells.iter().map(|x| x.reqs.len()).sum::<usize>();
+    assert_eq!(total, scans[0].passed + scans[0].failed + scans[0].manual + scans[0].partial);
+    let score = scans[0].score().unwrap();
+    assert!(score > 0.0 && score < 100.0);
+    assert_eq!(0, store.scans(Some("no-such-host")).unwrap().len());
+
+    let statuses = store.statuses(first).unwrap();
+    assert_eq!(("acct.expired".to_string(), Status::Partial), statuses[0]);
+    assert_eq!("demo-host", store.load(first).unwrap().unwrap().meta.hostname);
+    assert!(store.load(0).unwrap().is_none());
+
+    assert_eq!(None, store.last_digest("demo-host").unwrap());
+    store.set_digest(first, "").unwrap();
+    store.set_digest(second, "").unwrap();
+    assert_eq!(Some(second), store.last_digest("demo-host").unwrap());
+}
+
+#[test]
+fn test_findings() {
+    let mut store = Store::open_in_memory().unwrap();
+    let mut report = ScanReport::demo();
+    store.insert(&report).unwrap();
+    report.meta.hostname = "web-01".to_string();
+    store.insert(&report).unwrap();
+    // 同一主机只统计最近一次扫描
+    report.cells.iter_mut().flat_map(|x| x.reqs.iter_mut()).for_each(|x| if x.1 == Some(false) { x.1 = Some(true) });
+    let (id, _) = report.cells.iter().flat_map(|x| x.reqs.iter()).find(|x| x.1 == Some(true)).unwrap().clone();
+    report.cells.iter_mut().flat_map(|x| x.reqs.iter_mut()).find(|x| x.0 == id).unwrap().1 = Some(false);
+    report.meta.hostname = "db-01".to_string();
+    store.insert(&report).unwrap();
+    store.insert(&report).unwrap();
+
+    let findings = store.findings().unwrap();
+    assert_eq!(vec!["demo-host", "web-01"], findings[0].hosts);
+    let finding = findings.iter().find(|x| x.requirement_id == id).unwrap();
+    assert_eq!(vec!["db-01".to_string()], finding.hosts);
+    assert_eq!(i18n::tr(&id), finding.text);
+}
+
+#[test]
+fn test_recover() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("results.db");
+    let report = ScanReport::demo();
+    Store::open(&path).unwrap().insert(&report).unwrap();
+    assert_eq!(1, Store::open_or_recover(&path).unwrap().scans(None).unwrap().len());
+
+    std::fs::write(&path, "not a database").unwrap();
+    let store = Store::open_or_recover(&path).unwrap();
+    assert_eq!(0, store.scans(None).unwrap().len());
+    let backups = std::fs::read_dir(dir.path()).unwrap()
+        .flatten()
+        .filter(|x| x.file_name().to_string_lossy().starts_with("results.db.corrupt-"))
+        .count();
+    assert_eq!(1, backups);
+}
@@ -1,20 +1,51 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::net::TcpListener;
+#[cfg(target_os = "linux")]
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(target_os = "linux")]
+use std::net::{SocketAddr, TcpStream};
+#[cfg(target_os = "linux")]
+use std::process::Command;
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::MetadataExt;
 
+use clap::ArgEnum;
 use pnet::datalink;
+#[cfg(target_os = "linux")]
 use regex::Regex;
 use serde::{Serialize, Deserialize};
-use indoc::formatdoc;
 
+use crate::check::Check;
+use crate::config;
+use crate::i18n;
+#[cfg(target_os = "linux")]
+use crate::init::InitSystem;
+#[cfg(target_os = "linux")]
+use crate::integrity;
+use crate::policy::{self, HostRole};
+use crate::schema::Status;
+#[cfg(target_os = "linux")]
 use crate::util;
+#[cfg(target_os = "linux")]
+use crate::parse;
+#[cfg(target_os = "linux")]
+use crate::patterns;
+#[cfg(target_os = "linux")]
+use crate::policy::{HistoryMode, RtcMode};
+#[cfg(target_os = "macos")]
+use crate::macos;
 
 enum Mark {
     OK,
     ERR,
+    PARTIAL,
 }
 
 impl Mark {
-    fn as_str(&self) -> &str {
+    fn as_str(&self) -> &'static str {
         match self {
             Mark::OK => {
                 "✓"
@@ -22,6 +53,9 @@ impl Mark {
             Mark::ERR => {
                 "✗"
             },
+            Mark::PARTIAL => {
+                "◐"
+            },
         }
     }
     fn from(v: bool) -> Self {
@@ -44,20 +78,91 @@ pub enum GuardItem {
     IPTables,
     Service,
     CommandHistory,
+    ConsoleAccess,
+    HomeDir,
+    DevTools,
+    ListenAddr,
+    TmpMounts,
+    CloudMetadata,
+    SshCredential,
+    KernelModules,
+    X11,
+    KernelParams,
+    TimeLocale,
+    PamStack,
+    SudoLog,
+    PendingReboot,
+    ResourceLimits,
+    SshHostKey,
+    PathEnv,
+    Integrity,
+}
+
+/// 检查项分类, 用于界面分组及按分类设置退出码
+#[derive(Clone, Copy, PartialEq, ArgEnum)]
+pub enum Category {
+    Account,
+    Network,
+    Audit,
+    System,
+}
+
+impl Category {
+    pub fn all() -> Vec<Category> {
+        vec![Category::Account, Category::Network, Category::Audit, Category::System]
+    }
+
+    pub fn title(&self) -> &str {
+        match self {
+            Category::Account => i18n::tr("category.account"),
+            Category::Network => i18n::tr("category.network"),
+            Category::Audit => i18n::tr("category.audit"),
+            Category::System => i18n::tr("category.system"),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GuardCell {
+    // 检查项 ID, 见 `GuardItem::id`
+    #[serde(default)]
+    pub id: String,
     pub mp: HashMap<String, String>,
+    // 各条安全要求的检查结果, 按要求 ID 记录, None 表示需要人工确认
+    pub reqs: Vec<(String, Option<bool>)>,
+    // 只能自动检查部分条件的要求 ID, 自动检查的部分符合时其余条件仍需人工核查
+    #[serde(default)]
+    pub partial: Vec<String>,
+    // 证据来源: 检查时读取的文件及执行的命令, 便于复核
+    #[serde(default)]
+    pub sources: Vec<String>,
 }
 
 impl GuardCell {
     pub fn new() -> Self {
         GuardCell {
+            id: "".to_string(),
             mp: HashMap::new(),
+            reqs: vec![],
+            partial: vec![],
+            sources: vec![],
         }
     }
 
+    /// 各条安全要求的状态, 自动检查的部分符合的要求为 `Status::Partial`
+    pub fn statuses(&self) -> Vec<(String, Status)> {
+        self.reqs.iter()
+            .map(|(id, passed)| {
+                let status = if *passed == Some(true) && self.partial.contains(id) {
+                    Status::Partial
+                } else {
+                    Status::from(*passed)
+                };
+                (id.to_string(), status)
+            })
+            .collect()
+    }
+
     pub fn add<S1, S2>(&mut self, pos: S1, val: S2) where S1: AsRef<str>, S2: AsRef<str> {
         self.mp.insert(pos.as_ref().to_string(), val.as_ref().to_string());
     }
@@ -69,23 +174,303 @@ impl GuardCell {
             "".to_string()
         }
     }
+
+    /// 按行号顺序取出某一列(A 标题, B 要求, C 备注)中所有非空的单元格内容
+    pub fn column(&self, col: char) -> Vec<String> {
+        let mut cells = self.mp.iter()
+            .filter(|(k, v)| k.starts_with(col) && v.len() > 0)
+            .filter_map(|(k, v)| k[1..].parse::<u32>().ok().map(|row| (row, v.to_string())))
+            .collect::<Vec<(u32, String)>>();
+        cells.sort();
+        cells.into_iter().map(|(_, v)| v).collect()
+    }
+}
+
+/// 安全要求 ID. 内置检查项以 `req!` 引用, 编译时确认 ID 在文本目录中; 由端口、内核模块等运行时数据
+/// 组成的 ID 及自行编译进来的检查项使用 `Req::from`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Req(Cow<'static, str>);
+
+impl Req {
+    /// 目录中不存在该 ID 时在编译期报错, 需在常量中调用, 见 `req!`
+    pub const fn known(id: &'static str) -> Req {
+        if !i18n::known(id) {
+            panic!("requirement ID is not in the i18n catalog");
+        }
+        Req(Cow::Borrowed(id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
-impl GuardItem {
-    pub fn check(&self) -> GuardCell {
+impl From<String> for Req {
+    fn from(id: String) -> Self {
+        Req(Cow::Owned(id))
+    }
+}
+
+impl From<&str> for Req {
+    fn from(id: &str) -> Self {
+        Req(Cow::Owned(id.to_string()))
+    }
+}
+
+/// 引用内置的安全要求, 例如 `req!("acct.umask")`, ID 拼写错误时无法编译
+macro_rules! req {
+    ($id:literal) => {{
+        const REQ: $crate::sysguard::Req = $crate::sysguard::Req::known($id);
+        REQ
+    }};
+}
+// 供 macOS 上的实现使用
+#[cfg(target_os = "macos")]
+pub(crate) use req;
+
+// 台账中的一行: B 列为各条要求(或采集到的信息), C 列为备注
+#[derive(Default)]
+struct RowCells {
+    lines: Vec<String>,
+    evidence: Vec<String>,
+}
+
+/// 检查项的结果, 由检查项构建并返回, 再按检查项在台账中的行号写入 `GuardCell`, 例如
+/// `ReportRow::new("acct").status(req!("acct.umask"), Some(true)).evidence("...")`
+pub struct ReportRow {
+    id: String,
+    reqs: Vec<(String, Option<bool>)>,
+    partial: Vec<String>,
+    // 第一个为标题所在行, 之后为 `next_row` 开始的各行
+    rows: Vec<RowCells>,
+}
+
+impl ReportRow {
+    pub fn new<S: Into<String>>(id: S) -> Self {
+        ReportRow {
+            id: id.into(),
+            reqs: vec![],
+            partial: vec![],
+            rows: vec![RowCells::default()],
+        }
+    }
+
+    fn current(&mut self) -> &mut RowCells {
+        self.rows.last_mut().unwrap()
+    }
+
+    /// 记录一条安全要求的检查结果, 显示为 `[✓]要求内容`, None 表示需要人工确认
+    pub fn status(mut self, req: Req, passed: Option<bool>) -> Self {
+        let mark = match passed {
+            Some(v) => Mark::from(v).as_str(),
+            None => "  ",
+        };
+        let line = format!("[{}]{}", mark, i18n::tr(req.as_str()));
+        self.current().lines.push(line);
+        self.reqs.push((req.as_str().to_string(), passed));
+        self
+    }
+
+    /// 记录一条只能自动检查部分条件的安全要求, 自动检查的部分符合时显示为 `[◐]要求内容`
+    pub fn partial(mut self, req: Req, passed: Option<bool>) -> Self {
+        self.partial.push(req.as_str().to_string());
+        if passed != Some(true) {
+            return self.status(req, passed);
+        }
+        let line = format!("[{}]{}", Mark::PARTIAL.as_str(), i18n::tr(req.as_str()));
+        self.current().lines.push(line);
+        self.reqs.push((req.as_str().to_string(), passed));
+        self
+    }
+
+    /// 没有安全要求的检查项(例如操作系统、设备 IP)采集到的信息, 与要求写在同一列
+    pub fn info<S: AsRef<str>>(mut self, text: S) -> Self {
+        self.current().lines.push(text.as_ref().to_string());
+        self
+    }
+
+    /// 备注中的一段证据或说明, 多次调用时按行拼接
+    pub fn evidence<S: AsRef<str>>(mut self, text: S) -> Self {
+        self.current().evidence.push(text.as_ref().to_string());
+        self
+    }
+
+    /// 之后的要求与备注写入台账的下一行, 用于占用多行的检查项
+    pub fn next_row(mut self) -> Self {
+        self.rows.push(RowCells::default());
+        self
+    }
+
+    /// 以标题所在的行号写入单元格: A 列为标题, 各行的 B 列为要求, C 列为备注
+    pub fn into_cell(self, row: u32, title: &str) -> GuardCell {
         let mut cell = GuardCell::new();
+        cell.id = self.id;
+        cell.add(format!("A{}", row), title);
+        for (i, cells) in self.rows.iter().enumerate() {
+            let n = row + i as u32;
+            if cells.lines.len() > 0 {
+                cell.add(format!("B{}", n), cells.lines.join("\n"));
+            }
+            if cells.evidence.len() > 0 {
+                cell.add(format!("C{}", n), cells.evidence.join("\n"));
+            }
+        }
+        cell.reqs = self.reqs;
+        cell.partial = self.partial;
+        cell
+    }
+}
+
+impl GuardItem {
+    /// 按报告中的顺序列出所有检查项
+    pub fn all() -> Vec<GuardItem> {
+        vec![
+            GuardItem::OS,
+            GuardItem::IP,
+            GuardItem::UserMgmt,
+            GuardItem::PasswdComplexity,
+            GuardItem::OperationTimeout,
+            GuardItem::Port,
+            GuardItem::Audit,
+            GuardItem::IPTables,
+            GuardItem::Service,
+            GuardItem::CommandHistory,
+            GuardItem::ConsoleAccess,
+            GuardItem::HomeDir,
+            GuardItem::DevTools,
+            GuardItem::ListenAddr,
+            GuardItem::TmpMounts,
+            GuardItem::CloudMetadata,
+            GuardItem::SshCredential,
+            GuardItem::KernelModules,
+            GuardItem::X11,
+            GuardItem::KernelParams,
+            GuardItem::TimeLocale,
+            GuardItem::PamStack,
+            GuardItem::SudoLog,
+            GuardItem::PendingReboot,
+            GuardItem::ResourceLimits,
+            GuardItem::SshHostKey,
+            GuardItem::PathEnv,
+            GuardItem::Integrity,
+        ]
+    }
+}
+
+impl Check for GuardItem {
+    fn id(&self) -> &str {
+        match self {
+            GuardItem::OS => "os",
+            GuardItem::IP => "net.ip",
+            GuardItem::UserMgmt => "acct",
+            GuardItem::PasswdComplexity => "passwd",
+            GuardItem::OperationTimeout => "session",
+            GuardItem::Port => "net.port",
+            GuardItem::Audit => "audit",
+            GuardItem::IPTables => "fw",
+            GuardItem::Service => "svc",
+            GuardItem::CommandHistory => "hist",
+            GuardItem::ConsoleAccess => "console",
+            GuardItem::HomeDir => "home",
+            GuardItem::DevTools => "devtools",
+            GuardItem::ListenAddr => "net.listen",
+            GuardItem::TmpMounts => "mount",
+            GuardItem::CloudMetadata => "cloud",
+            GuardItem::SshCredential => "ssh",
+            GuardItem::KernelModules => "kmod",
+            GuardItem::X11 => "x11",
+            GuardItem::KernelParams => "kernel",
+            GuardItem::TimeLocale => "time",
+            GuardItem::PamStack => "pam",
+            GuardItem::SudoLog => "sudo",
+            GuardItem::PendingReboot => "reboot",
+            GuardItem::ResourceLimits => "limits",
+            GuardItem::SshHostKey => "sshkey",
+            GuardItem::PathEnv => "path",
+            GuardItem::Integrity => "integrity",
+        }
+    }
+
+    fn row(&self) -> u32 {
+        match self {
+            GuardItem::OS => 4,
+            GuardItem::IP => 5,
+            GuardItem::UserMgmt => 8,
+            GuardItem::PasswdComplexity => 10,
+            GuardItem::OperationTimeout => 11,
+            GuardItem::Port => 14,
+            GuardItem::Service => 15,
+            GuardItem::Audit => 19,
+            GuardItem::IPTables => 21,
+            GuardItem::CommandHistory => 25,
+            GuardItem::ConsoleAccess => 26,
+            GuardItem::HomeDir => 27,
+            GuardItem::DevTools => 28,
+            GuardItem::ListenAddr => 29,
+            GuardItem::TmpMounts => 30,
+            GuardItem::CloudMetadata => 31,
+            GuardItem::SshCredential => 32,
+            GuardItem::KernelModules => 33,
+            GuardItem::X11 => 34,
+            GuardItem::KernelParams => 35,
+            GuardItem::TimeLocale => 36,
+            GuardItem::PamStack => 37,
+            GuardItem::SudoLog => 38,
+            GuardItem::PendingReboot => 39,
+            GuardItem::ResourceLimits => 40,
+            GuardItem::SshHostKey => 41,
+            GuardItem::PathEnv => 42,
+            GuardItem::Integrity => 43,
+        }
+    }
+
+    fn category(&self) -> Category {
+        match self {
+            GuardItem::UserMgmt | GuardItem::PasswdComplexity | GuardItem::OperationTimeout | GuardItem::ConsoleAccess
+                | GuardItem::HomeDir | GuardItem::SshCredential | GuardItem::PamStack => Category::Account,
+            GuardItem::IP | GuardItem::Port | GuardItem::Service | GuardItem::IPTables | GuardItem::ListenAddr
+                | GuardItem::CloudMetadata | GuardItem::X11 | GuardItem::SshHostKey => Category::Network,
+            GuardItem::Audit | GuardItem::CommandHistory | GuardItem::SudoLog => Category::Audit,
+            GuardItem::OS | GuardItem::DevTools | GuardItem::TmpMounts | GuardItem::KernelModules
+                | GuardItem::KernelParams | GuardItem::TimeLocale | GuardItem::PendingReboot
+                | GuardItem::ResourceLimits | GuardItem::PathEnv | GuardItem::Integrity => Category::System,
+        }
+    }
+
+    fn live_only(&self) -> bool {
+        match self {
+            GuardItem::IP | GuardItem::Port | GuardItem::Service | GuardItem::Audit | GuardItem::ListenAddr
+                | GuardItem::CloudMetadata | GuardItem::SshCredential | GuardItem::X11 | GuardItem::KernelParams
+                | GuardItem::SudoLog | GuardItem::PendingReboot | GuardItem::Integrity => true,
+            _ => false,
+        }
+    }
+
+    fn run(&self) -> ReportRow {
+        let row = ReportRow::new(self.id());
         match self {
+            #[cfg(target_os = "linux")]
             GuardItem::OS => {
-                cell.add("A4", "操作系统");
-                if let Ok(r) = util::runcmd("cat /etc/issue", None) {
-                    cell.add("B4", r.trim().replace("\r", " ").replace("\n", " "));
+                // 系统标识取自 os-release; /etc/issue 是登录前显示的提示信息, 常被改为警示语, 原样列在备注中
+                let mut identity = String::new();
+                if let Ok(lines) = parse::read_lines("/etc/os-release") {
+                    for line in lines {
+                        if let Some(v) = line.strip_prefix("PRETTY_NAME=") {
+                            identity = v.trim().trim_matches('"').to_string();
+                        }
+                    }
                 } else {
-                    println!("cannot read /etc/issue");
-                    cell.add("B4", "");
+                    eprintln!("cannot read /etc/os-release");
+                }
+                let row = row.info(&identity);
+                if let Ok(lines) = parse::read_lines("/etc/issue") {
+                    row.evidence(lines.collect::<Vec<String>>().join("\n").trim())
+                } else {
+                    eprintln!("cannot read /etc/issue");
+                    row
                 }
             },
             GuardItem::IP => {
-                cell.add("A5", "设备 IP");
                 let mut iplist = vec![];
                 for iface in datalink::interfaces() {
                     let ips = iface.ips.iter().filter(|x| x.is_ipv4())
@@ -96,55 +481,169 @@ impl GuardItem {
                         iplist.extend(ips);
                     }
                 }
-                cell.add("B5", &iplist.join(";"));
+                row.info(&iplist.join(";"))
             },
+            #[cfg(target_os = "linux")]
             GuardItem::UserMgmt => {
-                cell.add("A8", "用户管理");
+                // umask 依次来自 pam_umask(未指定 umask= 时取 login.defs 的 UMASK)、登录 shell 读取的
+                // /etc/profile 及 /etc/profile.d/*.sh、交互式 shell 读取的 bashrc, 后设置的生效;
+                // 非登录 shell(例如图形桌面的终端)不读取 profile, su 不带 "-" 时只经过 PAM 与 bashrc
+                let umasks = |file: &str| -> Vec<(u32, String)> {
+                    let lines = match parse::read_lines(file) {
+                        Ok(lines) => lines,
+                        Err(_) => return vec![],
+                    };
+                    lines.filter(|x| !x.trim().starts_with("#"))
+                        .flat_map(|x| x.split(";").map(|x| x.trim().to_string()).collect::<Vec<String>>())
+                        .filter_map(|x| patterns::RE_UMASK.captures(&x).and_then(|x| u32::from_str_radix(&x[1], 8).ok()))
+                        .map(|x| (x, file.to_string()))
+                        .collect()
+                };
 
-                // umask 是 shell builtin 命令, 因此不能直接通过 Command 模块运行, 解决方法来自
-                // https://stackoverflow.com/questions/32146111/run-shell-builtin-command-in-python
-                let mark = if let Ok(r) = util::runcmd("bash -i -c 'umask'", None) {
-                    if r.trim() == "0022" {
-                        Mark::from(true)
-                    } else {
-                        println!("[x] cannot run command 'umask'");
-                        Mark::from(false)
+                let mut defs_umask = None;
+                if let Ok(lines) = parse::read_lines("/etc/login.defs") {
+                    for line in lines {
+                        let items = line.split_whitespace().collect::<Vec<&str>>();
+                        if items.len() == 2 && items[0] == "UMASK" {
+                            defs_umask = u32::from_str_radix(items[1], 8).ok();
+                        }
                     }
-                } else {
-                    Mark::from(false)
+                }
+                let pam_umask = |services: Vec<&str>| -> Vec<(u32, String)> {
+                    for service in services {
+                        let file = format!("/etc/pam.d/{}", service);
+                        let rules = match parse::read_lines(&file) {
+                            Ok(lines) => parse::pam_rules(lines),
+                            Err(_) => continue,
+                        };
+                        if let Some(rule) = rules.iter().find(|x| x.typ == "session" && x.module == "pam_umask.so") {
+                            let value = rule.args.iter()
+                                .filter_map(|x| x.strip_prefix("umask="))
+                                .filter_map(|x| u32::from_str_radix(x, 8).ok())
+                                .next()
+                                .or(defs_umask);
+                            return value.map(|x| vec![(x, format!("pam_umask({})", file))]).unwrap_or_default();
+                        }
+                    }
+                    vec![]
                 };
-                cell.add("B8", &formatdoc!(r#"
-                        [  ]应删除或锁定过期帐户、无用帐户和隐藏账号
-                        [{}]每个用户是否按要求开展权限设置
-                    "#,  mark.as_str()),
-                );
 
-                let users = if let Ok(r) = util::runcmd("cat /etc/passwd", None) {
-                    let lines = r.trim().lines()
-                        .filter(|x| !x.trim().ends_with("/nologin") && !x.trim().ends_with("/false") && !x.trim().starts_with("#"))
-                        .collect::<Vec<&str>>();
-                    lines.join("\n")
+                let mut profile = umasks("/etc/profile");
+                if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/profile.d")) {
+                    let mut scripts = entries.filter_map(|x| x.ok())
+                        .map(|x| x.path().to_string_lossy().to_string())
+                        .filter(|x| x.ends_with(".sh"))
+                        .collect::<Vec<String>>();
+                    scripts.sort();
+                    for script in scripts {
+                        profile.extend(umasks(&script));
+                    }
+                }
+                let mut bashrc = umasks("/etc/bashrc");
+                bashrc.extend(umasks("/etc/bash.bashrc"));
+
+                let pam_login = pam_umask(vec!["common-session", "login", "sshd", "system-auth", "password-auth"]);
+                let mut login = pam_login.clone();
+                login.extend(profile);
+                login.extend(bashrc.clone());
+                let mut nonlogin = pam_login;
+                nonlogin.extend(bashrc.clone());
+                let mut su = pam_umask(vec!["common-session", "su", "system-auth"]);
+                su.extend(bashrc);
+
+                // 同一文件中可能按用户分支设置多个值(例如 RHEL 的 /etc/profile), 以最后一个为准;
+                // 至少应去掉同组与其他用户的写权限(022)
+                let show = |x: u32| format!("{:04o}", x);
+                let mut umask_passed = true;
+                let mut desc = vec![format!("login.defs UMASK={}", defs_umask.map(show).unwrap_or("未设置".to_string()))];
+                for (name, candidates) in vec![("登录会话", &login), ("非登录 shell", &nonlogin), ("su", &su)] {
+                    let effective = candidates.last().map(|x| x.0).unwrap_or(0o022);
+                    umask_passed = umask_passed && effective & 0o022 == 0o022;
+                    let sources = candidates.iter().map(|(v, src)| format!("{} {}", show(*v), src)).collect::<Vec<String>>();
+                    desc.push(format!("{}: 生效 {}{}", name, show(effective), if sources.len() > 0 {
+                        format!(" ({})", sources.join(" → "))
+                    } else {
+                        " (未设置, 默认值)".to_string()
+                    }));
+                }
+                let all = login.iter().chain(su.iter()).collect::<Vec<&(u32, String)>>();
+                if let Some(strictest) = all.iter().max_by_key(|x| x.0.count_ones()) {
+                    desc.push(format!("最严格: {} ({})", show(strictest.0), strictest.1));
+                }
+                if let Some(weakest) = all.iter().min_by_key(|x| x.0.count_ones()) {
+                    desc.push(format!("最宽松: {} ({})", show(weakest.0), weakest.1));
+                }
+
+                // 只能自动检查 UID 为 0 的非 root 帐户(隐藏账号), 过期及无用帐户需人工核对
+                let hidden = if let Ok(lines) = parse::read_lines("/etc/passwd") {
+                    Some(lines.filter_map(|x| {
+                        let items = x.trim().split(":").collect::<Vec<&str>>();
+                        if items.len() == 7 && items[2] == "0" && items[0] != "root" {
+                            Some(items[0].to_string())
+                        } else {
+                            None
+                        }
+                    }).collect::<Vec<String>>())
                 } else {
-                    println!("cannot read /etc/passwd");
-                    "".to_string()
+                    eprintln!("cannot read /etc/passwd");
+                    None
                 };
-                cell.add("C9", &users);
+                if let Some(hidden) = hidden.as_ref().filter(|x| x.len() > 0) {
+                    desc.push(format!("UID 为 0 的非 root 帐户：{}", hidden.join("、")));
+                }
+                let row = row
+                    .partial(req!("acct.expired"), hidden.map(|x| x.len() == 0))
+                    .status(req!("acct.umask"), Some(umask_passed))
+                    .evidence(&desc.join("\n"));
 
-                let mark = if let Ok(r) = util::runcmd("cat /etc/passwd", None) {
-                    if let Some(_) = r.trim().lines().filter(|x| x.trim().starts_with("root")).nth(0) {
-                        Mark::from(false)
-                    } else {
-                        Mark::from(true)
+                // 除 root 外, UID 小于 1000 的系统账户不应拥有可登录的 shell
+                let mut shell_accounts = vec![];
+                let users = if let Ok(lines) = parse::read_lines("/etc/passwd") {
+                    let lines = lines
+                        .filter(|x| x.trim().len() > 0 && !x.trim().ends_with("/nologin") && !x.trim().ends_with("/false") && !x.trim().starts_with("#"))
+                        .collect::<Vec<String>>();
+                    for line in lines.iter() {
+                        // 格式为 name:password:uid:gid:gecos:home:shell
+                        let items = line.trim().split(":").collect::<Vec<&str>>();
+                        if items.len() != 7 {
+                            continue;
+                        }
+                        // sync、shutdown、halt 为系统自带的特殊账户, 其 shell 为对应的命令
+                        if vec!["sync", "shutdown", "halt"].contains(&items[0]) {
+                            continue;
+                        }
+                        if let Ok(uid) = items[2].parse::<u32>() {
+                            if uid != 0 && uid < 1000 {
+                                shell_accounts.push(format!("{}({})", items[0], items[6]));
+                            }
+                        }
                     }
+                    Some(lines.join("\n"))
                 } else {
-                    println!("cannot read /etc/passwd");
-                    Mark::from(false)
+                    eprintln!("cannot read /etc/passwd");
+                    None
                 };
-                cell.add("B9", &formatdoc!("[{}]不能使用默认用户名，例如：root、superadmin、administrator等", mark.as_str()));
-
+                let system_shell_passed = users.is_some() && shell_accounts.len() == 0;
+                let mut desc = vec![];
+                if shell_accounts.len() > 0 {
+                    desc.push(format!("可登录的系统账户：{}", shell_accounts.join("、")));
+                }
+                if let Some(users) = users {
+                    desc.push(users);
+                }
+                let default_name_passed = if let Ok(mut lines) = parse::read_lines("/etc/passwd") {
+                    !lines.any(|x| x.trim().starts_with("root"))
+                } else {
+                    eprintln!("cannot read /etc/passwd");
+                    false
+                };
+                row.next_row()
+                    .status(req!("acct.default_name"), Some(default_name_passed))
+                    .status(req!("acct.system_shell"), Some(system_shell_passed))
+                    .evidence(&desc.join("\n"))
             },
+            #[cfg(target_os = "linux")]
             GuardItem::PasswdComplexity => {
-                cell.add("A10", "密码复杂度配置");
 
                 #[derive(Debug, Serialize, Deserialize)]
                 struct Passwd {
@@ -164,8 +663,14 @@ impl GuardItem {
                 }
 
                 let mut passwd = Passwd::default();
+                // 实际读取到的配置值, 写入备注列供测评人员核对
+                let mut min_len = None;
+                let mut max_days = None;
+                let mut min_days = None;
+                let mut warn_age = None;
+                let mut evidence = vec![];
 
-                if let Ok(r) = util::runcmd("cat /etc/login.defs", None) {
+                if let Ok(lines) = parse::read_lines("/etc/login.defs") {
                     let get_value = |line: &str| -> Option<u32> {
                         if let Some(v) = line.split("\t").filter(|x| x.trim().len() > 0).nth(1) {
                             if let Ok(v) = v.parse::<u32>() {
@@ -174,33 +679,48 @@ impl GuardItem {
                         };
                         return None;
                     };
-                    for line in r.trim().lines() {
+                    for line in lines {
                         if line.starts_with("PASS_MIN_LEN") {
-                            if let Some(v) = get_value(line) {
+                            if let Some(v) = get_value(&line) {
                                 passwd.minimum_size = v;
+                                min_len = Some(v);
                             }
                         }
 
                         if line.starts_with("PASS_MAX_DAYS") {
-                            if let Some(v) = get_value(line) {
+                            if let Some(v) = get_value(&line) {
                                 passwd.update_cycle = v;
+                                max_days = Some(v);
                             }
                         }
+
+                        if line.starts_with("PASS_MIN_DAYS") {
+                            min_days = get_value(&line);
+                        }
+
+                        if line.starts_with("PASS_WARN_AGE") {
+                            warn_age = get_value(&line);
+                        }
                     }
                 } else {
-                    println!("cannot read /etc/login.defs");
+                    eprintln!("cannot read /etc/login.defs");
+                }
+                for (name, value) in vec![("PASS_MIN_LEN", min_len), ("PASS_MAX_DAYS", max_days), ("PASS_MIN_DAYS", min_days), ("PASS_WARN_AGE", warn_age)] {
+                    evidence.push(match value {
+                        Some(v) => format!("{}={}", name, v),
+                        None => format!("{} 未设置", name),
+                    });
                 }
 
-                if let Ok(r) = util::runcmd("cat /etc/pam.d/system-auth", None) {
+                if let Ok(lines) = parse::read_lines("/etc/pam.d/system-auth") {
                     let mut credits = HashMap::new();
 
-                    let credit_lines = r.trim().lines().filter(|x|
+                    let credit_lines = lines.filter(|x|
                         x.trim().starts_with("password requisite pam_cracklib")
-                    ).collect::<Vec<&str>>();
+                    ).collect::<Vec<String>>();
 
                     if let Some(credit_line) = credit_lines.get(0) {
-                        let re = Regex::new(r"([dulo]credit\s*=\s*-\d+)").unwrap();
-                        for cap in re.captures_iter(credit_line) {
+                        for cap in patterns::RE_PAM_CREDIT.captures_iter(credit_line) {
                             let kv = &cap[1].split("=").collect::<Vec<&str>>();
                             let (name, value) = (kv.get(0), kv.get(1));
                             if let Some(name) = name {
@@ -228,111 +748,216 @@ impl GuardItem {
                     if cond("ucredit") <= -2 && cond("lcredit") <= -1 && cond("dcredit") <= -4 && cond("ocredit") <= -1 {
                         passwd.is_strong_combination = true;
                     }
+                    if credit_lines.len() > 0 {
+                        let values = vec!["ucredit", "lcredit", "dcredit", "ocredit"].into_iter()
+                            .map(|k| match credits.get(k) {
+                                Some(v) => format!("{}={}", k, v),
+                                None => format!("{} 未设置", k),
+                            })
+                            .collect::<Vec<String>>();
+                        evidence.push(format!("pam_cracklib: {}", values.join(" ")));
+                    } else {
+                        evidence.push("/etc/pam.d/system-auth 未配置 pam_cracklib".to_string());
+                    }
                 } else {
-                    println!("cannot read /etc/pam.d/system-auth");
-                };
-
-                cell.add("B10", &formatdoc!("
-                        [{}]密码长度不小于8位
-                        [{}]采取字母、数字和特殊字符的混合组合
-                        [  ]密码与用户名不相同
-                        [{}]密码更新周期180天
-                    ",
-                    Mark::from(passwd.minimum_size >= 8).as_str(),
-                    Mark::from(passwd.is_strong_combination).as_str(),
-                    Mark::from(passwd.update_cycle <= 180).as_str(),
-                ));
+                    eprintln!("cannot read /etc/pam.d/system-auth");
+                };
+
+                // pam_cracklib 需显式配置 reject_username, pam_pwquality 默认开启 usercheck, 除非配置为 0;
+                // 两个模块都没有使用时无法自动判断, 保留为人工确认
+                let mut username_check = None;
+                for pam_file in vec!["/etc/pam.d/system-auth", "/etc/pam.d/common-password"] {
+                    let lines = match parse::read_lines(pam_file) {
+                        Ok(lines) => lines,
+                        Err(_) => continue,
+                    };
+                    for line in lines {
+                        let line = line.trim();
+                        if !line.starts_with("password") {
+                            continue;
+                        }
+                        let options = line.split_whitespace().collect::<Vec<&str>>();
+                        if options.iter().any(|x| x.starts_with("pam_cracklib.so")) {
+                            let enabled = options.contains(&"reject_username");
+                            username_check = Some(username_check.unwrap_or(false) || enabled);
+                        }
+                        if options.iter().any(|x| x.starts_with("pam_pwquality.so")) {
+                            let mut enabled = !options.contains(&"usercheck=0");
+                            if let Ok(mut conf) = parse::read_lines("/etc/security/pwquality.conf") {
+                                if conf.any(|x| patterns::RE_USERCHECK_OFF.is_match(x.trim())) && !options.iter().any(|x| x.starts_with("usercheck=") && *x != "usercheck=0") {
+                                    enabled = false;
+                                }
+                            }
+                            username_check = Some(username_check.unwrap_or(false) || enabled);
+                        }
+                    }
+                }
+
+                row.status(req!("passwd.min_len"), Some(passwd.minimum_size >= policy::get().passwd.min_len))
+                    .status(req!("passwd.combination"), Some(passwd.is_strong_combination))
+                    .status(req!("passwd.username"), username_check)
+                    .status(req!("passwd.max_days"), Some(passwd.update_cycle <= policy::get().passwd.max_days))
+                    // 未设置时 PASS_MIN_DAYS 默认为 0, PASS_WARN_AGE 默认为 7
+                    .status(req!("passwd.min_days"), Some(min_days.unwrap_or(0) >= policy::get().passwd.min_days))
+                    .status(req!("passwd.warn_age"), Some(warn_age.unwrap_or(7) >= policy::get().passwd.warn_age))
+                    .evidence(&evidence.join("\n"))
             },
+            #[cfg(target_os = "linux")]
             GuardItem::OperationTimeout => {
-                cell.add("A11", "登录终端的操作超时锁定");
+                // 登录 shell 依次读取 /etc/profile 及其引用的 /etc/profile.d/*.sh, 交互式 shell 再读取 bashrc;
+                // 后面的赋值覆盖前面的, 但 TMOUT 设为只读后再赋值无效
+                let mut files = vec!["/etc/profile".to_string()];
+                if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/profile.d")) {
+                    let mut scripts = entries.filter_map(|x| x.ok())
+                        .map(|x| x.path().to_string_lossy().to_string())
+                        .filter(|x| x.ends_with(".sh"))
+                        .collect::<Vec<String>>();
+                    scripts.sort();
+                    files.extend(scripts);
+                }
+                files.push("/etc/bashrc".to_string());
+                files.push("/etc/bash.bashrc".to_string());
 
-                let mut tmout = None;
-                if let Ok(r) = util::runcmd("cat /etc/profile", None) {
-                    let re = Regex::new(r"TMOUT=(\d+)").unwrap();
-                    for line in r.lines().rev() {
+                // 生效的超时时间及所在文件
+                let mut tmout: Option<(u32, String)> = None;
+                let mut readonly: Option<String> = None;
+                let mut desc = vec![];
+                for file in files.iter() {
+                    let lines = match parse::read_lines(file) {
+                        Ok(lines) => lines,
+                        Err(_) => continue,
+                    };
+                    for line in lines {
                         let line = line.trim();
-                        if let Some(mat) = re.find(line) {
-                            if let Some(v) = line[mat.start()..mat.end()].split("=").nth(1) {
-                                tmout = Some(v.to_string())
+                        if line.starts_with("#") {
+                            continue;
+                        }
+                        for stmt in line.split(";").map(|x| x.trim()) {
+                            if let Some(v) = patterns::RE_TMOUT.captures(stmt).and_then(|x| x[1].parse::<u32>().ok()) {
+                                if let Some(src) = readonly.as_ref() {
+                                    desc.push(format!("{}: TMOUT={} 无效, {} 中已设为只读", file, v, src));
+                                } else {
+                                    if let Some((old, src)) = tmout.as_ref() {
+                                        if *old != v {
+                                            desc.push(format!("{}: TMOUT={} 覆盖了 {} 中的 TMOUT={}", file, v, src, old));
+                                        }
+                                    }
+                                    tmout = Some((v, file.to_string()));
+                                }
+                            }
+                            if readonly.is_none() && patterns::RE_TMOUT_READONLY.is_match(stmt) {
+                                readonly = Some(file.to_string());
                             }
                         }
                     }
-                } else {
-                    println!("cannot read /etc/profile");
                 }
 
-                let mut mark = Mark::ERR;
-                if let Some(tmout) = tmout {
-                    if let Ok(v) = tmout.parse::<i32>() {
-                        // 默认超时的单位是为秒, 要求超时时间小于等于 10 分钟
-                        if v <= 600 {
-                            mark = Mark::OK;
+                // sshd 以配置文件中第一次出现的值为准, ClientAliveCountMax 默认为 3, 为 0 时不断开空闲连接
+                let mut interval = None;
+                let mut count_max = None;
+                if let Ok(lines) = parse::read_lines("/etc/ssh/sshd_config") {
+                    for line in lines {
+                        let items = line.split_whitespace().collect::<Vec<&str>>();
+                        if items.len() != 2 {
+                            continue;
+                        }
+                        if items[0].eq_ignore_ascii_case("ClientAliveInterval") && interval.is_none() {
+                            interval = items[1].parse::<u32>().ok();
+                        }
+                        if items[0].eq_ignore_ascii_case("ClientAliveCountMax") && count_max.is_none() {
+                            count_max = items[1].parse::<u32>().ok();
                         }
                     }
+                } else {
+                    eprintln!("cannot read /etc/ssh/sshd_config");
                 }
+                let interval = interval.unwrap_or(0);
+                let count_max = count_max.unwrap_or(3);
 
-                cell.add("B11", &format!("[{}]设置操作超时为小于或等于10分钟", mark.as_str()));
+                // 超时的单位为秒, 默认要求小于等于 10 分钟
+                let limit = policy::get().session.tmout;
+                let tmout_passed = tmout.as_ref().map_or(false, |(v, _)| *v > 0 && *v <= limit);
+                let ssh_passed = interval > 0 && count_max > 0 && interval * count_max <= limit;
+                desc.insert(0, match tmout.as_ref() {
+                    Some((v, src)) => format!("TMOUT={} ({})", v, src),
+                    None => "未设置 TMOUT".to_string(),
+                });
+                desc.insert(1, match readonly.as_ref() {
+                    Some(src) => format!("TMOUT 只读 ({})", src),
+                    None => "TMOUT 未设为只读, 用户可自行取消".to_string(),
+                });
+                desc.push(format!("sshd: ClientAliveInterval={} ClientAliveCountMax={}", interval, count_max));
+                row.status(req!("session.tmout"), Some(tmout_passed))
+                    .status(req!("session.readonly"), Some(readonly.is_some()))
+                    .status(req!("session.ssh_idle"), Some(ssh_passed))
+                    .evidence(&desc.join("\n"))
             },
             GuardItem::Port => {
-                cell.add("A14", "高危端口封闭");
-
                 let tcp_port_list = vec![135, 137, 138, 139, 445, 3389];
-                let is_tcp_port_opened = |port: usize| -> bool {
-                    match TcpListener::bind(("127.0.0.1", port as u16)) {
-                        Ok(_) => true,
-                        Err(_) => false,
+                // 端口已被占用时无法在本地绑定; 非 root 用户绑定 1024 以下的端口会因权限不足失败, 不视为开放
+                let is_tcp_port_opened = |port: u16| -> bool {
+                    match TcpListener::bind(("127.0.0.1", port)) {
+                        Ok(_) => false,
+                        Err(e) => e.kind() == std::io::ErrorKind::AddrInUse,
                     }
                 };
-                let mut mp = HashMap::new();
+
+                // 以 ss 列出监听的端口及其所属进程, 无法执行时尝试绑定端口判断
+                #[cfg(target_os = "linux")]
+                let listeners = tcp_listeners();
+                #[cfg(not(target_os = "linux"))]
+                let listeners: Option<Vec<(u16, Vec<(String, u32)>)>> = None;
+
+                let mut row = row;
+                // 占用端口的进程只能在 Linux 上通过 ss 获取
+                #[cfg(target_os = "linux")]
+                let mut desc: Vec<String> = vec![];
                 for port in tcp_port_list {
-                    if is_tcp_port_opened(port) {
-                        mp.insert(port, true);
-                    }
-                }
-
-                cell.add("B14", &formatdoc!("
-                        [{}]关闭135
-                        [{}]关闭137
-                        [{}]关闭138
-                        [{}]关闭139
-                        [{}]关闭445
-                        [{}]关闭3389
-                    ",
-                    Mark::from(!mp.contains_key(&135)).as_str(),
-                    Mark::from(!mp.contains_key(&137)).as_str(),
-                    Mark::from(!mp.contains_key(&138)).as_str(),
-                    Mark::from(!mp.contains_key(&139)).as_str(),
-                    Mark::from(!mp.contains_key(&445)).as_str(),
-                    Mark::from(!mp.contains_key(&3389)).as_str(),
-                ));
-            },
-            GuardItem::Service => {
-                cell.add("A15", "关闭服务");
+                    let passed = match listeners.as_ref() {
+                        Some(listeners) => !listeners.iter().any(|x| x.0 == port),
+                        None => !is_tcp_port_opened(port),
+                    };
+                    row = row.status(Req::from(format!("net.port.{}", port)), Some(passed));
 
-                let parse = |line: &str| -> Option<(String, [bool; 7])> {
-                    let items = line.split("\t").filter(|x| x.trim().len() > 0).collect::<Vec<&str>>();
-                    if items.len() != 8 {
-                        return None;
-                    }
-                    let name = items[0].to_string();
-                    // 是否打开的状态, 为 true 表示打开
-                    let mut switches: [bool; 7] = [true; 7];
-                    for (idx, item) in items[1..].iter().enumerate() {
-                        if let Some(status) = item.split(":").nth(1) {
-                            if status == "关闭" {
-                                switches[idx] = false;
-                            } else {
-                                switches[idx] = true;
+                    // 列出占用端口的进程, 便于直接定位需要停止的服务
+                    #[cfg(target_os = "linux")]
+                    if let Some(listeners) = listeners.as_ref().filter(|_| !passed) {
+                        let mut owners = vec![];
+                        for (name, pid) in listeners.iter().filter(|x| x.0 == port).flat_map(|x| x.1.iter()) {
+                            let (user, started) = match util::runcmd(&format!("ps -o user=,lstart= -p {}", pid), None) {
+                                Ok(r) => match r.trim().split_once(" ") {
+                                    Some((user, started)) => (user.to_string(), started.trim().to_string()),
+                                    None => (r.trim().to_string(), "未知".to_string()),
+                                },
+                                Err(_) => ("未知".to_string(), "未知".to_string()),
+                            };
+                            let unit = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()
+                                .and_then(|x| parse::systemd_unit(&x))
+                                .unwrap_or("无".to_string());
+                            let owner = format!("{}(PID {}, 用户 {}, 启动于 {}, 单元 {})", name, pid, user, started, unit);
+                            if !owners.contains(&owner) {
+                                owners.push(owner);
                             }
                         }
+                        if owners.len() == 0 {
+                            owners.push("未能获取所属进程, 需以 root 运行".to_string());
+                        }
+                        desc.push(format!("{}: {}", port, owners.join("、")));
                     }
-                    return Some((name, switches));
-                };
+                }
+                #[cfg(target_os = "linux")]
+                if desc.len() > 0 {
+                    row = row.evidence(&desc.join("\n"));
+                }
+                row
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::Service => {
                 let service_name_main_list = vec![
                     // email 服务
                     "sendmail", "postfix",
                     // ftp 服务
-                    "ftp", "vsftpd",
+                    "ftp", "vsftpd", "proftpd",
                     "telnet",
                     "rlogin",
                     "netbios",
@@ -357,25 +982,56 @@ impl GuardItem {
                     "smtp",
                 ];
 
+                let init = InitSystem::detect();
                 let mut mp = HashMap::<String, bool>::new();
-                if let Ok(r) = util::runcmd("chkconfig --list", None) {
-                    for line in r.lines() {
-                        if let Some((name, switches)) = parse(line) {
-                            let name = name.as_str();
-
-                            // 更新实际的服务状态
-                            let is_service_enabeld = switches[2] && switches[3] && switches[4] && switches[5];
-                            if service_name_main_list.contains(&name) && is_service_enabeld {
+                match init.manager().list_enabled_services() {
+                    Ok(services) => {
+                        for name in services.iter().map(|x| x.as_str()) {
+                            if service_name_main_list.contains(&name) {
                                 mp.insert(name.to_string(), true);
                             }
-                            if service_name_extra_list.contains(&name) && is_service_enabeld {
+                            if service_name_extra_list.contains(&name) {
                                 mp.insert("minimum_service".to_string(), true);
                                 mp.insert(name.to_string(), true);
                             }
                         }
-                    }
-                } else {
-                    println!("cannot run 'chkconfig --list'");
+                    },
+                    Err(e) => eprintln!("{}", e),
+                }
+
+                // 由 inetd/xinetd、systemd socket 启动或临时运行的 FTP、telnet 及 r 系列服务不在已启用的服务中,
+                // 按实际监听的端口核对: (端口, 服务, 已启用时视为一致的服务名, 记录到 mp 中的服务名)
+                let legacy_ports = vec![
+                    (21, "FTP", vec!["ftp", "vsftpd", "proftpd"], "ftp"),
+                    (23, "telnet", vec!["telnet"], "telnet"),
+                    (512, "rexec", vec!["rlogin", "rexec"], "rlogin"),
+                    (513, "rlogin", vec!["rlogin"], "rlogin"),
+                    (514, "rsh", vec!["rlogin", "rsh"], "rlogin"),
+                ];
+                let mut port_desc = vec![];
+                match tcp_listeners() {
+                    Some(listeners) => {
+                        for (port, name, services, key) in legacy_ports {
+                            if !listeners.iter().any(|x| x.0 == port) {
+                                continue;
+                            }
+                            let processes = listeners.iter().filter(|x| x.0 == port).flat_map(|x| x.1.iter()).collect::<Vec<&(String, u32)>>();
+                            if !services.iter().any(|x| mp.contains_key(*x)) {
+                                let owner = match processes.len() {
+                                    0 => "未能获取所属进程, 需以 root 运行".to_string(),
+                                    _ => processes.iter().map(|x| format!("{}(PID {})", x.0, x.1)).collect::<Vec<String>>().join("、"),
+                                };
+                                port_desc.push(format!("{}服务未启用, 但端口 {} 正在监听：{}", name, port, owner));
+                            }
+                            // 按监听进程确定 FTP 服务程序, 以便检查其配置
+                            let key = match processes.iter().find(|x| x.0 == "vsftpd" || x.0 == "proftpd") {
+                                Some(process) if port == 21 => process.0.as_str(),
+                                _ => key,
+                            };
+                            mp.insert(key.to_string(), true);
+                        }
+                    },
+                    None => port_desc.push("无法执行 ss, 未核对 21、23、512-514 端口的监听情况".to_string()),
                 }
 
                 let mut extra_open_service_list = vec![];
@@ -390,39 +1046,227 @@ impl GuardItem {
                     "".to_string()
                 };
 
-                cell.add("B15", &formatdoc!("
-                        [{}]E-Mail
-                        [{}]FTP
-                        [{}]telnet
-                        [{}]rlogin
-                        [{}]NetBIOS
-                        [{}]DHCP
-                        [{}]SMB
-                        [{}]SNMPV3以下版本
-                        [{}]远程桌面
-                        [{}]最小服务原则关闭其他非必要服务
-                    ",
-                    Mark::from(!(mp.contains_key("sendmail") || mp.contains_key("postfix"))).as_str(),
-                    Mark::from(!(mp.contains_key("ftp") || mp.contains_key("vsftpd"))).as_str(),
-                    Mark::from(!mp.contains_key("telnet")).as_str(),
-                    Mark::from(!mp.contains_key("rlogin")).as_str(),
-                    Mark::from(!mp.contains_key("netbios")).as_str(),
-                    Mark::from(!mp.contains_key("dhcpd")).as_str(),
-                    Mark::from(!(mp.contains_key("smb") || mp.contains_key("samba"))).as_str(),
-                    Mark::from(!mp.contains_key("snmpd")).as_str(),
-                    Mark::from(!(mp.contains_key("xdmcp") || mp.contains_key("vncserver"))).as_str(),
-                    Mark::from(!mp.contains_key("minimum_service")).as_str(),
-                ));
-
-                cell.add("C15", &extra_open_service_list_desc);
+                // snmpd 往往无法直接关闭, 此时要求只使用 v3 且认证加密(authPriv), 不存在 v1/v2c 团体名
+                let mut snmp_desc = vec![];
+                let mut snmp_passed = !mp.contains_key("snmpd");
+                if mp.contains_key("snmpd") {
+                    if let Ok(lines) = parse::read_lines("/etc/snmp/snmpd.conf") {
+                        let (mut v1v2c_used, mut v3_authpriv) = (false, false);
+                        let mut default_communities = vec![];
+                        for line in lines {
+                            let line = line.trim();
+                            if line.starts_with("#") {
+                                continue;
+                            }
+                            let items = line.split_whitespace().collect::<Vec<&str>>();
+                            let community = match items.get(0) {
+                                Some(&"rocommunity") | Some(&"rwcommunity") | Some(&"rocommunity6") | Some(&"rwcommunity6") => {
+                                    v1v2c_used = true;
+                                    items.get(1)
+                                },
+                                Some(&"com2sec") | Some(&"com2sec6") => {
+                                    v1v2c_used = true;
+                                    items.get(3)
+                                },
+                                Some(&"group") => {
+                                    if let Some(&model) = items.get(2) {
+                                        if model == "v1" || model == "v2c" {
+                                            v1v2c_used = true;
+                                        }
+                                    }
+                                    None
+                                },
+                                Some(&"rouser") | Some(&"rwuser") => {
+                                    if items.get(2) == Some(&"priv") {
+                                        v3_authpriv = true;
+                                    }
+                                    None
+                                },
+                                Some(&"access") => {
+                                    if items.get(3) == Some(&"usm") && items.get(4) == Some(&"priv") {
+                                        v3_authpriv = true;
+                                    }
+                                    None
+                                },
+                                _ => None,
+                            };
+                            if let Some(&community) = community {
+                                if community == "public" || community == "private" {
+                                    default_communities.push(community.to_string());
+                                }
+                            }
+                        }
+                        if default_communities.len() > 0 {
+                            default_communities.sort();
+                            default_communities.dedup();
+                            snmp_desc.push(format!("SNMP使用默认团体名：{}", default_communities.join("、")));
+                        }
+                        if v1v2c_used {
+                            snmp_desc.push("SNMP启用了v1/v2c".to_string());
+                        }
+                        if !v3_authpriv {
+                            snmp_desc.push("SNMP未配置v3认证加密(authPriv)".to_string());
+                        }
+                        snmp_passed = default_communities.len() == 0 && !v1v2c_used && v3_authpriv;
+                    } else {
+                        eprintln!("cannot read /etc/snmp/snmpd.conf");
+                    }
+                }
+
+                // FTP 无法关闭时, 要求禁止匿名登录、将用户限制在主目录、启用 SSL 并配置登录提示
+                let mut ftp_desc = vec![];
+                let ftp_enabled = mp.contains_key("ftp") || mp.contains_key("vsftpd") || mp.contains_key("proftpd");
+                if mp.contains_key("ftp") || mp.contains_key("vsftpd") {
+                    let conf = parse::read_lines("/etc/vsftpd/vsftpd.conf")
+                        .or_else(|_| parse::read_lines("/etc/vsftpd.conf"));
+                    if let Ok(lines) = conf {
+                        let mut options = HashMap::new();
+                        for line in lines {
+                            let line = line.trim();
+                            if line.starts_with("#") {
+                                continue;
+                            }
+                            if let Some((k, v)) = line.split_once("=") {
+                                options.insert(k.trim().to_string(), v.trim().to_uppercase());
+                            }
+                        }
+                        let is_yes = |k: &str| options.get(k).map_or(false, |v| v == "YES");
+                        if !options.get("anonymous_enable").map_or(false, |v| v == "NO") {
+                            ftp_desc.push("vsftpd未禁止匿名登录(anonymous_enable)".to_string());
+                        }
+                        if !is_yes("chroot_local_user") {
+                            ftp_desc.push("vsftpd未限制用户目录(chroot_local_user)".to_string());
+                        }
+                        if !is_yes("ssl_enable") {
+                            ftp_desc.push("vsftpd未启用SSL(ssl_enable)".to_string());
+                        }
+                        if !options.contains_key("ftpd_banner") && !options.contains_key("banner_file") {
+                            ftp_desc.push("vsftpd未配置登录提示(ftpd_banner)".to_string());
+                        }
+                    } else {
+                        ftp_desc.push("无法读取vsftpd配置".to_string());
+                        eprintln!("cannot read vsftpd.conf");
+                    }
+                }
+                if mp.contains_key("proftpd") {
+                    let conf = parse::read_lines("/etc/proftpd/proftpd.conf")
+                        .or_else(|_| parse::read_lines("/etc/proftpd.conf"));
+                    if let Ok(lines) = conf {
+                        let lines = lines
+                            .map(|x| x.trim().to_string())
+                            .filter(|x| !x.starts_with("#"))
+                            .collect::<Vec<String>>();
+                        let has = |prefix: &str| lines.iter().any(|x| x.to_lowercase().starts_with(&prefix.to_lowercase()));
+                        if has("<Anonymous") {
+                            ftp_desc.push("proftpd允许匿名登录(<Anonymous>)".to_string());
+                        }
+                        if !has("DefaultRoot") {
+                            ftp_desc.push("proftpd未限制用户目录(DefaultRoot)".to_string());
+                        }
+                        if !has("TLSEngine on") {
+                            ftp_desc.push("proftpd未启用SSL(TLSEngine)".to_string());
+                        }
+                        if !has("ServerIdent on") && !has("DisplayConnect") {
+                            ftp_desc.push("proftpd未配置登录提示(ServerIdent/DisplayConnect)".to_string());
+                        }
+                    } else {
+                        ftp_desc.push("无法读取proftpd配置".to_string());
+                        eprintln!("cannot read proftpd.conf");
+                    }
+                }
+                let ftp_hardened = !ftp_enabled || ftp_desc.len() == 0;
+
+                // 策略允许保留的 FTP、telnet 按实际的登录警示信息及匿名访问区分符合策略的例外与未加固的服务
+                let host_policy = &policy::get().host;
+                let legacy_allowed = |name: &str| host_policy.legacy_services.iter().any(|x| x == name);
+                let mut legacy_desc = vec![];
+                let ftp_passed = if !ftp_enabled {
+                    true
+                } else if legacy_allowed("ftp") {
+                    let mut problems = vec![];
+                    match ftp_probe(21) {
+                        Ok((banner, anonymous)) => {
+                            if !banner_warns(&banner) {
+                                problems.push(format!("登录提示未包含警示信息({})", banner.trim()));
+                            }
+                            if anonymous {
+                                problems.push("允许匿名登录".to_string());
+                            }
+                        },
+                        Err(e) => problems.push(format!("无法连接本机 21 端口({})", e)),
+                    }
+                    legacy_desc.push(match problems.len() {
+                        0 => "FTP按策略保留, 登录提示含警示信息且禁止匿名登录".to_string(),
+                        _ => format!("FTP按策略保留, 但{}", problems.join("、")),
+                    });
+                    problems.len() == 0
+                } else {
+                    false
+                };
+                let telnet_passed = if !mp.contains_key("telnet") {
+                    true
+                } else if legacy_allowed("telnet") {
+                    let mut problems = vec![];
+                    // telnetd 在登录前显示 /etc/issue.net
+                    let banner = std::fs::read_to_string("/etc/issue.net").unwrap_or_default();
+                    if !banner_warns(&banner) {
+                        problems.push("登录提示(/etc/issue.net)未包含警示信息".to_string());
+                    }
+                    // telnet 没有匿名用户, 口令为空的账户即可不经认证登录
+                    match parse::read_lines("/etc/shadow") {
+                        Ok(lines) => {
+                            let users = lines
+                                .filter_map(|line| {
+                                    let items = line.split(":").collect::<Vec<&str>>();
+                                    match items.get(1) {
+                                        Some(&"") => Some(items[0].to_string()),
+                                        _ => None,
+                                    }
+                                })
+                                .collect::<Vec<String>>();
+                            if users.len() > 0 {
+                                problems.push(format!("以下账户口令为空, 可直接登录：{}", users.join("、")));
+                            }
+                        },
+                        Err(_) => problems.push("无法读取 /etc/shadow, 未核对空口令账户".to_string()),
+                    }
+                    legacy_desc.push(match problems.len() {
+                        0 => "telnet按策略保留, 登录提示含警示信息且没有空口令账户".to_string(),
+                        _ => format!("telnet按策略保留, 但{}", problems.join("、")),
+                    });
+                    problems.len() == 0
+                } else {
+                    false
+                };
+
+                let row = row
+                    .status(req!("svc.email"), Some(!(mp.contains_key("sendmail") || mp.contains_key("postfix"))))
+                    .status(req!("svc.ftp"), Some(ftp_passed))
+                    .status(req!("svc.ftp_hardened"), Some(ftp_hardened))
+                    .status(req!("svc.telnet"), Some(telnet_passed))
+                    .status(req!("svc.rlogin"), Some(!mp.contains_key("rlogin")))
+                    .status(req!("svc.netbios"), Some(!mp.contains_key("netbios")))
+                    .status(req!("svc.dhcp"), Some(!mp.contains_key("dhcpd")))
+                    .status(req!("svc.smb"), Some(!(mp.contains_key("smb") || mp.contains_key("samba"))))
+                    .status(req!("svc.snmp"), Some(snmp_passed))
+                    .status(req!("svc.remote_desktop"), Some(!(mp.contains_key("xdmcp") || mp.contains_key("vncserver"))))
+                    .status(req!("svc.minimum"), Some(!mp.contains_key("minimum_service")));
+
+                let desc = vec![format!("服务管理：{}", init.name()), extra_open_service_list_desc].into_iter()
+                    .chain(port_desc.into_iter())
+                    .chain(snmp_desc.into_iter())
+                    .chain(ftp_desc.into_iter())
+                    .chain(legacy_desc.into_iter())
+                    .filter(|x| x.len() > 0)
+                    .collect::<Vec<String>>();
+                row.evidence(&desc.join("\n"))
             },
+            #[cfg(target_os = "linux")]
             GuardItem::Audit => {
-                cell.add("A19", "远程访问/系统审计/审计内容");
-
                 let mut mp = HashMap::new();
 
-                if let Ok(r) = util::runcmd("cat /etc/ssh/sshd_config", None) {
-                    for line in r.lines() {
+                if let Ok(lines) = parse::read_lines("/etc/ssh/sshd_config") {
+                    for line in lines {
                         let line = line.trim();
                         if line.starts_with("Port") {
                             if let Some(port) = line.split(" ").filter(|x| x.trim().len() > 0).nth(1) {
@@ -436,11 +1280,11 @@ impl GuardItem {
                         }
                     }
                 } else {
-                    println!("cannot read /etc/ssh/sshd_config");
+                    eprintln!("cannot read /etc/ssh/sshd_config");
                 }
 
-                if let Ok(r) = util::runcmd("cat /etc/logrotate.conf", None) {
-                    for line in r.lines() {
+                if let Ok(lines) = parse::read_lines("/etc/logrotate.conf") {
+                    for line in lines {
                         if line.starts_with("rotate ") {
                             if let Some(cycle) = line.split(" ").nth(1) {
                                 if let Ok(cycle) = cycle.parse::<i32>() {
@@ -453,127 +1297,1621 @@ impl GuardItem {
                         }
                     }
                 } else {
-                    println!("cannot read /etc/logrotate.conf");
+                    eprintln!("cannot read /etc/logrotate.conf");
                 }
 
-                let service_list = vec!["sshd", "rsyslog", "auditd"];
-                for service in service_list {
-                    let cmd = format!("service {} status", service);
-                    if let Ok(r) = util::runcmd(&cmd, None) {
-                        if r.contains("正在运行") {
-                            mp.insert(service, true);
-                        }
-                    } else {
-                        println!("cannnot run command '{}'", &cmd);
+                let services = InitSystem::detect().manager();
+                for service in vec!["sshd", "rsyslog", "auditd"] {
+                    if services.is_active(service) {
+                        mp.insert(service, true);
                     }
                 }
 
-                let audit_file_list = vec![
-                    "/etc/group", "/etc/passwd", "/etc/ssh/sshd_config", "/etc/shadow",
-                    "/etc/sudoers", "/var/log/lastlog", "/etc/profile", "/etc/sysctl.conf",
-                ];
-                if let Ok(r) = util::runcmd("auditctl -l", None) {
-                    let mut watch_rule_indicator = HashMap::new();
+                // 要求监视的文件见 policy.toml 中的 [audit], 记录缺少写入或属性修改监视规则的文件
+                let audit_file_list = policy::get().audit.required_watches();
+                let missing_watches = if let Ok(r) = util::runcmd("auditctl -l", None) {
+                    let mut watched = vec![];
                     for audit_line in r.lines() {
-                        let audit_line = audit_line.trim();
-                        if audit_line.starts_with("-w") {
-                            // 匹配模式 "-w /etc/profile.d/ -p rwxa"
-                            let re = Regex::new(r"^-w\s+([^ ]+)\s+-p\s+([^ ]+)$").unwrap();
-                            let caps = re.captures(audit_line).unwrap();
+                        if let Some(caps) = patterns::RE_AUDIT_WATCH.captures(audit_line.trim()) {
                             let watch_file = caps.get(1).map_or("", |m| m.as_str());
                             let watch_action = caps.get(2).map_or("", |m| m.as_str());
-                            if audit_file_list.contains(&watch_file) && watch_action.contains(&['w', 'a'][..]) {
-                                watch_rule_indicator.insert(watch_file, true);
+                            if watch_action.contains(&['w', 'a'][..]) {
+                                watched.push(watch_file.to_string());
                             }
                         }
                     }
-                    let mut audit_file_passed = true;
-                    for audit_file in audit_file_list {
-                        if !watch_rule_indicator.contains_key(audit_file) {
-                            audit_file_passed = false;
-                            break;
-                        }
-                    }
-                    if audit_file_passed {
+                    let missing = audit_file_list.iter().filter(|x| !watched.contains(x)).cloned().collect::<Vec<String>>();
+                    if missing.len() == 0 {
                         mp.insert("audit_file_passed", true);
                     }
+                    Some(missing)
                 } else {
-                    println!("cannot run 'auditctl -l'");
-                }
-
-                cell.add("B19", &formatdoc!("
-                        [{}]开启系统日志进程(syslog)
-                        [{}]开启审计进程(auditd)
-                        [{}]开启SSH日志审计
-                        [{}]审计内容保存6个月
-                        [  ]将审计内容发送到其他日志审计设备存储
-                        [{}]至少包括：用户的添加和删除、审计功能的启动和关闭、审计策略的调整、权限变更、系统资源的异常使用、重要的系统操作（如用户登录、退出）等
-                        [{}]启用SSH
-                        [{}]修改SSH默认端口
-                    ",
-                    Mark::from(mp.contains_key("rsyslog")).as_str(),
-                    Mark::from(mp.contains_key("auditd")).as_str(),
-                    Mark::from(mp.contains_key("ssh_syslog_enabled")).as_str(),
-                    Mark::from(mp.contains_key("logrotate_cycle_passed")).as_str(),
-                    Mark::from(mp.contains_key("audit_file_passed")).as_str(),
-                    Mark::from(mp.contains_key("sshd")).as_str(),
-                    Mark::from(mp.contains_key("not_default_ssh_port")).as_str(),
-                ));
-            },
-            GuardItem::IPTables => {
-                cell.add("A21", "设定终端接入方式、网络地址范围");
-                let iplist = if let Ok(r) = util::runcmd("cat /etc/sysconfig/iptables", None) {
-                    let mut iplist = vec![];
-                    for line in r.lines() {
-                        if line.starts_with("-A whitelist") {
-                            let re = Regex::new(r"(\d{1,3}.\d{1,3}.\d{1,3}.\d{1,3}/(\d{1,2})?)").unwrap();
-                            let caps = re.captures(line).unwrap();
-                            let ip = caps.get(1).map_or("", |m| m.as_str());
-                            iplist.push(ip);
-                        }
-                    }
-                    iplist.join(";")
-                } else {
-                    println!("cannot read '/etc/sysconfig/iptables'");
-                    "".to_string()
+                    eprintln!("cannot run 'auditctl -l'");
+                    None
                 };
-                cell.add("C21", &iplist);
-            },
-            GuardItem::CommandHistory => {
-                cell.add("A25", "his命令");
 
-                let mut mp = HashMap::<&str, usize>::new();
-                if let Ok(r) = util::runcmd("cat /etc/profile", None) {
-                    let parse_size = |re: &Regex, line: &str| -> Option<usize> {
-                        if let Some(caps) = re.captures(line) {
-                            if let Some(histsz) = caps.get(1) {
-                                if let Ok(histsz) = histsz.as_str().parse::<usize>() {
-                                    return Some(histsz);
-                                }
-                            }
-                        }
-                        return None;
-                    };
-                    let re_histsz = Regex::new(r"HISTSIZE=(\d+)").unwrap();
-                    let re_histfsz = Regex::new(r"HISTFILESIZE=(\d+)").unwrap();
-                    for line in r.lines() {
-                        if !line.trim().starts_with("#") {
-                            if let Some(v) = parse_size(&re_histsz, line) {
-                                mp.insert("HISTSIZE", v);
-                            }
-                            if let Some(v) = parse_size(&re_histfsz, line) {
-                                mp.insert("HISTFILESIZE", v);
-                            }
+                // 审计配置锁定(-e 2)后, 规则在重启前不可修改
+                let audit_enabled = match util::runcmd("auditctl -s", None) {
+                    Ok(r) => parse::audit_status(&r).remove("enabled"),
+                    Err(_) => {
+                        eprintln!("cannot run 'auditctl -s'");
+                        None
+                    },
+                };
+                if audit_enabled.as_deref() == Some("2") {
+                    mp.insert("audit_immutable", true);
+                }
+
+                let mut space_left_action = None;
+                if let Ok(lines) = parse::read_lines("/etc/audit/auditd.conf") {
+                    for line in lines {
+                        let items = line.split("=").map(|x| x.trim()).collect::<Vec<&str>>();
+                        if items.len() == 2 && items[0] == "space_left_action" {
+                            space_left_action = Some(items[1].to_lowercase());
                         }
                     }
                 } else {
-                    println!("cannot read /etc/profile");
+                    eprintln!("cannot read /etc/audit/auditd.conf");
                 }
-                let histsz = mp.get("HISTSIZE").map_or(50000, |&v| v);
-                let histfsz = mp.get("HISTFILESIZE").map_or(50000, |&v| v);
-                cell.add("B25", &format!("[{}]删除系统his命令", Mark::from(histsz <= 5 && histfsz <= 5).as_str()));
-            },
-        }
-        cell
-    }
+                if let Some(action) = space_left_action.as_ref() {
+                    if action == "email" || action == "halt" {
+                        mp.insert("audit_space_left_passed", true);
+                    }
+                }
+
+                // /etc/audit 下的配置与规则文件应属于 root 且权限不宽于 640
+                let mut audit_conf_files = vec![
+                    "/etc/audit/auditd.conf".to_string(),
+                    "/etc/audit/audit.rules".to_string(),
+                ];
+                if let Ok(entries) = std::fs::read_dir("/etc/audit/rules.d") {
+                    for entry in entries.flatten() {
+                        audit_conf_files.push(entry.path().to_string_lossy().to_string());
+                    }
+                }
+                let mut bad_audit_conf = vec![];
+                for path in audit_conf_files.iter() {
+                    if let Ok(meta) = std::fs::metadata(path) {
+                        if meta.uid() != 0 || meta.mode() & 0o137 != 0 {
+                            bad_audit_conf.push(format!("{}({}:{} {:o})", path, meta.uid(), meta.gid(), meta.mode() & 0o777));
+                        }
+                    }
+                }
+
+                // 基于主机信任的认证绕过口令与密钥, .rhosts/.shosts 应被忽略, 且登录前检查用户文件的属主与权限;
+                // 均为 OpenSSH 的默认值, 配置文件中未设置时视为符合
+                let ssh_options = [
+                    ("HostbasedAuthentication", "no"),
+                    ("IgnoreRhosts", "yes"),
+                    ("StrictModes", "yes"),
+                ].iter()
+                    .map(|(keyword, expected)| {
+                        let value = sshd_option(keyword);
+                        let passed = value.as_ref().map_or(true, |x| x.eq_ignore_ascii_case(expected));
+                        (*keyword, value, passed)
+                    })
+                    .collect::<Vec<(&str, Option<String>, bool)>>();
+
+                let row = row
+                    .status(req!("audit.syslog"), Some(mp.contains_key("rsyslog")))
+                    .status(req!("audit.auditd"), Some(mp.contains_key("auditd")))
+                    .status(req!("audit.ssh_log"), Some(mp.contains_key("ssh_syslog_enabled")))
+                    .status(req!("audit.retention"), Some(mp.contains_key("logrotate_cycle_passed")))
+                    .status(req!("audit.forward"), None)
+                    .status(req!("audit.content"), Some(mp.contains_key("audit_file_passed")))
+                    .status(req!("audit.sshd"), Some(mp.contains_key("sshd")))
+                    .status(req!("audit.ssh_port"), Some(mp.contains_key("not_default_ssh_port")))
+                    .status(req!("audit.immutable"), Some(mp.contains_key("audit_immutable")))
+                    .status(req!("audit.space_left"), Some(mp.contains_key("audit_space_left_passed")))
+                    .status(req!("audit.conf_perm"), Some(bad_audit_conf.len() == 0))
+                    .status(req!("audit.ssh_hostbased"), Some(ssh_options[0].2))
+                    .status(req!("audit.ssh_ignore_rhosts"), Some(ssh_options[1].2))
+                    .status(req!("audit.ssh_strict_modes"), Some(ssh_options[2].2));
+
+                let mut desc = vec![format!(
+                    "space_left_action={}",
+                    space_left_action.unwrap_or("未设置".to_string()),
+                )];
+                match audit_enabled {
+                    Some(enabled) => desc.push(format!("enabled={}", enabled)),
+                    None => desc.push("无法执行 auditctl -s, 未能检查审计配置是否锁定".to_string()),
+                }
+                match missing_watches {
+                    Some(missing) if missing.len() > 0 => desc.push(format!("缺少审计监视规则：{}", missing.join("、"))),
+                    Some(_) => {},
+                    None => desc.push("无法执行 auditctl -l, 未能检查审计监视规则".to_string()),
+                }
+                if bad_audit_conf.len() > 0 {
+                    desc.push(format!("属主或权限不符合：{}", bad_audit_conf.join("、")));
+                }
+                for (keyword, value, _) in ssh_options.iter() {
+                    desc.push(format!("{} {}", keyword, value.as_deref().unwrap_or("未设置(默认)")));
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::IPTables => {
+                let mut iplist = vec![];
+                // filter 表中内置链的默认策略, 形如 ":INPUT DROP [0:0]"
+                let mut policies = vec![];
+                let readable = if let Ok(lines) = parse::read_lines("/etc/sysconfig/iptables") {
+                    let mut table = "".to_string();
+                    for line in lines {
+                        if line.starts_with("*") {
+                            table = line.trim_start_matches("*").trim().to_string();
+                        } else if line.starts_with(":") && table == "filter" {
+                            let items = line[1..].split_whitespace().collect::<Vec<&str>>();
+                            if items.len() >= 2 && items[1] != "-" {
+                                policies.push(format!("{} {}", items[0], items[1]));
+                            }
+                        } else if line.starts_with("-A whitelist") {
+                            if let Some(caps) = patterns::RE_IPV4_CIDR.captures(&line) {
+                                let ip = caps.get(1).map_or("", |m| m.as_str());
+                                iplist.push(ip.to_string());
+                            }
+                        }
+                    }
+                    true
+                } else {
+                    eprintln!("cannot read '/etc/sysconfig/iptables'");
+                    false
+                };
+
+                // 未使用 iptables 配置文件时(如 firewalld、nftables)需人工确认; 地址段是否仅为管理终端也需人工核对
+                let row = row.partial(req!("fw.whitelist"), if readable { Some(iplist.len() > 0) } else { None });
+
+                let mut desc = vec![];
+                if iplist.len() > 0 {
+                    desc.push(iplist.join(";"));
+                } else if readable {
+                    desc.push("whitelist 链中没有规则".to_string());
+                }
+                if policies.len() > 0 {
+                    desc.push(format!("默认策略: {}", policies.join(", ")));
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::CommandHistory => {
+                let mut mp = HashMap::<&str, usize>::new();
+                let mut histtimeformat = None;
+                if let Ok(lines) = parse::read_lines("/etc/profile") {
+                    let parse_size = |re: &Regex, line: &str| -> Option<usize> {
+                        if let Some(caps) = re.captures(line) {
+                            if let Some(histsz) = caps.get(1) {
+                                if let Ok(histsz) = histsz.as_str().parse::<usize>() {
+                                    return Some(histsz);
+                                }
+                            }
+                        }
+                        return None;
+                    };
+                    for line in lines {
+                        if !line.trim().starts_with("#") {
+                            if let Some(v) = parse_size(&patterns::RE_HISTSIZE, &line) {
+                                mp.insert("HISTSIZE", v);
+                            }
+                            if let Some(v) = parse_size(&patterns::RE_HISTFILESIZE, &line) {
+                                mp.insert("HISTFILESIZE", v);
+                            }
+                            if let Some(caps) = patterns::RE_HISTTIMEFORMAT.captures(&line) {
+                                histtimeformat = caps.get(1).map(|x| x.as_str().trim_matches(|c| c == '"' || c == '\'').to_string());
+                            }
+                        }
+                    }
+                } else {
+                    eprintln!("cannot read /etc/profile");
+                }
+                let histsz = mp.get("HISTSIZE").map_or(50000, |&v| v);
+                let histfsz = mp.get("HISTFILESIZE").map_or(50000, |&v| v);
+
+                // 删除历史记录与"保留操作日志"的审计要求相冲突, 由策略决定站点采用哪一种方式
+                let history = &policy::get().history;
+                let row = match history.mode {
+                    HistoryMode::Minimize => {
+                        row.status(req!("hist.delete"), Some(histsz <= 5 && histfsz <= 5))
+                    },
+                    HistoryMode::Audit => {
+                        // 命令执行由 auditd 的 execve 系统调用规则记录; 备用根目录下没有运行中的审计规则, 需人工确认
+                        let execve_audited = if util::root().is_some() {
+                            None
+                        } else if let Ok(r) = util::runcmd("auditctl -l", None) {
+                            Some(r.lines().any(|x| x.contains("-S execve") || x.contains(",execve")))
+                        } else {
+                            eprintln!("cannot run 'auditctl -l'");
+                            Some(false)
+                        };
+                        let retained = histsz >= history.retain_size && histfsz >= history.retain_size;
+                        row.status(req!("hist.retain"), Some(retained && histtimeformat.is_some()))
+                            .status(req!("hist.auditd"), execve_audited)
+                    },
+                };
+                row.evidence(&format!(
+                    "HISTSIZE={} HISTFILESIZE={} HISTTIMEFORMAT={}",
+                    mp.get("HISTSIZE").map_or("未设置".to_string(), |v| v.to_string()),
+                    mp.get("HISTFILESIZE").map_or("未设置".to_string(), |v| v.to_string()),
+                    histtimeformat.unwrap_or("未设置".to_string()),
+                ))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::ConsoleAccess => {
+                // root 的控制台登录可由以下任一方式限制: securetty 只列出物理控制台和虚拟终端且 login 启用 pam_securetty,
+                // login 启用 pam_access 且 access.conf 中有拒绝 root 的规则, 或 root 口令已锁定(Debian 系及 RHEL 8 起
+                // 不再提供 securetty)
+                let mut console_desc = vec![];
+                let mut unapproved_ttys = vec![];
+                let securetty_passed = match parse::read_lines("/etc/securetty") {
+                    Ok(lines) => {
+                        for line in lines {
+                            let line = line.trim();
+                            if line.len() > 0 && !line.starts_with("#") && !patterns::RE_CONSOLE_TTY.is_match(line) {
+                                unapproved_ttys.push(line.to_string());
+                            }
+                        }
+                        if unapproved_ttys.len() > 0 {
+                            console_desc.push(format!("securetty：未授权终端 {}", unapproved_ttys.join("、")));
+                        } else {
+                            console_desc.push("securetty：只列出本地终端".to_string());
+                        }
+                        unapproved_ttys.len() == 0
+                    },
+                    Err(_) => {
+                        console_desc.push("securetty：/etc/securetty 不存在".to_string());
+                        false
+                    },
+                };
+
+                let login_pam = match parse::read_lines("/etc/pam.d/login") {
+                    Ok(lines) => parse::pam_rules(lines),
+                    Err(_) => {
+                        eprintln!("cannot read /etc/pam.d/login");
+                        vec![]
+                    },
+                };
+                let pam_securetty_enabled = login_pam.iter().any(|x| x.module == "pam_securetty.so");
+                console_desc.push(format!("pam_securetty：/etc/pam.d/login 中{}", if pam_securetty_enabled { "已启用" } else { "未启用" }));
+
+                // access.conf 的规则形如 "-:root:ALL EXCEPT LOCAL", 拒绝规则的用户列表包含 root 或 ALL 即限制了 root
+                let access_restricted = login_pam.iter().any(|x| x.module == "pam_access.so") && match parse::read_lines("/etc/security/access.conf") {
+                    Ok(mut lines) => lines.any(|x| {
+                        let fields = x.trim().splitn(3, ":").collect::<Vec<&str>>();
+                        fields.len() == 3 && fields[0].trim() == "-" && fields[1].split_whitespace().any(|x| x == "root" || x == "ALL")
+                    }),
+                    Err(_) => false,
+                };
+                if access_restricted {
+                    console_desc.push("pam_access：access.conf 中有拒绝 root 的规则".to_string());
+                }
+
+                let root_locked = match parse::read_lines("/etc/shadow") {
+                    Ok(mut lines) => lines.find(|x| x.starts_with("root:"))
+                        .and_then(|x| x.split(":").nth(1).map(|x| x.starts_with("!") || x.starts_with("*"))),
+                    Err(_) => None,
+                };
+                console_desc.push(format!("root 口令：{}", match root_locked {
+                    Some(true) => "已锁定",
+                    Some(false) => "未锁定",
+                    None => "无法读取 /etc/shadow",
+                }));
+                let console_restricted = (securetty_passed && pam_securetty_enabled) || access_restricted || root_locked == Some(true);
+
+                // systemd 下由 ctrl-alt-del.target 处理, 屏蔽(链接到 /dev/null)该单元或将 CtrlAltDelBurstAction 设为 none 即禁用;
+                // SysV 及 OpenRC 由 init 按 inittab 中的 ctrlaltdel 行处理, 需注释该行
+                let init = InitSystem::detect();
+                let (ctrlaltdel_disabled, ctrlaltdel_desc) = match init {
+                    InitSystem::Systemd => {
+                        let masked = ["/etc/systemd/system", "/usr/lib/systemd/system", "/lib/systemd/system"].iter()
+                            .map(|dir| format!("{}/ctrl-alt-del.target", dir))
+                            .find(|unit| match std::fs::read_link(util::rooted(unit)) {
+                                Ok(target) => target.to_string_lossy() == "/dev/null",
+                                Err(_) => false,
+                            });
+                        let mut burst_action = None;
+                        for file in systemd_system_conf() {
+                            if let Ok(lines) = parse::read_lines(&file) {
+                                for line in lines {
+                                    if let Some(("CtrlAltDelBurstAction", v)) = line.trim().split_once("=") {
+                                        burst_action = Some(v.trim().to_string());
+                                    }
+                                }
+                            }
+                        }
+                        match (masked, burst_action) {
+                            (Some(unit), _) => (true, format!("Ctrl+Alt+Del：{} 已屏蔽", unit)),
+                            (None, Some(action)) if action == "none" => (true, "Ctrl+Alt+Del：CtrlAltDelBurstAction=none".to_string()),
+                            (None, action) => (false, format!(
+                                "Ctrl+Alt+Del：ctrl-alt-del.target 未屏蔽, CtrlAltDelBurstAction={}",
+                                action.unwrap_or("reboot-force(默认)".to_string()),
+                            )),
+                        }
+                    },
+                    InitSystem::SysV | InitSystem::OpenRC => match parse::read_lines("/etc/inittab") {
+                        Ok(mut lines) => match lines.find(|x| !x.trim().starts_with("#") && x.contains(":ctrlaltdel:")) {
+                            Some(line) => (false, format!("Ctrl+Alt+Del：/etc/inittab 中 {}", line.trim())),
+                            None => (true, "Ctrl+Alt+Del：/etc/inittab 中无 ctrlaltdel 行".to_string()),
+                        },
+                        Err(_) => (true, "Ctrl+Alt+Del：无 /etc/inittab".to_string()),
+                    },
+                };
+
+                row.status(req!("console.securetty"), Some(console_restricted))
+                    .status(req!("console.ctrlaltdel"), Some(ctrlaltdel_disabled))
+                    .evidence(&console_desc.join("\n"))
+                    .evidence(&format!("{} ({})", ctrlaltdel_desc, init.name()))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::HomeDir => {
+                let mut bad_homes = vec![];
+                let mut bad_dotfiles = vec![];
+                let mut trust_files = vec![];
+                if let Ok(lines) = parse::read_lines("/etc/passwd") {
+                    for line in lines {
+                        // 格式为 name:password:uid:gid:gecos:home:shell
+                        let items = line.trim().split(":").collect::<Vec<&str>>();
+                        if items.len() != 7 || items[0].starts_with("#") {
+                            continue;
+                        }
+                        let (name, home, shell) = (items[0], items[5], items[6]);
+                        let uid = match items[2].parse::<u32>() {
+                            Ok(uid) => uid,
+                            Err(_) => continue,
+                        };
+                        // 只检查可登录的 root 及普通用户
+                        if (uid != 0 && uid < 1000) || uid == 65534 || shell.ends_with("/nologin") || shell.ends_with("/false") {
+                            continue;
+                        }
+                        let meta = match std::fs::metadata(util::rooted(home)) {
+                            Ok(meta) => meta,
+                            Err(_) => continue,
+                        };
+                        // 属主必须为用户自身, 权限不宽于 750
+                        if meta.uid() != uid || meta.mode() & 0o027 != 0 {
+                            bad_homes.push(format!("{}({} {:o})", name, home, meta.mode() & 0o777));
+                        }
+                        for dotfile in vec![".bashrc", ".bash_profile", ".bash_login", ".bash_logout", ".profile", ".cshrc"] {
+                            let path = format!("{}/{}", home.trim_end_matches("/"), dotfile);
+                            if let Ok(meta) = std::fs::metadata(util::rooted(&path)) {
+                                if meta.mode() & 0o022 != 0 {
+                                    bad_dotfiles.push(path);
+                                }
+                            }
+                        }
+                        for trust_file in vec![".rhosts", ".forward"] {
+                            let path = format!("{}/{}", home.trim_end_matches("/"), trust_file);
+                            if util::rooted(&path).exists() {
+                                trust_files.push(path);
+                            }
+                        }
+                    }
+                } else {
+                    eprintln!("cannot read /etc/passwd");
+                }
+
+                let row = row
+                    .status(req!("home.owner_mode"), Some(bad_homes.len() == 0))
+                    .status(req!("home.dotfiles"), Some(bad_dotfiles.len() == 0))
+                    .status(req!("home.trust_files"), Some(trust_files.len() == 0));
+
+                let mut desc = vec![];
+                if bad_homes.len() > 0 {
+                    desc.push(format!("主目录属主或权限不符合：{}", bad_homes.join("、")));
+                }
+                if bad_dotfiles.len() > 0 {
+                    desc.push(format!("组或其他用户可写：{}", bad_dotfiles.join("、")));
+                }
+                if trust_files.len() > 0 {
+                    desc.push(format!("存在文件：{}", trust_files.join("、")));
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            GuardItem::DevTools => {
+                let mut dirs = vec!["/usr/local/sbin", "/usr/local/bin", "/usr/sbin", "/usr/bin", "/sbin", "/bin"]
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>();
+                // 检查备用根目录时本机的 PATH 没有意义
+                if let (Ok(path), None) = (std::env::var("PATH"), util::root()) {
+                    for dir in path.split(":") {
+                        if dir.len() > 0 && !dirs.iter().any(|x| x == dir) {
+                            dirs.push(dir.to_string());
+                        }
+                    }
+                }
+
+                let mut installed = vec![];
+                for tool in vec!["gcc", "cc", "g++", "c++", "clang", "make", "gdb", "cpan"] {
+                    for dir in dirs.iter() {
+                        let path = format!("{}/{}", dir.trim_end_matches("/"), tool);
+                        if util::rooted(&path).exists() {
+                            installed.push(path);
+                            break;
+                        }
+                    }
+                }
+
+                // 只对生产主机提出要求, 开发或测试主机仅列出已安装的工具
+                let row = match policy::get().host.role {
+                    HostRole::Production => row.status(req!("devtools.absent"), Some(installed.len() == 0)),
+                    HostRole::Development => row.status(req!("devtools.absent"), None),
+                };
+
+                let mut desc = vec![];
+                if installed.len() > 0 {
+                    desc.push(format!("已安装：{}", installed.join("、")));
+                }
+                if policy::get().host.role == HostRole::Development {
+                    desc.push("主机角色为开发/测试, 不要求移除开发工具".to_string());
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::ListenAddr => {
+                // 常见管理服务端口: 数据库、缓存、Docker API 及各类监控采集程序
+                let management_ports = vec![
+                    (3306, "MySQL"), (5432, "PostgreSQL"), (1521, "Oracle"), (27017, "MongoDB"),
+                    (6379, "Redis"), (11211, "Memcached"), (9200, "Elasticsearch"),
+                    (2375, "Docker API"), (2376, "Docker API"), (9100, "node_exporter"), (9090, "Prometheus"),
+                ];
+                let allowlist = &policy::get().network.listen_allowlist;
+
+                let mut exposed = vec![];
+                let passed = if let Ok(r) = util::runcmd("ss -tlnp", None) {
+                    // 格式为 State Recv-Q Send-Q Local-Address:Port Peer-Address:Port Process
+                    for line in r.lines().skip(1) {
+                        let items = line.split_whitespace().collect::<Vec<&str>>();
+                        if items.len() < 5 {
+                            continue;
+                        }
+                        let (addr, port) = match items[3].rsplitn(2, ":").collect::<Vec<&str>>()[..] {
+                            [port, addr] => (addr, port),
+                            _ => continue,
+                        };
+                        let port = match port.parse::<u16>() {
+                            Ok(port) => port,
+                            Err(_) => continue,
+                        };
+                        if !vec!["0.0.0.0", "*", "[::]"].contains(&addr) {
+                            continue;
+                        }
+                        let service = match management_ports.iter().find(|x| x.0 == port) {
+                            Some(x) => x.1,
+                            None => continue,
+                        };
+                        let process = items.get(5)
+                            .and_then(|x| patterns::RE_SS_PROCESS.captures(x))
+                            .and_then(|caps| caps.get(1))
+                            .map_or("", |m| m.as_str());
+                        if allowlist.iter().any(|x| x == &port.to_string() || (process.len() > 0 && x == process)) {
+                            continue;
+                        }
+                        let desc = format!("{}({} {})", service, items[3], process);
+                        if !exposed.contains(&desc) {
+                            exposed.push(desc);
+                        }
+                    }
+                    Some(exposed.len() == 0)
+                } else {
+                    eprintln!("cannot run command 'ss -tlnp'");
+                    None
+                };
+                let row = row.status(req!("net.listen.local"), passed);
+                if exposed.len() > 0 {
+                    row.evidence(&format!("监听在所有地址上：{}", exposed.join("、")))
+                } else {
+                    row
+                }
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::TmpMounts => {
+                let mount_points = vec!["/tmp", "/var/tmp", "/dev/shm"];
+                let required = vec!["noexec", "nosuid", "nodev"];
+                // 两个文件的格式均为 device mountpoint fstype options ..., 返回挂载点对应的挂载选项
+                let read_mounts = |path: &str| -> Option<HashMap<String, Vec<String>>> {
+                    let lines = match parse::read_lines(path) {
+                        Ok(lines) => lines,
+                        Err(_) => {
+                            eprintln!("cannot read {}", path);
+                            return None;
+                        },
+                    };
+                    let mut mounts = HashMap::new();
+                    for line in lines {
+                        let items = line.split_whitespace().collect::<Vec<&str>>();
+                        if items.len() < 4 || items[0].starts_with("#") {
+                            continue;
+                        }
+                        let options = items[3].split(",").map(|x| x.to_string()).collect::<Vec<String>>();
+                        mounts.insert(items[1].to_string(), options);
+                    }
+                    Some(mounts)
+                };
+                let proc_mounts = read_mounts("/proc/mounts");
+                let fstab = read_mounts("/etc/fstab");
+
+                let mut not_separate = vec![];
+                let mut missing_options = vec![];
+                if let Some(proc_mounts) = proc_mounts.as_ref() {
+                    for mount_point in mount_points.iter() {
+                        match proc_mounts.get(*mount_point) {
+                            Some(options) => {
+                                let missing = required.iter().filter(|x| !options.iter().any(|o| o == *x)).map(|x| *x).collect::<Vec<&str>>();
+                                if missing.len() > 0 {
+                                    missing_options.push(format!("{} 缺少 {}", mount_point, missing.join(",")));
+                                }
+                            },
+                            None => not_separate.push(mount_point.to_string()),
+                        }
+                    }
+                }
+                // fstab 中的配置决定重启后的挂载选项, 存在条目时也需满足要求
+                if let Some(fstab) = fstab.as_ref() {
+                    for mount_point in mount_points.iter() {
+                        if let Some(options) = fstab.get(*mount_point) {
+                            let missing = required.iter().filter(|x| !options.iter().any(|o| o == *x)).map(|x| *x).collect::<Vec<&str>>();
+                            if missing.len() > 0 {
+                                missing_options.push(format!("/etc/fstab 中 {} 缺少 {}", mount_point, missing.join(",")));
+                            }
+                        }
+                    }
+                }
+
+                let row = row
+                    .status(req!("mount.separate"), proc_mounts.as_ref().map(|_| not_separate.len() == 0))
+                    .status(req!("mount.options"), proc_mounts.as_ref().map(|_| not_separate.len() == 0 && missing_options.len() == 0));
+
+                let mut desc = vec![];
+                if not_separate.len() > 0 {
+                    desc.push(format!("未单独挂载：{}", not_separate.join("、")));
+                }
+                desc.extend(missing_options);
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::CloudMetadata => {
+                // 通过 DMI 信息识别云平台, Azure 的 chassis_asset_tag 为固定值
+                let mut dmi = vec![];
+                for name in vec!["sys_vendor", "product_name", "bios_vendor", "chassis_asset_tag"] {
+                    if let Ok(mut lines) = parse::read_lines(format!("/sys/class/dmi/id/{}", name)) {
+                        if let Some(line) = lines.next() {
+                            dmi.push(line.trim().to_string());
+                        }
+                    }
+                }
+                let dmi = dmi.join(" ");
+                let providers = vec![
+                    ("Amazon EC2", "Amazon EC2"), ("Google", "Google Cloud"),
+                    ("7783-7084-3265-9085-8269-3286-77", "Microsoft Azure"), ("Alibaba Cloud", "阿里云"),
+                    ("Tencent Cloud", "腾讯云"), ("HUAWEICLOUD", "华为云"), ("OpenStack", "OpenStack"),
+                ];
+                let provider = providers.iter().find(|x| dmi.contains(x.0)).map(|x| x.1);
+
+                if config::get().network.offline {
+                    let desc = match provider {
+                        Some(provider) => format!("云平台：{}", provider),
+                        None => "未识别到云平台".to_string(),
+                    };
+                    return row.status(req!("cloud.imds"), None)
+                        .evidence(&format!("{}\n离线模式, 未探测元数据服务", desc));
+                }
+
+                // 元数据服务统一使用链路本地地址 169.254.169.254
+                let addr = SocketAddr::from(([169, 254, 169, 254], 80));
+                let reachable = TcpStream::connect_timeout(&addr, Duration::from_secs(1)).is_ok();
+
+                // 不带令牌或特定请求头即可读取元数据, 即 IMDSv1 方式的未鉴权访问. 优先以 nobody 用户
+                // 发起请求, 以确认普通用户是否可以访问; 无法切换用户时由当前用户直接请求.
+                // 元数据服务只能从本机直接访问, 请求不能经过 http_proxy 等环境变量指定的代理
+                let url = "http://169.254.169.254/latest/meta-data/";
+                let mut unprivileged = true;
+                let unauthenticated = if !reachable {
+                    false
+                } else if let Ok(r) = util::runcmd(&format!("runuser -u nobody -- curl -s -m 2 --noproxy '*' -o /dev/null -w '%{{http_code}}' {}", url), None) {
+                    r.trim() == "200"
+                } else {
+                    unprivileged = false;
+                    let mut status = String::new();
+                    if let Ok(mut stream) = TcpStream::connect_timeout(&addr, Duration::from_secs(1)) {
+                        let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+                        let request = "GET /latest/meta-data/ HTTP/1.0\r\nHost: 169.254.169.254\r\n\r\n";
+                        if stream.write_all(request.as_bytes()).is_ok() {
+                            let mut buf = [0u8; 64];
+                            if let Ok(n) = stream.read(&mut buf) {
+                                status = String::from_utf8_lossy(&buf[..n]).lines().next().unwrap_or("").to_string();
+                            }
+                        }
+                    }
+                    status.split_whitespace().nth(1) == Some("200")
+                };
+
+                let row = row.status(req!("cloud.imds"), Some(!unauthenticated));
+
+                let mut desc = vec![match provider {
+                    Some(provider) => format!("云平台：{}", provider),
+                    None => "未识别到云平台".to_string(),
+                }];
+                if reachable {
+                    desc.push("元数据服务 169.254.169.254 可访问".to_string());
+                }
+                if unauthenticated {
+                    let user = if unprivileged { "普通用户" } else { "当前用户" };
+                    desc.push(format!("{}可未经鉴权读取 {}", user, url));
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::SshCredential => {
+                let ssh = &policy::get().ssh;
+                if !ssh.weak_credential_test {
+                    return row.status(req!("ssh.default_cred"), None)
+                        .evidence("未启用默认口令登录测试(policy.toml 中 [ssh] weak_credential_test)");
+                }
+
+                let mut port = 22;
+                if let Ok(lines) = parse::read_lines("/etc/ssh/sshd_config") {
+                    for line in lines {
+                        let items = line.split_whitespace().collect::<Vec<&str>>();
+                        if items.len() == 2 && items[0] == "Port" {
+                            if let Ok(v) = items[1].parse::<u16>() {
+                                port = v;
+                            }
+                        }
+                    }
+                }
+
+                let mut succeeded = vec![];
+                let mut error = None;
+                for credential in ssh.credentials.iter() {
+                    let items = credential.splitn(2, ":").collect::<Vec<&str>>();
+                    if items.len() != 2 {
+                        continue;
+                    }
+                    match ssh_login(port, items[0], items[1]) {
+                        Ok(true) => succeeded.push(items[0].to_string()),
+                        Ok(false) => {},
+                        Err(e) => {
+                            error = Some(e);
+                            break;
+                        },
+                    }
+                }
+
+                let passed = match error {
+                    Some(_) => None,
+                    None => Some(succeeded.len() == 0),
+                };
+                let row = row.status(req!("ssh.default_cred"), passed);
+
+                let mut desc = vec![];
+                if succeeded.len() > 0 {
+                    desc.push(format!("使用默认口令登录成功的用户：{}", succeeded.join("、")));
+                }
+                if let Some(e) = error {
+                    desc.push(format!("无法完成登录测试：{}", e));
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::KernelModules => {
+                // modprobe 中模块名的 "-" 与 "_" 等价, 统一使用 "_" 比较
+                let normalize = |name: &str| name.trim().replace("-", "_");
+                let modules = vec!["dccp", "sctp", "rds", "tipc", "cramfs", "usb-storage", "firewire-core"];
+
+                // "install <模块> /bin/true" 禁止加载, "blacklist <模块>" 禁止自动加载
+                let mut disabled = vec![];
+                if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/modprobe.d")) {
+                    for entry in entries.flatten() {
+                        let path = entry.path();
+                        if path.extension().map_or(true, |x| x != "conf") {
+                            continue;
+                        }
+                        let lines = match parse::read_lines(&path) {
+                            Ok(lines) => lines,
+                            Err(_) => continue,
+                        };
+                        for line in lines {
+                            let items = line.split_whitespace().collect::<Vec<&str>>();
+                            if items.len() >= 2 && !items[0].starts_with("#") {
+                                let install_disabled = items[0] == "install" && items.len() >= 3
+                                    && (items[2].ends_with("/true") || items[2].ends_with("/false"));
+                                if items[0] == "blacklist" || install_disabled {
+                                    disabled.push(normalize(items[1]));
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    eprintln!("cannot read /etc/modprobe.d");
+                }
+
+                // 与 lsmod 相同, 从 /proc/modules 读取已加载的模块
+                let mut loaded = vec![];
+                if let Ok(lines) = parse::read_lines("/proc/modules") {
+                    for line in lines {
+                        if let Some(name) = line.split_whitespace().nth(0) {
+                            loaded.push(normalize(name));
+                        }
+                    }
+                } else {
+                    eprintln!("cannot read /proc/modules");
+                }
+
+                let mut row = row;
+                let mut desc = vec![];
+                for module in modules {
+                    let name = normalize(module);
+                    let is_disabled = disabled.contains(&name);
+                    let is_loaded = loaded.contains(&name);
+                    row = row.status(Req::from(format!("kmod.{}", module)), Some(is_disabled && !is_loaded));
+                    if !is_disabled {
+                        desc.push(format!("{} 未禁用", module));
+                    }
+                    if is_loaded {
+                        desc.push(format!("{} 已加载", module));
+                    }
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::X11 => {
+                // OpenSSH 默认不开启 X11 转发, sshd 以配置文件中第一次出现的值为准
+                let mut forwarding = false;
+                if let Ok(lines) = parse::read_lines("/etc/ssh/sshd_config") {
+                    for line in lines {
+                        let items = line.split_whitespace().collect::<Vec<&str>>();
+                        if items.len() == 2 && items[0].eq_ignore_ascii_case("X11Forwarding") {
+                            forwarding = items[1].eq_ignore_ascii_case("yes");
+                            break;
+                        }
+                    }
+                } else {
+                    eprintln!("cannot read /etc/ssh/sshd_config");
+                }
+
+                // 显示 :N 对应 TCP 端口 6000+N
+                let mut tcp_displays = vec![];
+                for port in 6000..6010u16 {
+                    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+                    if TcpStream::connect_timeout(&addr, Duration::from_millis(200)).is_ok() {
+                        tcp_displays.push(port.to_string());
+                    }
+                }
+
+                // 只有在图形会话中才能查询 xhost 访问控制
+                let xhost = if std::env::var("DISPLAY").map_or(false, |x| x.len() > 0) {
+                    match util::runcmd("xhost", None) {
+                        Ok(r) => Some(r),
+                        Err(_) => {
+                            eprintln!("cannot run command 'xhost'");
+                            None
+                        },
+                    }
+                } else {
+                    None
+                };
+                let access_control = xhost.as_ref().map(|x| !x.contains("access control disabled"));
+
+                let row = row
+                    .status(req!("x11.forwarding"), Some(!forwarding))
+                    .status(req!("x11.tcp"), Some(tcp_displays.len() == 0))
+                    .status(req!("x11.xhost"), access_control);
+
+                let mut desc = vec![];
+                if forwarding {
+                    desc.push("sshd_config 中 X11Forwarding yes".to_string());
+                }
+                if tcp_displays.len() > 0 {
+                    desc.push(format!("X 服务监听 TCP 端口：{}", tcp_displays.join("、")));
+                }
+                match xhost {
+                    Some(xhost) => desc.push(format!("xhost：{}", xhost.trim())),
+                    None => desc.push("未在图形会话中运行, 无法检查 xhost".to_string()),
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::KernelParams => {
+                let kernel = &policy::get().kernel;
+                // 与 sysctl 相同, 从 /proc/sys 读取当前生效的值
+                let sysctl = |name: &str| -> Option<i64> {
+                    let path = format!("/proc/sys/{}", name.replace(".", "/"));
+                    match std::fs::read_to_string(&path) {
+                        Ok(v) => v.trim().parse::<i64>().ok(),
+                        Err(_) => {
+                            eprintln!("cannot read {}", path);
+                            None
+                        },
+                    }
+                };
+                let panic = sysctl("kernel.panic");
+                let kptr_restrict = sysctl("kernel.kptr_restrict");
+                let dmesg_restrict = sysctl("kernel.dmesg_restrict");
+
+                // kernel.panic 为负数时崩溃后立即重启, 为 0 时不重启
+                let panic_passed = panic.map(|v| v < 0 || (v > 0 && v <= kernel.panic_timeout as i64));
+
+                // 崩溃转储内核已加载即说明 kdump 可用, 否则查看 kdump(Debian 系为 kdump-tools)服务状态
+                let crash_loaded = match parse::read_lines("/sys/kernel/kexec_crash_loaded") {
+                    Ok(mut lines) => lines.next().map_or(false, |x| x.trim() == "1"),
+                    Err(_) => false,
+                };
+                let kdump_active = crash_loaded || {
+                    let services = InitSystem::detect().manager();
+                    vec!["kdump", "kdump-tools"].into_iter().any(|service| services.is_active(service))
+                };
+
+                let row = row
+                    .status(req!("kernel.panic"), panic_passed)
+                    .status(req!("kernel.kptr_restrict"), kptr_restrict.map(|v| v >= kernel.kptr_restrict as i64))
+                    .status(req!("kernel.dmesg_restrict"), dmesg_restrict.map(|v| v >= kernel.dmesg_restrict as i64))
+                    .status(req!("kernel.kdump"), if kernel.kdump { Some(kdump_active) } else { None });
+
+                let value = |v: Option<i64>| v.map_or("无法读取".to_string(), |v| v.to_string());
+                let mut desc = vec![
+                    format!("kernel.panic = {}", value(panic)),
+                    format!("kernel.kptr_restrict = {}", value(kptr_restrict)),
+                    format!("kernel.dmesg_restrict = {}", value(dmesg_restrict)),
+                    format!("kdump: {}", if kdump_active { "已启用" } else { "未启用" }),
+                ];
+                if !kernel.kdump {
+                    desc.push("策略未要求启用 kdump(policy.toml 中 [kernel] kdump)".to_string());
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::TimeLocale => {
+                let time = &policy::get().time;
+                // Debian 系记录在 /etc/timezone, 其余发行版的 /etc/localtime 为指向时区文件的链接
+                let timezone = match parse::read_lines("/etc/timezone") {
+                    Ok(mut lines) => lines.next().map(|x| x.trim().to_string()),
+                    Err(_) => None,
+                }.or_else(|| {
+                    std::fs::read_link(util::rooted("/etc/localtime")).ok()
+                        .and_then(|x| x.to_string_lossy().split("zoneinfo/").nth(1).map(|x| x.to_string()))
+                });
+
+                // RHEL 系为 /etc/locale.conf, Debian 系为 /etc/default/locale
+                let mut locale = None;
+                for path in vec!["/etc/locale.conf", "/etc/default/locale"] {
+                    if let Ok(lines) = parse::read_lines(path) {
+                        for line in lines {
+                            if let Some(v) = line.trim().strip_prefix("LANG=") {
+                                locale = Some(v.trim_matches('"').to_string());
+                            }
+                        }
+                    }
+                    if locale.is_some() {
+                        break;
+                    }
+                }
+                // 未指定语言环境时只要求使用 UTF-8 编码
+                let locale_passed = locale.as_ref().map(|x| {
+                    if time.locale.len() > 0 {
+                        x == &time.locale
+                    } else {
+                        let x = x.to_lowercase();
+                        x.ends_with("utf-8") || x.ends_with("utf8")
+                    }
+                });
+
+                // /etc/adjtime 第三行为 UTC 或 LOCAL, 文件不存在时硬件时钟按 UTC 处理
+                let rtc = match parse::read_lines("/etc/adjtime") {
+                    Ok(mut lines) => lines.nth(2).map_or(RtcMode::Utc, |x| {
+                        if x.trim() == "LOCAL" { RtcMode::Local } else { RtcMode::Utc }
+                    }),
+                    Err(_) => RtcMode::Utc,
+                };
+
+                let row = row
+                    .status(req!("time.timezone"), Some(timezone.as_ref() == Some(&time.timezone)))
+                    .status(req!("time.locale"), locale_passed)
+                    .status(req!("time.rtc"), Some(rtc == time.rtc));
+
+                let desc = vec![
+                    format!("时区: {} (策略: {})", timezone.unwrap_or("未知".to_string()), time.timezone),
+                    format!("语言环境: {}", locale.unwrap_or("未设置".to_string())),
+                    format!("硬件时钟: {}", if rtc == RtcMode::Utc { "UTC" } else { "本地时间" }),
+                ];
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::PamStack => {
+                // Debian 系各服务包含 common-*, RHEL 系包含 system-auth、password-auth
+                let (stacks, common) = if util::rooted("/etc/pam.d/common-auth").exists() {
+                    (vec!["common-auth", "common-password"], vec!["common-auth"])
+                } else {
+                    (vec!["system-auth", "password-auth"], vec!["system-auth", "password-auth"])
+                };
+                let read = |name: &str| parse::read_lines(format!("/etc/pam.d/{}", name)).ok().map(|x| parse::pam_rules(x));
+
+                let mut desc = vec![];
+                let mut unique = None;
+                let mut ordered = None;
+                for name in stacks {
+                    let rules = match read(name) {
+                        Some(rules) => rules,
+                        None => continue,
+                    };
+                    // 同时或重复配置复杂度模块时以先执行的为准, 后面的参数看似已配置但并不生效
+                    let quality = rules.iter()
+                        .filter(|x| x.typ == "password" && (x.module == "pam_cracklib.so" || x.module == "pam_pwquality.so"))
+                        .map(|x| x.module.as_str())
+                        .collect::<Vec<&str>>();
+                    if quality.len() > 1 {
+                        desc.push(format!("{}: 重复配置口令复杂度模块 {}", name, quality.join("、")));
+                    }
+                    unique = Some(unique.unwrap_or(true) && quality.len() <= 1);
+
+                    // sufficient 规则成功时直接返回, 排在其后的复杂度与登录失败处理模块不会执行
+                    let mut passed = true;
+                    for (typ, modules) in vec![
+                        ("password", vec!["pam_cracklib.so", "pam_pwquality.so"]),
+                        ("auth", vec!["pam_faillock.so", "pam_tally2.so"]),
+                    ] {
+                        let rules = rules.iter().filter(|x| x.typ == typ).collect::<Vec<&parse::PamRule>>();
+                        if let Some(idx) = rules.iter().position(|x| modules.contains(&x.module.as_str())) {
+                            if let Some(r) = rules[..idx].iter().find(|x| x.control == "sufficient") {
+                                desc.push(format!("{}: {} 排在 {} {} 之后, 可能被跳过", name, rules[idx].module, r.control, r.module));
+                                passed = false;
+                            }
+                        }
+                    }
+                    ordered = Some(ordered.unwrap_or(true) && passed);
+                }
+
+                // 远程与控制台登录需包含公共认证配置, 否则其中的策略对这些登录方式不生效
+                let mut included = None;
+                for service in vec!["sshd", "login"] {
+                    let rules = match read(service) {
+                        Some(rules) => rules,
+                        None => continue,
+                    };
+                    let passed = rules.iter().any(|x| {
+                        (x.typ == "@include" || (x.typ == "auth" && (x.control == "include" || x.control == "substack")))
+                            && common.contains(&x.module.as_str())
+                    });
+                    if !passed {
+                        desc.push(format!("{}: 未包含 {}", service, common.join(" 或 ")));
+                    }
+                    included = Some(included.unwrap_or(true) && passed);
+                }
+
+                let row = row
+                    .status(req!("pam.unique"), unique)
+                    .status(req!("pam.order"), ordered)
+                    .status(req!("pam.include"), included);
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::SudoLog => {
+                // sudoers 中的 "Defaults !syslog" 会关闭 sudo 的 syslog 记录
+                let mut sudoers = vec!["/etc/sudoers".to_string()];
+                if let Ok(entries) = std::fs::read_dir("/etc/sudoers.d") {
+                    sudoers.extend(entries.filter_map(|x| x.ok()).map(|x| x.path().to_string_lossy().to_string()));
+                }
+                let mut nosyslog = vec![];
+                for file in sudoers.iter() {
+                    if let Ok(lines) = parse::read_lines(file) {
+                        if lines.map(|x| x.trim().to_string()).any(|x| patterns::RE_SUDO_NOSYSLOG.is_match(&x)) {
+                            nosyslog.push(file.to_string());
+                        }
+                    } else if file == "/etc/sudoers" {
+                        eprintln!("cannot read /etc/sudoers");
+                    }
+                }
+
+                // 优先读取 journald 中最近 30 天的记录, 没有 journald 时读取 RHEL 系的 secure 或 Debian 系的 auth.log
+                let mut source = "journald".to_string();
+                let mut records = match util::runcmd("journalctl _COMM=sudo --since -30d --no-pager -o cat", None) {
+                    Ok(r) => r.lines().filter(|x| !x.starts_with("-- ")).map(|x| x.to_string()).collect::<Vec<String>>(),
+                    Err(_) => vec![],
+                };
+                if records.len() == 0 {
+                    for path in vec!["/var/log/secure", "/var/log/auth.log"] {
+                        if let Ok(lines) = parse::read_lines(path) {
+                            source = path.to_string();
+                            records = lines.filter_map(|x| x.split(" sudo: ").nth(1).map(|x| x.to_string())).collect();
+                            break;
+                        }
+                    }
+                }
+
+                // 记录形如 "alice : TTY=pts/0 ; PWD=/home/alice ; USER=root ; COMMAND=/bin/ls",
+                // 失败时为 "alice : 3 incorrect password attempts ; ..." 或 "alice : user NOT in sudoers ; ..."
+                let mut count = 0;
+                let mut failed = 0;
+                let mut users = vec![];
+                for record in records.iter() {
+                    let user = match record.split(" : ").next() {
+                        Some(user) if record.contains(" : ") => user.trim().to_string(),
+                        _ => continue,
+                    };
+                    if record.contains("incorrect password attempt") || record.contains("NOT in sudoers") {
+                        failed += 1;
+                    } else if record.contains("COMMAND=") {
+                        count += 1;
+                    } else {
+                        continue;
+                    }
+                    if !users.contains(&user) {
+                        users.push(user);
+                    }
+                }
+
+                // 失败次数只作为证据供审计人员核实, 不计入是否符合: 输错一次口令也会留下记录
+                let row = row.status(req!("sudo.syslog"), Some(nosyslog.len() == 0));
+
+                let mut desc = vec![
+                    format!("来源: {}", source),
+                    format!("sudo 执行 {} 次, 失败 {} 次", count, failed),
+                    format!("使用者: {}", if users.len() > 0 { users.join(", ") } else { "无".to_string() }),
+                ];
+                for file in nosyslog {
+                    desc.push(format!("{}: Defaults !syslog", file));
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::PendingReboot => {
+                let running = match util::runcmd("uname -r", None) {
+                    Ok(r) => r.trim().to_string(),
+                    Err(_) => {
+                        eprintln!("cannot run command 'uname -r'");
+                        "".to_string()
+                    },
+                };
+                // 各发行版安装的内核都在 /boot 下有 vmlinuz-<版本>, RHEL 系的救援内核除外
+                let mut installed = match std::fs::read_dir("/boot") {
+                    Ok(entries) => entries.filter_map(|x| x.ok())
+                        .filter_map(|x| x.file_name().to_string_lossy().strip_prefix("vmlinuz-").map(|x| x.to_string()))
+                        .filter(|x| !x.contains("rescue"))
+                        .collect::<Vec<String>>(),
+                    Err(_) => vec![],
+                };
+                installed.sort_by_key(|x| parse::version_key(x));
+                let newest = installed.last().cloned();
+                let kernel_passed = match (newest.as_ref(), running.len()) {
+                    (Some(newest), n) if n > 0 => Some(&running == newest),
+                    _ => None,
+                };
+
+                // RHEL 系 needs-restarting -r 需要重启时退出码为 1; Debian 系更新后创建 /var/run/reboot-required
+                let mut desc = vec![
+                    format!("运行内核: {}", running),
+                    format!("已安装内核: {}", if installed.len() > 0 { installed.join(", ") } else { "无法获取".to_string() }),
+                ];
+                let required = if std::path::Path::new("/usr/bin/needs-restarting").exists() {
+                    // 其他退出码表示命令本身出错(如缺少插件或权限不足), 不能据此判断
+                    match util::output(Command::new("needs-restarting").arg("-r")) {
+                        Ok(output) => match output.status.code() {
+                            Some(0) => {
+                                desc.push("needs-restarting -r: 无需重启".to_string());
+                                Some(false)
+                            },
+                            Some(1) => {
+                                desc.push("needs-restarting -r: 需要重启".to_string());
+                                Some(true)
+                            },
+                            code => {
+                                desc.push(format!(
+                                    "needs-restarting -r: 执行失败({}) {}",
+                                    code.map_or("killed".to_string(), |x| format!("exit {}", x)),
+                                    String::from_utf8_lossy(&output.stderr).trim(),
+                                ));
+                                None
+                            },
+                        },
+                        Err(e) => {
+                            desc.push(format!("needs-restarting -r: 无法执行 {:?}", e));
+                            None
+                        },
+                    }
+                } else if std::path::Path::new("/var/lib/dpkg").exists() {
+                    let required = std::path::Path::new("/var/run/reboot-required").exists();
+                    let pkgs = parse::read_lines("/var/run/reboot-required.pkgs")
+                        .map(|lines| lines.map(|x| x.trim().to_string()).filter(|x| x.len() > 0).collect::<Vec<String>>())
+                        .unwrap_or_default();
+                    desc.push(match (required, pkgs.len()) {
+                        (false, _) => "/var/run/reboot-required: 不存在".to_string(),
+                        (true, 0) => "/var/run/reboot-required: 存在".to_string(),
+                        (true, _) => format!("/var/run/reboot-required: 存在, 涉及 {}", pkgs.join(", ")),
+                    });
+                    Some(required)
+                } else {
+                    None
+                };
+
+                let row = row
+                    .status(req!("reboot.kernel"), kernel_passed)
+                    .status(req!("reboot.required"), required.map(|x| !x));
+
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::ResourceLimits => {
+                let limits = &policy::get().limits;
+
+                // pam_limits 先读取 limits.conf, 再按文件名顺序读取 limits.d 下的 *.conf
+                let mut files = vec!["/etc/security/limits.conf".to_string()];
+                if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/security/limits.d")) {
+                    let mut confs = entries.filter_map(|x| x.ok())
+                        .map(|x| format!("/etc/security/limits.d/{}", x.file_name().to_string_lossy()))
+                        .filter(|x| x.ends_with(".conf"))
+                        .collect::<Vec<String>>();
+                    confs.sort();
+                    files.extend(confs);
+                }
+                let mut rules = vec![];
+                for file in files.iter() {
+                    match parse::read_lines(file) {
+                        Ok(lines) => rules.extend(parse::limit_rules(lines)),
+                        Err(_) => if file == "/etc/security/limits.conf" {
+                            eprintln!("cannot read /etc/security/limits.conf");
+                        },
+                    }
+                }
+
+                let mut desc = vec![];
+                let mut passed = vec![];
+                for (item, max) in vec![("maxlogins", limits.maxlogins), ("nproc", limits.nproc), ("nofile", limits.nofile)] {
+                    let (ok, value) = match parse::hard_limit(&rules, item) {
+                        Some(Some(v)) => (v <= max, v.to_string()),
+                        Some(None) => (false, "不限制".to_string()),
+                        None => (false, "未设置".to_string()),
+                    };
+                    desc.push(format!("* hard {}: {} (要求不超过 {})", item, value, max));
+                    passed.push(ok);
+                }
+
+                // limits.conf 只对经过 pam_limits 的登录会话生效
+                let pam_files = vec!["/etc/pam.d/system-auth", "/etc/pam.d/common-session", "/etc/pam.d/login", "/etc/pam.d/sshd"];
+                let pam_limits = pam_files.iter()
+                    .filter(|file| match parse::read_lines(file) {
+                        Ok(lines) => parse::pam_rules(lines).iter().any(|x| x.typ == "session" && x.module == "pam_limits.so"),
+                        Err(_) => false,
+                    })
+                    .map(|x| x.to_string())
+                    .collect::<Vec<String>>();
+                desc.push(match pam_limits.len() {
+                    0 => "pam_limits: 未在登录会话中启用, limits.conf 不生效".to_string(),
+                    _ => format!("pam_limits: {}", pam_limits.join(", ")),
+                });
+
+                // 系统服务不经过 PAM, 其默认限制由 systemd 的 DefaultLimitNPROC/DefaultLimitNOFILE 设置
+                let systemd_conf = systemd_system_conf();
+                let mut defaults = HashMap::new();
+                let mut has_systemd = false;
+                for file in systemd_conf.iter() {
+                    if let Ok(lines) = parse::read_lines(file) {
+                        has_systemd = true;
+                        for line in lines {
+                            if let Some((k, v)) = line.trim().split_once("=") {
+                                if k == "DefaultLimitNPROC" || k == "DefaultLimitNOFILE" {
+                                    defaults.insert(k.to_string(), v.trim().to_string());
+                                }
+                            }
+                        }
+                    }
+                }
+                let systemd_passed = if has_systemd {
+                    for k in vec!["DefaultLimitNPROC", "DefaultLimitNOFILE"] {
+                        desc.push(format!("systemd {}: {}", k, defaults.get(k).map(|x| x.as_str()).unwrap_or("默认")));
+                    }
+                    Some(!defaults.values().any(|x| x.split(":").any(|x| x == "infinity")))
+                } else {
+                    desc.push("未找到 /etc/systemd/system.conf".to_string());
+                    None
+                };
+
+                let row = row
+                    .status(req!("limits.maxlogins"), Some(passed[0]))
+                    .status(req!("limits.nproc"), Some(passed[1]))
+                    .status(req!("limits.nofile"), Some(passed[2]))
+                    .status(req!("limits.systemd"), systemd_passed);
+
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::SshHostKey => {
+                // 主机密钥为 /etc/ssh/ssh_host_<类型>_key, 以公钥读取类型与长度
+                let mut keys = vec![];
+                if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/ssh")) {
+                    keys = entries.filter_map(|x| x.ok())
+                        .map(|x| x.file_name().to_string_lossy().to_string())
+                        .filter(|x| x.starts_with("ssh_host_") && x.ends_with("_key.pub"))
+                        .collect::<Vec<String>>();
+                    keys.sort();
+                } else {
+                    eprintln!("cannot read /etc/ssh");
+                }
+                let mut desc = vec![];
+                let mut dsa = vec![];
+                let mut weak_rsa = vec![];
+                for key in keys.iter() {
+                    let path = util::rooted(format!("/etc/ssh/{}", key));
+                    let (bits, typ) = match util::runcmd(&format!("ssh-keygen -l -f '{}'", path.display()), None) {
+                        Ok(r) => match patterns::RE_KEYGEN_BITS.captures(r.trim()) {
+                            Some(caps) => (caps[1].parse::<u32>().unwrap_or(0), caps[2].to_uppercase()),
+                            None => continue,
+                        },
+                        Err(_) => {
+                            eprintln!("cannot run command 'ssh-keygen -l -f {}'", path.display());
+                            continue;
+                        },
+                    };
+                    desc.push(format!("{}: {} {} 位", key, typ, bits));
+                    if typ == "DSA" {
+                        dsa.push(key.to_string());
+                    } else if typ == "RSA" && bits < 2048 {
+                        weak_rsa.push(key.to_string());
+                    }
+                }
+                if keys.len() == 0 {
+                    desc.push("/etc/ssh 中没有主机密钥".to_string());
+                }
+
+                let weak_algorithms = ["ssh-rsa", "ssh-dss", "ssh-rsa-cert-v01@openssh.com", "ssh-dss-cert-v01@openssh.com"];
+                let algorithms = sshd_option("HostKeyAlgorithms");
+                // "+"、"^" 在默认列表上追加, "-" 从默认列表中去掉; 未设置时取决于 OpenSSH 版本(8.8 起不再默认启用 ssh-rsa)
+                let algorithms_passed = match algorithms.as_ref() {
+                    Some(value) if value.starts_with("-") => {
+                        desc.push(format!("HostKeyAlgorithms: {}", value));
+                        None
+                    },
+                    Some(value) => {
+                        let enabled = value.trim_start_matches(|c| c == '+' || c == '^')
+                            .split(",")
+                            .filter(|x| weak_algorithms.contains(x))
+                            .map(|x| x.to_string())
+                            .collect::<Vec<String>>();
+                        desc.push(format!("HostKeyAlgorithms: {}", value));
+                        if enabled.len() > 0 {
+                            desc.push(format!("允许 SHA-1 签名的算法: {}", enabled.join(", ")));
+                        }
+                        Some(enabled.len() == 0)
+                    },
+                    None => {
+                        desc.push("HostKeyAlgorithms: 未设置, 取决于 OpenSSH 版本的默认值".to_string());
+                        None
+                    },
+                };
+
+                if dsa.len() > 0 || weak_rsa.len() > 0 {
+                    desc.push("建议删除上述弱密钥, 使用 ssh-keygen -t ed25519 或 ssh-keygen -t rsa -b 3072 重新生成主机密钥, 并从 sshd_config 的 HostKey 中移除".to_string());
+                }
+                let row = row
+                    .status(req!("sshkey.dsa"), Some(dsa.len() == 0))
+                    .status(req!("sshkey.rsa_size"), Some(weak_rsa.len() == 0))
+                    .status(req!("sshkey.algorithms"), algorithms_passed);
+
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::PathEnv => {
+                // 普通用户的主目录, root 及服务账户的 PATH 不应包含其中的目录
+                let mut homes = vec![];
+                let mut names = HashMap::new();
+                if let Ok(lines) = parse::read_lines("/etc/passwd") {
+                    for line in lines {
+                        // 格式为 name:password:uid:gid:gecos:home:shell
+                        let items = line.trim().split(":").collect::<Vec<&str>>();
+                        if items.len() != 7 || items[0].starts_with("#") {
+                            continue;
+                        }
+                        let uid = match items[2].parse::<u32>() {
+                            Ok(uid) => uid,
+                            Err(_) => continue,
+                        };
+                        names.insert(uid, items[0].to_string());
+                        if uid >= 1000 && uid != 65534 && items[5] != "/" {
+                            homes.push((items[0].to_string(), items[5].trim_end_matches("/").to_string()));
+                        }
+                    }
+                } else {
+                    eprintln!("cannot read /etc/passwd");
+                }
+
+                // (账户, PATH 的值, 来源); root 的 PATH 取自登录、su、sudo 及 cron 的配置
+                let mut paths: Vec<(String, String, String)> = vec![];
+                let mut files = vec![
+                    "/etc/environment".to_string(),
+                    "/etc/login.defs".to_string(),
+                    "/etc/profile".to_string(),
+                    "/etc/bashrc".to_string(),
+                    "/etc/bash.bashrc".to_string(),
+                    "/etc/crontab".to_string(),
+                    "/etc/sudoers".to_string(),
+                ];
+                for dir in vec!["/etc/profile.d", "/etc/sudoers.d"] {
+                    if let Ok(entries) = std::fs::read_dir(util::rooted(dir)) {
+                        let mut confs = entries.filter_map(|x| x.ok())
+                            .map(|x| format!("{}/{}", dir, x.file_name().to_string_lossy()))
+                            .collect::<Vec<String>>();
+                        confs.sort();
+                        files.extend(confs);
+                    }
+                }
+                for dotfile in vec![".bashrc", ".bash_profile", ".bash_login", ".profile"] {
+                    files.push(format!("/root/{}", dotfile));
+                }
+                for file in files.iter() {
+                    if let Ok(lines) = parse::read_lines(file) {
+                        for line in lines {
+                            if let Some(value) = parse::path_value(&line) {
+                                paths.push(("root".to_string(), value, file.to_string()));
+                            }
+                        }
+                    }
+                }
+
+                // 运行中的 root 及服务账户(UID 小于 1000)进程实际使用的 PATH, 相同的值只记录第一个进程
+                if util::root().is_none() {
+                    let mut pids = std::fs::read_dir("/proc")
+                        .map(|x| x.filter_map(|x| x.ok()).filter_map(|x| x.file_name().to_string_lossy().parse::<u32>().ok()).collect::<Vec<u32>>())
+                        .unwrap_or(vec![]);
+                    pids.sort();
+                    for pid in pids {
+                        let uid = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()
+                            .and_then(|x| x.lines().find_map(|x| x.strip_prefix("Uid:").map(|x| x.to_string())))
+                            .and_then(|x| x.split_whitespace().next().and_then(|x| x.parse::<u32>().ok()));
+                        let uid = match uid {
+                            Some(uid) if uid < 1000 || uid == 65534 => uid,
+                            _ => continue,
+                        };
+                        let environ = match std::fs::read(format!("/proc/{}/environ", pid)) {
+                            Ok(environ) => environ,
+                            Err(_) => continue,
+                        };
+                        let value = match environ.split(|x| *x == 0).find_map(|x| x.strip_prefix(b"PATH=")) {
+                            Some(value) => String::from_utf8_lossy(value).to_string(),
+                            None => continue,
+                        };
+                        let account = names.get(&uid).cloned().unwrap_or(uid.to_string());
+                        if paths.iter().any(|x| x.0 == account && x.1 == value) {
+                            continue;
+                        }
+                        let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default();
+                        paths.push((account, value, format!("进程 {}({})", comm.trim(), pid)));
+                    }
+                }
+
+                let mut writable = vec![];
+                let mut relative = vec![];
+                let mut in_home = vec![];
+                let mut duplicates = vec![];
+                for (account, value, source) in paths.iter() {
+                    let entries = parse::path_entries(value);
+                    for (i, entry) in entries.iter().enumerate() {
+                        let home = homes.iter().find(|x| std::path::Path::new(entry).starts_with(&x.1));
+                        let (list, finding) = if entry == "." || !entry.starts_with("/") {
+                            (&mut relative, format!("{}: {} ({})", account, entry, source))
+                        } else if let Some((user, _)) = home {
+                            (&mut in_home, format!("{}: {} 位于用户 {} 的主目录 ({})", account, entry, user, source))
+                        } else if std::fs::metadata(util::rooted(entry)).map_or(false, |x| x.mode() & 0o002 != 0) {
+                            (&mut writable, format!("{}: {} ({})", account, entry, source))
+                        } else if entries[..i].contains(entry) {
+                            (&mut duplicates, format!("{}: {} ({})", account, entry, source))
+                        } else {
+                            continue;
+                        };
+                        if !list.contains(&finding) {
+                            list.push(finding);
+                        }
+                    }
+                }
+
+                let row = row
+                    .status(req!("path.writable"), Some(writable.len() == 0))
+                    .status(req!("path.dot"), Some(relative.len() == 0))
+                    .status(req!("path.home"), Some(in_home.len() == 0));
+
+                let mut desc = vec![];
+                if writable.len() > 0 {
+                    desc.push(format!("所有用户可写的目录：{}", writable.join("、")));
+                }
+                if relative.len() > 0 {
+                    desc.push(format!("当前目录或相对路径：{}", relative.join("、")));
+                }
+                if in_home.len() > 0 {
+                    desc.push(format!("普通用户主目录下的目录：{}", in_home.join("、")));
+                }
+                if duplicates.len() > 0 {
+                    desc.push(format!("重复的目录：{}", duplicates.join("、")));
+                }
+                if util::root().is_some() {
+                    desc.push("检查备用根目录时只检查配置文件, 未检查运行中进程的 PATH".to_string());
+                }
+                row.evidence(&desc.join("\n"))
+            },
+            #[cfg(target_os = "linux")]
+            GuardItem::Integrity => {
+                let baseline = match integrity::load() {
+                    Ok(Some(baseline)) => baseline,
+                    Ok(None) => {
+                        return row.status(req!("integrity.files"), None)
+                            .evidence(format!("尚未建立完整性基线({}), 以 root 运行 baseline create 建立", integrity::path().display()));
+                    },
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return row.status(req!("integrity.files"), None).evidence(format!("无法读取完整性基线: {}", e));
+                    },
+                };
+                let changes = integrity::verify(&baseline);
+                let mut desc = vec![format!("基线建立于 {}, 共 {} 个文件", baseline.created_at, baseline.files.len())];
+                if changes.changed.len() > 0 {
+                    desc.push(format!("内容变化：{}", changes.changed.join("、")));
+                }
+                if changes.missing.len() > 0 {
+                    desc.push(format!("缺失：{}", changes.missing.join("、")));
+                }
+                if changes.added.len() > 0 {
+                    desc.push(format!("新增：{}", changes.added.join("、")));
+                }
+                row.status(req!("integrity.files"), Some(changes.is_empty())).evidence(desc.join("\n"))
+            },
+            // 其余检查项依赖 Linux 特有的文件与命令, 在 macOS 上使用单独的实现
+            #[cfg(target_os = "macos")]
+            _ => macos::check(self, row),
+        }
+    }
+}
+
+/// sshd 选项的生效值, 优先取 sshd -T 的输出; 检查备用根目录或无法运行时按配置文件判断, Include 的
+/// sshd_config.d 通常位于文件开头, 按 sshd 取首次出现的值的规则先读取. 配置文件中未设置时为 None
+#[cfg(target_os = "linux")]
+fn sshd_option(keyword: &str) -> Option<String> {
+    if util::root().is_none() {
+        if let Ok(r) = util::runcmd("sshd -T", None) {
+            let prefix = format!("{} ", keyword.to_lowercase());
+            let value = r.lines()
+                .find_map(|x| x.strip_prefix(&prefix))
+                .map(|x| x.trim().to_string());
+            if value.is_some() {
+                return value;
+            }
+        }
+    }
+    let mut files = vec![];
+    if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/ssh/sshd_config.d")) {
+        files = entries.filter_map(|x| x.ok())
+            .map(|x| format!("/etc/ssh/sshd_config.d/{}", x.file_name().to_string_lossy()))
+            .filter(|x| x.ends_with(".conf"))
+            .collect::<Vec<String>>();
+        files.sort();
+    }
+    files.push("/etc/ssh/sshd_config".to_string());
+    files.iter()
+        .filter_map(|x| parse::read_lines(x).ok())
+        .flatten()
+        .find_map(|line| {
+            let items = line.split_whitespace().collect::<Vec<&str>>();
+            if items.len() == 2 && items[0].eq_ignore_ascii_case(keyword) {
+                Some(items[1].to_string())
+            } else {
+                None
+            }
+        })
+}
+
+/// 以 ss 列出监听的 TCP 端口及其所属进程(名称、PID), 无法执行 ss 时为 None
+#[cfg(target_os = "linux")]
+fn tcp_listeners() -> Option<Vec<(u16, Vec<(String, u32)>)>> {
+    let r = util::runcmd("ss -tlnp", None).ok()?;
+    let mut listeners = vec![];
+    // 格式为 State Recv-Q Send-Q Local-Address:Port Peer-Address:Port Process
+    for line in r.lines().skip(1) {
+        let items = line.split_whitespace().collect::<Vec<&str>>();
+        let port = match items.get(3).and_then(|x| x.rsplit(":").next()).map(|x| x.parse::<u16>()) {
+            Some(Ok(port)) => port,
+            _ => continue,
+        };
+        let processes = items.get(5).map_or(vec![], |x| {
+            patterns::RE_SS_PID.captures_iter(x)
+                .filter_map(|caps| caps[2].parse::<u32>().ok().map(|pid| (caps[1].to_string(), pid)))
+                .collect()
+        });
+        listeners.push((port, processes));
+    }
+    Some(listeners)
+}
+
+// 登录警示信息是否包含策略要求的关键字之一
+#[cfg(target_os = "linux")]
+fn banner_warns(banner: &str) -> bool {
+    let banner = banner.to_lowercase();
+    policy::get().host.banner_keywords.iter().any(|x| banner.contains(&x.to_lowercase()))
+}
+
+// 读取一个 FTP 应答, 返回应答码及文本; 多行应答以 "220-" 开始, 以 "220 " 结束
+#[cfg(target_os = "linux")]
+fn ftp_reply(reader: &mut impl BufRead) -> Result<(String, String), String> {
+    let mut lines = vec![];
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err("connection closed".to_string()),
+            Ok(_) => {},
+            Err(e) => return Err(format!("{:?}", e)),
+        }
+        let line = line.trim_end().to_string();
+        let done = line.len() >= 4 && line.as_bytes()[3] == b' ' && line[..3].chars().all(|x| x.is_ascii_digit());
+        lines.push(line);
+        if done {
+            break;
+        }
+    }
+    let code = lines.last().map_or("", |x| &x[..3]).to_string();
+    let text = lines.iter().map(|x| x.get(4..).unwrap_or("")).collect::<Vec<&str>>().join("\n");
+    Ok((code, text))
+}
+
+// 连接本机 FTP, 返回欢迎信息及能否匿名登录
+#[cfg(target_os = "linux")]
+fn ftp_probe(port: u16) -> Result<(String, bool), String> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_secs(3)).map_err(|e| format!("{:?}", e))?;
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(3)));
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| format!("{:?}", e))?);
+    let (_, banner) = ftp_reply(&mut reader)?;
+    stream.write_all(b"USER anonymous\r\n").map_err(|e| format!("{:?}", e))?;
+    let (mut code, _) = ftp_reply(&mut reader)?;
+    if code == "331" {
+        stream.write_all(b"PASS anonymous@\r\n").map_err(|e| format!("{:?}", e))?;
+        code = ftp_reply(&mut reader)?.0;
+    }
+    let _ = stream.write_all(b"QUIT\r\n");
+    Ok((banner, code == "230"))
+}
+
+/// 当前配置下扫描会用到的网络功能, 用于在界面上提示
+pub fn network_features() -> Vec<String> {
+    let mut features = vec![];
+    if config::get().network.offline {
+        features.push("离线模式".to_string());
+    } else {
+        features.push("云元数据服务探测(169.254.169.254, 不经过代理)".to_string());
+    }
+    if policy::get().ssh.weak_credential_test {
+        features.push("SSH 默认口令登录测试(仅本机)".to_string());
+    }
+    if policy::get().host.legacy_services.iter().any(|x| x == "ftp") {
+        features.push("FTP 匿名登录测试(仅本机)".to_string());
+    }
+    if !config::get().network.offline && config::get().upload.url.len() > 0 {
+        features.push(format!("导出后上传到 {}", config::get().upload.url));
+    }
+    if !config::get().network.offline && config::get().mail.url.len() > 0 {
+        features.push(format!("通过 {} 发送合规摘要", config::get().mail.url));
+    }
+    if !config::get().network.offline && config::get().network.proxy.len() > 0 {
+        features.push(format!("WebDAV 上传及邮件经代理 {}", config::get().network.proxy));
+    }
+    features
+}
+
+// systemd 的 system.conf 及按文件名排序的 system.conf.d/*.conf, 后读取的设置覆盖之前的
+#[cfg(target_os = "linux")]
+fn systemd_system_conf() -> Vec<String> {
+    let mut files = vec!["/etc/systemd/system.conf".to_string()];
+    if let Ok(entries) = std::fs::read_dir(util::rooted("/etc/systemd/system.conf.d")) {
+        let mut confs = entries.filter_map(|x| x.ok())
+            .map(|x| format!("/etc/systemd/system.conf.d/{}", x.file_name().to_string_lossy()))
+            .filter(|x| x.ends_with(".conf"))
+            .collect::<Vec<String>>();
+        confs.sort();
+        files.extend(confs);
+    }
+    files
+}
+
+// 尝试使用口令登录本机 SSH, 认证失败时返回 false, 无法连接或握手失败时返回错误
+#[cfg(target_os = "linux")]
+fn ssh_login(port: u16, user: &str, password: &str) -> Result<bool, String> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let tcp = TcpStream::connect_timeout(&addr, Duration::from_secs(3))
+        .map_err(|e| format!("cannot connect to {}: {:?}", addr, e))?;
+    let mut session = ssh2::Session::new().map_err(|e| format!("cannot create ssh session: {:?}", e))?;
+    session.set_tcp_stream(tcp);
+    session.set_timeout(5000);
+    session.handshake().map_err(|e| format!("ssh handshake failed: {:?}", e))?;
+    Ok(session.userauth_password(user, password).is_ok() && session.authenticated())
 }
@@ -0,0 +1,204 @@
+//! 终端界面, 用于通过 SSH 登录检查主机等没有图形界面的场景
+
+use std::io;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::execute;
+use errlog::{elog, AnyResult, AnyContext};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{Frame, Terminal};
+
+use crate::export;
+use crate::report::ScanReport;
+use crate::schema::Status;
+use crate::store;
+use crate::sysguard::GuardCell;
+use crate::util;
+
+struct App {
+    report: ScanReport,
+    list: ListState,
+    // 是否展开显示当前检查项的备注(证据)
+    expanded: bool,
+    // 正在输入导出文件名时为 Some
+    export_path: Option<String>,
+    // 是否在右侧显示已执行命令的日志
+    show_cmdlog: bool,
+    status: String,
+}
+
+impl App {
+    fn new(report: ScanReport) -> Self {
+        let mut list = ListState::default();
+        list.select(Some(0));
+        let status = if report.meta.missing.len() > 0 {
+            format!("缺少依赖: {}", report.meta.missing.join("; "))
+        } else {
+            "↑/↓ 选择  Enter 展开备注  l 命令日志  e 导出  q 退出".to_string()
+        };
+        App {
+            report,
+            list,
+            expanded: false,
+            export_path: None,
+            show_cmdlog: util::verbose(),
+            status,
+        }
+    }
+
+    fn selected(&self) -> usize {
+        self.list.selected().unwrap_or(0)
+    }
+
+    fn select(&mut self, offset: isize) {
+        let total = self.report.cells.len() as isize;
+        let idx = (self.selected() as isize + offset).rem_euclid(total);
+        self.list.select(Some(idx as usize));
+    }
+}
+
+fn summary(cell: &GuardCell) -> String {
+    let statuses = cell.statuses();
+    let count = |status: Status| statuses.iter().filter(|x| x.1 == status).count();
+    let partial = match count(Status::Partial) {
+        0 => "".to_string(),
+        n => format!(" ◐{}", n),
+    };
+    format!("{} (✓{} ✗{}{})", cell.column('A').join(" "), count(Status::Pass), count(Status::Fail), partial)
+}
+
+fn draw(f: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(f.size());
+    let constraints = if app.show_cmdlog {
+        vec![Constraint::Percentage(30), Constraint::Percentage(40), Constraint::Percentage(30)]
+    } else {
+        vec![Constraint::Percentage(35), Constraint::Percentage(65)]
+    };
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(rows[0]);
+
+    let items = app.report.cells.iter().map(|x| ListItem::new(summary(x))).collect::<Vec<ListItem>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("检查项"))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, cols[0], &mut app.list);
+
+    let cell = &app.report.cells[app.selected()];
+    let mut lines = vec![];
+    for text in cell.column('B') {
+        lines.extend(text.lines().map(|x| Line::from(x.to_string())));
+    }
+    let evidence = cell.column('C');
+    if app.expanded && evidence.len() > 0 {
+        lines.push(Line::from(""));
+        lines.push(Line::from("备注:"));
+        for text in evidence {
+            lines.extend(text.lines().map(|x| Line::from(x.to_string())));
+        }
+        if cell.sources.len() > 0 {
+            lines.push(Line::from(format!("来源: {}", cell.sources.join(", "))));
+        }
+    } else if evidence.len() > 0 {
+        lines.push(Line::from(""));
+        lines.push(Line::from("(按 Enter 展开备注)"));
+    }
+    let detail = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(cell.column('A').join(" ")))
+        .wrap(Wrap { trim: false });
+    f.render_widget(detail, cols[1]);
+
+    if app.show_cmdlog {
+        // 只显示最近的命令, 使日志始终停留在末尾
+        let log = util::cmdlog();
+        let height = cols[2].height.saturating_sub(2) as usize;
+        let lines = log.iter()
+            .skip(log.len().saturating_sub(height))
+            .map(|x| Line::from(x.to_string()))
+            .collect::<Vec<Line>>();
+        let cmdlog = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(format!("命令日志 ({})", log.len())));
+        f.render_widget(cmdlog, cols[2]);
+    }
+
+    let status = match &app.export_path {
+        Some(path) => format!("导出到: {}▏(Enter 确认, Esc 取消)", path),
+        None => app.status.to_string(),
+    };
+    let status = Paragraph::new(status).block(Block::default().borders(Borders::ALL));
+    f.render_widget(status, rows[1]);
+}
+
+fn event_loop<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> AnyResult<()> {
+    loop {
+        terminal.draw(|f| draw(f, app)).context(elog!("failed to draw terminal"))?;
+
+        let key = match event::read().context(elog!("failed to read terminal event"))? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        if let Some(path) = app.export_path.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    app.status = match export::saveas(&app.report, path.to_string()) {
+                        Ok(_) if app.report.changed_files().len() > 0 => format!("已导出到 {}; 部分配置文件在扫描后有变化, 建议重新扫描", path),
+                        Ok(_) => format!("已导出到 {}", path),
+                        Err(e) => format!("导出失败: {}", e),
+                    };
+                    app.export_path = None;
+                },
+                KeyCode::Esc => app.export_path = None,
+                KeyCode::Backspace => {
+                    path.pop();
+                },
+                KeyCode::Char(c) => path.push(c),
+                _ => {},
+            }
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => app.select(-1),
+            KeyCode::Down | KeyCode::Char('j') => app.select(1),
+            KeyCode::Enter => app.expanded = !app.expanded,
+            KeyCode::Char('l') => app.show_cmdlog = !app.show_cmdlog,
+            KeyCode::Char('e') => app.export_path = Some("report.xlsx".to_string()),
+            _ => {},
+        }
+    }
+}
+
+pub fn run(demo: bool) -> AnyResult<()> {
+    let report = if demo {
+        ScanReport::demo()
+    } else {
+        println!("scanning ...");
+        let report = ScanReport::scan();
+        store::record(&report);
+        report
+    };
+    let mut app = App::new(report);
+
+    enable_raw_mode().context(elog!("failed to enable raw mode"))?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen).context(elog!("failed to enter alternate screen"))?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout)).context(elog!("failed to create terminal"))?;
+
+    let r = event_loop(&mut terminal, &mut app);
+
+    let _ = disable_raw_mode();
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = terminal.show_cursor();
+    r
+}
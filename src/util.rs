@@ -1,7 +1,41 @@
 use errlog::{elog, AnyResult, AnyContext};
+use once_cell::sync::{Lazy, OnceCell};
 
-use std::process::Command;
+use std::cell::RefCell;
+use std::process::{Command, Output};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use crate::config;
+
+// 详细模式下, 每条命令执行完成后立即输出到终端
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static CMDLOG: Lazy<Mutex<Vec<CmdRecord>>> = Lazy::new(|| Mutex::new(vec![]));
+// 命令行指定的备用根目录(挂载的镜像或磁盘), 需在扫描前设置
+static ROOT: OnceCell<PathBuf> = OnceCell::new();
+
+thread_local! {
+    // 当前线程中正在执行的检查项读取的文件及执行的命令, 不在收集时为 None
+    static SOURCES: RefCell<Option<Vec<String>>> = RefCell::new(None);
+}
+
+/// 一条已执行命令的记录
+#[derive(Clone, Debug)]
+pub struct CmdRecord {
+    pub cmd: String,
+    pub millis: u128,
+    // 退出状态, 例如 "exit 0"、"exit 1"、"killed"、"failed to run"
+    pub status: String,
+}
+
+impl CmdRecord {
+    pub fn to_string(&self) -> String {
+        format!("[{:>6}ms] {:<13} {}", self.millis, self.status, self.cmd)
+    }
+}
 
 struct ArgParser<'a> {
     buf: &'a str,
@@ -72,20 +106,107 @@ impl<'a> Iterator for ArgParser<'a> {
     }
 }
 
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+pub fn set_root(root: PathBuf) {
+    let _ = ROOT.set(root);
+}
+
+/// 备用根目录, 检查本机时为 None
+pub fn root() -> Option<&'static Path> {
+    ROOT.get().map(|x| x.as_path())
+}
+
+/// 将绝对路径映射到备用根目录下; 未设置备用根目录、相对路径或已在其下的路径保持不变
+pub fn rooted<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    match (root(), path.strip_prefix("/")) {
+        (Some(root), Ok(relative)) if !path.starts_with(root) => root.join(relative),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 开始收集当前线程的证据来源
+pub fn begin_sources() {
+    SOURCES.with(|x| *x.borrow_mut() = Some(vec![]));
+}
+
+/// 结束收集, 返回按首次出现的顺序排列且不重复的证据来源
+pub fn end_sources() -> Vec<String> {
+    SOURCES.with(|x| x.borrow_mut().take()).unwrap_or_default()
+}
+
+/// 记录一个证据来源(读取的文件或执行的命令), 未在收集时忽略
+pub fn add_source(source: &str) {
+    SOURCES.with(|x| {
+        if let Some(sources) = x.borrow_mut().as_mut() {
+            if !sources.iter().any(|x| x == source) {
+                sources.push(source.to_string());
+            }
+        }
+    });
+}
+
+/// 本次运行中已执行的所有命令
+pub fn cmdlog() -> Vec<CmdRecord> {
+    match CMDLOG.lock() {
+        Ok(log) => log.clone(),
+        Err(_) => vec![],
+    }
+}
+
+fn record(cmd: &str, started: Instant, status: String) {
+    let record = CmdRecord {
+        cmd: cmd.to_string(),
+        millis: started.elapsed().as_millis(),
+        status,
+    };
+    if verbose() {
+        eprintln!("{}", record.to_string());
+    }
+    if let Ok(mut log) = CMDLOG.lock() {
+        log.push(record);
+    }
+}
+
 pub fn runcmd(cmd: &str, envs: Option<Vec<(String, String)>>) -> AnyResult<String> {
+    let started = Instant::now();
+    let cmdline = cmd;
     let argparser = ArgParser::new(cmd);
     let cmd: Vec<String> = argparser.into_iter().collect();
-    let envs: HashMap<String, String> = if let Some(envs) = envs {
+    let mut envs: HashMap<String, String> = if let Some(envs) = envs {
         envs.into_iter().collect()
     } else {
         HashMap::new()
     };
+    // 默认以 C 语言环境执行, 证据文本及解析不随主机的语言设置变化
+    if !config::get().evidence.host_locale && !envs.contains_key("LC_ALL") {
+        envs.insert("LC_ALL".to_string(), "C".to_string());
+    }
     let outbuf = match cmd.len() {
         0 => return Err(elog!("Empty command")),
         1 => Command::new(&cmd[0]).envs(&envs).output(),
         _ => Command::new(&cmd[0]).envs(&envs).args(&cmd[1..]).output(),
     };
-    let outbuf = outbuf.context(elog!("failed to run command {:?}", cmd))?;
+    let outbuf = match outbuf {
+        Ok(outbuf) => outbuf,
+        Err(e) => {
+            record(cmdline, started, "failed to run".to_string());
+            return Err(e).context(elog!("failed to run command {:?}", cmd));
+        },
+    };
+    let status = match outbuf.status.code() {
+        Some(code) => format!("exit {}", code),
+        None => "killed".to_string(),
+    };
+    record(cmdline, started, status);
+    add_source(cmdline);
     if !outbuf.status.success() {
         let err = match std::str::from_utf8(&outbuf.stderr[..]) {
             Ok(e) => e.to_string(),
@@ -98,6 +219,97 @@ pub fn runcmd(cmd: &str, envs: Option<Vec<(String, String)>>) -> AnyResult<Strin
     Ok(output.to_string())
 }
 
+/// 执行已构造好参数的命令并返回其完整输出, 与 runcmd 一样记录到命令执行记录中; 用于需要区分退出码或参数中
+/// 可能含有引号的命令
+pub fn output(cmd: &mut Command) -> std::io::Result<Output> {
+    let started = Instant::now();
+    let cmdline = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|x| {
+            let x = x.to_string_lossy();
+            if x.len() == 0 || x.contains(char::is_whitespace) { format!("'{}'", x) } else { x.to_string() }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    if !config::get().evidence.host_locale && !cmd.get_envs().any(|(k, _)| k == "LC_ALL") {
+        cmd.env("LC_ALL", "C");
+    }
+    let output = cmd.output();
+    let status = match output.as_ref().map(|x| x.status.code()) {
+        Ok(Some(code)) => format!("exit {}", code),
+        Ok(None) => "killed".to_string(),
+        Err(_) => "failed to run".to_string(),
+    };
+    record(&cmdline, started, status);
+    if output.is_ok() {
+        add_source(&cmdline);
+    }
+    output
+}
+
+/// 文件带时区的修改时间, 格式与报告中的扫描时间相同; 路径作为单独的参数传给 date, 可以含有引号
+pub fn mtime(path: &Path) -> Option<String> {
+    match output(Command::new("date").arg("-r").arg(path).arg("+%Y-%m-%d %H:%M:%S %z")) {
+        Ok(r) if r.status.success() => Some(String::from_utf8_lossy(&r.stdout).trim().to_string()),
+        _ => None,
+    }
+}
+
+/// 文本超过 `max` 个字符时返回截断后的前 `max` 个字符, 否则返回 None
+pub fn truncate(text: &str, max: usize) -> Option<String> {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => Some(text[..idx].to_string()),
+        None => None,
+    }
+}
+
+/// 解码 URI 中的百分号编码(例如 "%20"), 无效的编码保持原样
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|x| u8::from_str_radix(x, 16).ok())
+        } else {
+            None
+        };
+        match hex {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn home_dir() -> PathBuf {
+    match std::env::var("HOME") {
+        Ok(home) if home.len() > 0 => PathBuf::from(home),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[test]
+fn test_truncate() {
+    assert_eq!(None, truncate("abc", 3));
+    assert_eq!(Some("ab".to_string()), truncate("abc", 2));
+    assert_eq!(Some("关闭".to_string()), truncate("关闭445", 2));
+}
+
+#[test]
+fn test_percent_decode() {
+    assert_eq!("/tmp/my policy.toml", percent_decode("/tmp/my%20policy.toml"));
+    assert_eq!("/tmp/策略.toml", percent_decode("/tmp/%E7%AD%96%E7%95%A5.toml"));
+    assert_eq!("100%", percent_decode("100%"));
+    assert_eq!("%zz", percent_decode("%zz"));
+}
+
 #[test]
 fn test_argparser() {
     let cmd = "a bc def";
@@ -122,3 +334,13 @@ fn test_argparser() {
     assert_eq!(Some("pqr   st".into()), argparser.next());
     assert_eq!(Some(" x y z  ".into()), argparser.next());
 }
+
+#[test]
+fn test_mtime() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("it's.xlsx");
+    assert_eq!(None, mtime(&path));
+    std::fs::write(&path, "").unwrap();
+    let text = mtime(&path).unwrap();
+    assert_eq!(25, text.len(), "{}", text);
+}
@@ -0,0 +1,169 @@
+//! 监视与安全要求相关的配置文件, 在两次定时扫描之间发现合规状态的恶化
+//!
+//! 以 inotify 监视账户、sshd、sudo 及 PAM 的配置, 文件变化后只重新执行读取该文件的检查项,
+//! 原先符合的要求变为不符合时输出日志并发送桌面通知.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use inotify::{Inotify, WatchMask};
+
+use crate::check;
+use crate::i18n;
+use crate::report;
+use crate::schema::Status;
+
+// 监视的文件或目录及其变化时重新执行的检查项; 不包括会尝试登录的 SSH 默认口令检查
+const WATCHES: &[(&str, &[&str])] = &[
+    ("/etc/passwd", &["acct", "home"]),
+    ("/etc/login.defs", &["acct", "passwd"]),
+    ("/etc/ssh/sshd_config", &["session", "audit", "x11", "sshkey"]),
+    ("/etc/ssh/sshd_config.d", &["sshkey"]),
+    ("/etc/sudoers", &["sudo"]),
+    ("/etc/sudoers.d", &["sudo"]),
+    ("/etc/pam.d", &["acct", "passwd", "console", "pam", "limits"]),
+];
+
+// 编辑器及 vipw、visudo 等工具多以改名的方式替换文件, 因此监视所在目录而不是文件本身
+fn watched_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    for (path, _) in WATCHES {
+        let path = Path::new(path);
+        let dir = if path.is_dir() {
+            path.to_path_buf()
+        } else {
+            path.parent().unwrap_or(Path::new("/")).to_path_buf()
+        };
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// 变化的文件所影响的检查项, 按 `WATCHES` 中的顺序排列且不重复
+fn affected(paths: &[PathBuf]) -> Vec<&'static str> {
+    let mut ids = vec![];
+    for (watched, checks) in WATCHES {
+        if !paths.iter().any(|x| x.starts_with(watched)) {
+            continue;
+        }
+        for id in checks.iter() {
+            if !ids.contains(id) {
+                ids.push(*id);
+            }
+        }
+    }
+    ids
+}
+
+/// 原先符合(或自动检查的部分符合)而现在不符合的要求
+fn regressions(last: &HashMap<String, Status>, current: &[(String, Status)]) -> Vec<String> {
+    current.iter()
+        .filter(|(id, status)| {
+            *status == Status::Fail && matches!(last.get(id), Some(Status::Pass) | Some(Status::Partial))
+        })
+        .map(|(id, _)| id.to_string())
+        .collect()
+}
+
+// 重新执行检查项, 更新各要求的状态并返回恶化的要求
+fn recheck(ids: &[&str], last: &mut HashMap<String, Status>) -> Vec<String> {
+    let mut regressed = vec![];
+    for id in ids {
+        let item = match check::find(id) {
+            Some(item) => item,
+            None => continue,
+        };
+        let statuses = check::run(item.as_ref()).statuses();
+        regressed.extend(regressions(last, &statuses));
+        last.extend(statuses);
+    }
+    regressed
+}
+
+fn alert(changed: &[PathBuf], regressed: &[String]) {
+    let files = changed.iter().map(|x| x.display().to_string()).collect::<Vec<String>>().join(", ");
+    let titles = regressed.iter().map(|x| i18n::tr(x)).collect::<Vec<&str>>();
+    for (id, title) in regressed.iter().zip(titles.iter()) {
+        println!("{} 不符合: {} {} (变化的文件: {})", report::now(), id, title, files);
+    }
+    let body = format!("{} 变化后 {} 项安全要求不再符合:\n{}", files, titles.len(), titles.join("\n"));
+    if let Err(e) = notify_rust::Notification::new().appname("SH-SDS").summary("安全配置发生变化").body(&body).show() {
+        eprintln!("cannot show notification: {:?}", e);
+    }
+}
+
+/// 监视配置文件直到进程退出, 启动时先执行一遍相关检查项作为比较的基准
+pub fn run() -> Result<(), String> {
+    let mut inotify = Inotify::init().map_err(|e| format!("cannot initialize inotify: {:?}", e))?;
+    let mask = WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE | WatchMask::DELETE | WatchMask::ATTRIB;
+    let mut dirs = vec![];
+    for dir in watched_dirs() {
+        match inotify.add_watch(&dir, mask) {
+            Ok(wd) => dirs.push((wd, dir)),
+            Err(e) => eprintln!("cannot watch {}: {:?}", dir.display(), e),
+        }
+    }
+    if dirs.len() == 0 {
+        return Err("没有可以监视的配置文件".to_string());
+    }
+
+    let mut last = HashMap::new();
+    let all = WATCHES.iter().map(|x| PathBuf::from(x.0)).collect::<Vec<PathBuf>>();
+    recheck(&affected(&all), &mut last);
+    println!("{} 开始监视 {}", report::now(), dirs.iter().map(|x| x.1.display().to_string()).collect::<Vec<String>>().join(", "));
+
+    let mut buffer = [0; 4096];
+    loop {
+        let mut changed = vec![];
+        let mut collect = |name: Option<&OsStr>, dir: Option<&PathBuf>| {
+            if let (Some(name), Some(dir)) = (name, dir) {
+                let path = dir.join(name);
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        };
+        let events = inotify.read_events_blocking(&mut buffer).map_err(|e| format!("cannot read inotify events: {:?}", e))?;
+        for event in events {
+            collect(event.name, dirs.iter().find(|x| x.0 == event.wd).map(|x| &x.1));
+        }
+        // 保存文件时通常连续产生多个事件, 稍等片刻后一并处理
+        std::thread::sleep(Duration::from_secs(1));
+        let events = inotify.read_events(&mut buffer).map_err(|e| format!("cannot read inotify events: {:?}", e))?;
+        for event in events {
+            collect(event.name, dirs.iter().find(|x| x.0 == event.wd).map(|x| &x.1));
+        }
+
+        let ids = affected(&changed);
+        if ids.len() == 0 {
+            continue;
+        }
+        let regressed = recheck(&ids, &mut last);
+        if regressed.len() > 0 {
+            alert(&changed, &regressed);
+        }
+    }
+}
+
+#[test]
+fn test_watch() {
+    assert_eq!(vec!["sudo"], affected(&[PathBuf::from("/etc/sudoers.d/90-cloud-init-users")]));
+    assert_eq!(vec!["acct", "home"], affected(&[PathBuf::from("/etc/passwd")]));
+    assert_eq!(0, affected(&[PathBuf::from("/etc/passwd-"), PathBuf::from("/etc/hosts")]).len());
+
+    let last = vec![("sudo.syslog", Status::Pass), ("reboot.required", Status::Fail), ("acct.expired", Status::Partial)]
+        .into_iter()
+        .map(|(id, status)| (id.to_string(), status))
+        .collect::<HashMap<String, Status>>();
+    let current = vec![
+        ("sudo.syslog".to_string(), Status::Fail),
+        ("reboot.required".to_string(), Status::Fail),
+        ("acct.expired".to_string(), Status::Fail),
+        ("acct.umask".to_string(), Status::Fail),
+    ];
+    assert_eq!(vec!["sudo.syslog", "acct.expired"], regressions(&last, &current));
+}